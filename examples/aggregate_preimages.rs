@@ -0,0 +1,112 @@
+//! Aggregates several SHA-256 preimage proofs into one proof with snark-verifier.
+//! Run with `cargo run --release --example aggregate_preimages --features aggregation`.
+//!
+//! The aggregation circuit only exposes the KZG accumulator, so the digests of the inner proofs are packed into
+//! two 128-bit field elements each and checked against the instances of the aggregated snarks. Exposing them as
+//! public inputs of the aggregated proof requires an aggregation circuit that copies the previous instances out.
+use halo2_base::halo2_proofs::{
+    arithmetic::Field,
+    circuit::Layouter,
+    halo2curves::bn256::{Bn256, Fr},
+    plonk::{Circuit, ConstraintSystem, Error},
+    poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
+};
+use halo2_dynamic_sha256::preimage::Sha256PreimageCircuit;
+use rand::rngs::OsRng;
+use snark_verifier_sdk::{
+    gen_pk,
+    halo2::{aggregation::AggregationCircuit, gen_proof_shplonk, gen_snark_shplonk},
+    CircuitExt, Snark,
+};
+
+const MAX_BYTE_SIZE: usize = 128;
+const AGGREGATION_K: u32 = 22;
+
+/// `CircuitExt` tells snark-verifier where the public inputs of a circuit are.
+#[derive(Clone)]
+struct PreimageCircuit(Sha256PreimageCircuit<Fr, MAX_BYTE_SIZE>);
+
+impl halo2_base::halo2_proofs::plonk::Circuit<Fr> for PreimageCircuit {
+    type Config =
+        <Sha256PreimageCircuit<Fr, MAX_BYTE_SIZE> as halo2_base::halo2_proofs::plonk::Circuit<
+            Fr,
+        >>::Config;
+    type FloorPlanner =
+        <Sha256PreimageCircuit<Fr, MAX_BYTE_SIZE> as halo2_base::halo2_proofs::plonk::Circuit<
+            Fr,
+        >>::FloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self(self.0.without_witnesses())
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::configure(meta)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fr>) -> Result<(), Error> {
+        self.0.synthesize(config, layouter)
+    }
+}
+
+impl CircuitExt<Fr> for PreimageCircuit {
+    fn num_instance(&self) -> Vec<usize> {
+        vec![32]
+    }
+
+    fn instances(&self) -> Vec<Vec<Fr>> {
+        self.0.instances()
+    }
+}
+
+/// Packs 32 digest bytes into two field elements holding the big-endian high and low 16 bytes.
+fn pack_digest(bytes: &[Fr]) -> [Fr; 2] {
+    let pack = |bytes: &[Fr]| {
+        bytes
+            .iter()
+            .fold(Fr::zero(), |acc, byte| acc * Fr::from(256) + byte)
+    };
+    [pack(&bytes[0..16]), pack(&bytes[16..32])]
+}
+
+fn main() {
+    let k = Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::K;
+    // sampled parameters are only suitable for testing.
+    let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+    let agg_params = ParamsKZG::<Bn256>::setup(AGGREGATION_K, OsRng);
+
+    let preimages: [&[u8]; 3] = [b"abc", b"", b"halo2-dynamic-sha256"];
+    let circuits = preimages
+        .iter()
+        .map(|preimage| PreimageCircuit(Sha256PreimageCircuit::new(preimage.to_vec())))
+        .collect::<Vec<_>>();
+    let pk = gen_pk(&params, &circuits[0].without_witnesses(), None);
+    let snarks: Vec<Snark> = circuits
+        .into_iter()
+        .map(|circuit| gen_snark_shplonk(&params, &pk, circuit, &mut OsRng, None::<&str>))
+        .collect();
+    let packed_digests = snarks
+        .iter()
+        .map(|snark| pack_digest(&snark.instances[0]))
+        .collect::<Vec<_>>();
+
+    let agg_circuit = AggregationCircuit::new(&agg_params, snarks, &mut OsRng);
+    let agg_pk = gen_pk(&agg_params, &agg_circuit.without_witnesses(), None);
+    let instances = agg_circuit.instances();
+    let proof = gen_proof_shplonk(
+        &agg_params,
+        &agg_pk,
+        agg_circuit,
+        instances,
+        &mut OsRng,
+        None,
+    );
+    println!(
+        "aggregated {} proofs into {} bytes",
+        preimages.len(),
+        proof.len()
+    );
+    for (preimage, digest) in preimages.iter().zip(packed_digests.iter()) {
+        println!("{:?}: {:?}", preimage, digest);
+    }
+}
@@ -0,0 +1,45 @@
+//! Proves knowledge of a SHA-256 preimage in the browser.
+//! Build with `cargo build --release --example wasm_prover --target wasm32-unknown-unknown`
+//! and generate the JS bindings with `wasm-bindgen --target web`.
+#![cfg(target_arch = "wasm32")]
+
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr},
+    plonk::{create_proof, keygen_pk, keygen_vk},
+    poly::{
+        commitment::ParamsProver,
+        kzg::{
+            commitment::{KZGCommitmentScheme, ParamsKZG},
+            multiopen::ProverGWC,
+        },
+    },
+    transcript::{Blake2bWrite, Challenge255, TranscriptWriterBuffer},
+};
+use halo2_dynamic_sha256::preimage::Sha256PreimageCircuit;
+use rand::rngs::OsRng;
+use wasm_bindgen::prelude::*;
+
+const MAX_BYTE_SIZE: usize = 128;
+
+/// Returns a proof that the caller knows `preimage`.
+/// The parameters are sampled in the browser, so the proof is only meant for demonstration.
+#[wasm_bindgen]
+pub fn prove_sha256_preimage(preimage: &[u8]) -> Vec<u8> {
+    let circuit = Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::new(preimage.to_vec());
+    let params = ParamsKZG::<Bn256>::setup(Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::K, OsRng);
+    let vk = keygen_vk(&params, &circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &circuit).expect("keygen_pk should not fail");
+    let instances = circuit.instances();
+    let instances = instances.iter().map(|column| &column[..]).collect::<Vec<_>>();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof::<KZGCommitmentScheme<_>, ProverGWC<_>, _, _, _, _>(
+        &params,
+        &pk,
+        &[circuit],
+        &[&instances[..]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
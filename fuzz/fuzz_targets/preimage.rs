@@ -0,0 +1,22 @@
+//! Hashes arbitrary messages of up to `MAX_BYTE_SIZE` bytes with `Sha256PreimageCircuit` under `MockProver`.
+//! The digest is the public input of the circuit, so a satisfied circuit also means that the digest is correct.
+//! Run with `cargo fuzz run preimage` from the repository root.
+#![no_main]
+
+use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+use halo2_dynamic_sha256::preimage::Sha256PreimageCircuit;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_BYTE_SIZE: usize = 183;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() > MAX_BYTE_SIZE {
+        return;
+    }
+    let circuit = Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::new(data.to_vec());
+    let instances = circuit.instances();
+    let prover =
+        MockProver::run(Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::K, &circuit, instances)
+            .unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+});
@@ -0,0 +1,357 @@
+use alloc::{format, vec, vec::Vec};
+use core::marker::PhantomData;
+
+use crate::{AssignedHashResult, Sha256DynamicConfig};
+use halo2_base::halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{Advice, Column, ConstraintSystem, Error, Selector, TableColumn},
+    poly::Rotation,
+};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_PAD: u8 = b'=';
+
+/// Decodes assigned base64 characters (standard alphabet) into assigned bytes.
+/// Every character is looked up in a `(tag, char, sextet)` table, so characters outside the alphabet are rejected.
+/// The padding character `=` decodes to a zero sextet and is only accepted as the last one or two characters of the
+/// input, which then decodes to one or two bytes fewer. The bits of the last sextet that the padding drops must be
+/// zero, as in the canonical encoding of RFC 4648, so that the decoded data has a single encoding.
+#[derive(Debug, Clone)]
+pub struct Base64Config<F: PrimeField> {
+    q_lookup: Selector,
+    chars: Column<Advice>,
+    sextets: Column<Advice>,
+    table_tag: TableColumn,
+    table_char: TableColumn,
+    table_sextet: TableColumn,
+    row_offset: usize,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField> Base64Config<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let q_lookup = meta.complex_selector();
+        let chars = meta.advice_column();
+        meta.enable_equality(chars);
+        let sextets = meta.advice_column();
+        meta.enable_equality(sextets);
+        let table_tag = meta.lookup_table_column();
+        let table_char = meta.lookup_table_column();
+        let table_sextet = meta.lookup_table_column();
+        meta.lookup("base64 lookup", |meta| {
+            let q = meta.query_selector(q_lookup);
+            let char = meta.query_advice(chars, Rotation::cur());
+            let sextet = meta.query_advice(sextets, Rotation::cur());
+            vec![
+                (q.clone(), table_tag),
+                (q.clone() * char, table_char),
+                (q * sextet, table_sextet),
+            ]
+        });
+        Self {
+            q_lookup,
+            chars,
+            sextets,
+            table_tag,
+            table_char,
+            table_sextet,
+            row_offset: 0,
+            _f: PhantomData,
+        }
+    }
+
+    /// Decodes `chars` (a multiple of 4 characters) into `3 * chars.len() / 4` range-checked bytes and returns them
+    /// with the decoded length, which is one or two less than the number of bytes when `chars` ends with padding.
+    /// The bytes from the decoded length on are not part of the decoded data.
+    pub fn decode<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        range: &RangeConfig<F>,
+        chars: &[AssignedValue<'a, F>],
+    ) -> Result<(Vec<AssignedValue<'a, F>>, AssignedValue<'a, F>), Error> {
+        assert_eq!(chars.len() % 4, 0);
+        let gate = range.gate();
+        let sextets = chars
+            .iter()
+            .map(|char| self.char_to_sextet(ctx, gate, char))
+            .collect::<Result<Vec<AssignedValue<F>>, Error>>()?;
+        let mut bytes = vec![];
+        for quad in sextets.chunks(4) {
+            let composed = gate.inner_product(
+                ctx,
+                quad.iter().map(QuantumCell::Existing),
+                (0..4).map(|idx| QuantumCell::Constant(F::from(1u64 << (6 * (3 - idx))))),
+            );
+            let composed_val = composed.value().map(|v| v.get_lower_32());
+            let decoded = (0..3)
+                .map(|idx| {
+                    let byte = gate.load_witness(
+                        ctx,
+                        composed_val.map(|v| F::from(((v >> (8 * (2 - idx))) & 0xff) as u64)),
+                    );
+                    range.range_check(ctx, &byte, 8);
+                    byte
+                })
+                .collect_vec();
+            let recomposed = gate.inner_product(
+                ctx,
+                decoded.iter().map(QuantumCell::Existing),
+                (0..3).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (2 - idx))))),
+            );
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(&composed),
+                QuantumCell::Existing(&recomposed),
+            );
+            bytes.extend(decoded);
+        }
+        let num_pad = self.constrain_padding(ctx, gate, chars, &bytes);
+        let len = gate.sub(
+            ctx,
+            QuantumCell::Constant(F::from(bytes.len() as u64)),
+            QuantumCell::Existing(&num_pad),
+        );
+        Ok((bytes, len))
+    }
+
+    /// Decodes `chars` and hashes the decoded bytes, without the bytes dropped by the padding.
+    /// The entry of `max_variable_byte_sizes` must fit `3 * chars.len() / 4` bytes.
+    pub fn digest_decoded<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        sha256: &mut Sha256DynamicConfig<F>,
+        chars: &[AssignedValue<'b, F>],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = sha256.range().clone();
+        let (bytes, len) = self.decode(ctx, &range, chars)?;
+        sha256.digest_assigned_dynamic(ctx, &bytes, &len, None)
+    }
+
+    /// Constrains `=` to appear only as the last character, or as the last two characters, of `chars` and the
+    /// decoded `bytes` it drops to be zero, and returns the number of padding characters.
+    /// A dropped byte holds the low bits of the last sextet before the padding, so a non-canonical encoding such as
+    /// `Rh==` instead of `Rg==` is rejected.
+    fn constrain_padding<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        gate: &FlexGateConfig<F>,
+        chars: &[AssignedValue<'a, F>],
+        bytes: &[AssignedValue<'a, F>],
+    ) -> AssignedValue<'a, F> {
+        if chars.is_empty() {
+            return gate.load_zero(ctx);
+        }
+        let is_pads = chars
+            .iter()
+            .map(|char| {
+                gate.is_equal(
+                    ctx,
+                    QuantumCell::Existing(char),
+                    QuantumCell::Constant(F::from(BASE64_PAD as u64)),
+                )
+            })
+            .collect_vec();
+        let num_chars = chars.len();
+        for is_pad in is_pads[..num_chars - 2].iter() {
+            gate.assert_is_const(ctx, is_pad, F::zero());
+        }
+        // `=` second to last is only valid if it is followed by another `=`.
+        let is_lone_pad = gate.mul_not(
+            ctx,
+            QuantumCell::Existing(&is_pads[num_chars - 1]),
+            QuantumCell::Existing(&is_pads[num_chars - 2]),
+        );
+        gate.assert_is_const(ctx, &is_lone_pad, F::zero());
+        let num_bytes = bytes.len();
+        for (is_pad, byte) in is_pads[num_chars - 2..]
+            .iter()
+            .zip(bytes[num_bytes - 2..].iter())
+        {
+            let dropped = gate.mul(
+                ctx,
+                QuantumCell::Existing(is_pad),
+                QuantumCell::Existing(byte),
+            );
+            gate.assert_is_const(ctx, &dropped, F::zero());
+        }
+        gate.add(
+            ctx,
+            QuantumCell::Existing(&is_pads[num_chars - 2]),
+            QuantumCell::Existing(&is_pads[num_chars - 1]),
+        )
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "base64 table",
+            |mut table| {
+                let mut rows = vec![(0u64, 0u64, 0u64)];
+                for (sextet, char) in BASE64_ALPHABET.iter().enumerate() {
+                    rows.push((1, *char as u64, sextet as u64));
+                }
+                rows.push((1, BASE64_PAD as u64, 0));
+                for (idx, (tag, char, sextet)) in rows.into_iter().enumerate() {
+                    table.assign_cell(
+                        || format!("table_tag at {}", idx),
+                        self.table_tag,
+                        idx,
+                        || Value::known(F::from(tag)),
+                    )?;
+                    table.assign_cell(
+                        || format!("table_char at {}", idx),
+                        self.table_char,
+                        idx,
+                        || Value::known(F::from(char)),
+                    )?;
+                    table.assign_cell(
+                        || format!("table_sextet at {}", idx),
+                        self.table_sextet,
+                        idx,
+                        || Value::known(F::from(sextet)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+
+    fn char_to_sextet<'v: 'a, 'a>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        gate: &FlexGateConfig<F>,
+        char: &AssignedValue<F>,
+    ) -> Result<AssignedValue<'a, F>, Error> {
+        self.q_lookup.enable(&mut ctx.region, self.row_offset)?;
+        let assigned_char_cell = ctx.region.assign_advice(
+            || format!("base64 char at offset {}", self.row_offset),
+            self.chars,
+            self.row_offset,
+            || char.value,
+        )?;
+        ctx.region
+            .constrain_equal(assigned_char_cell.cell(), char.cell())?;
+        let sextet_value = char.value().map(|v| {
+            let char = v.get_lower_32() as u8;
+            let sextet = BASE64_ALPHABET.iter().position(|c| *c == char).unwrap_or(0);
+            F::from(sextet as u64)
+        });
+        let assigned_sextet_cell = ctx.region.assign_advice(
+            || format!("base64 sextet at offset {}", self.row_offset),
+            self.sextets,
+            self.row_offset,
+            || sextet_value,
+        )?;
+        let assigned_sextet = gate.load_witness(ctx, sextet_value);
+        ctx.region
+            .constrain_equal(assigned_sextet_cell.cell(), assigned_sextet.cell())?;
+        self.row_offset += 1;
+        Ok(assigned_sextet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{prove, TestChip, TestShape};
+    use halo2_base::halo2_proofs::{circuit::Layouter, dev::VerifyFailure, halo2curves::bn256::Fr};
+    use sha2::{Digest, Sha256};
+
+    impl<F: PrimeField> TestChip<F> for Base64Config<F> {
+        fn configure(meta: &mut ConstraintSystem<F>, _: &RangeConfig<F>) -> Self {
+            Base64Config::configure(meta)
+        }
+
+        fn load(&mut self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+            Base64Config::load(self, layouter)
+        }
+    }
+
+    struct Base64Shape;
+
+    impl TestShape<Fr> for Base64Shape {
+        type Extra = Base64Config<Fr>;
+        const NUM_ADVICE: usize = 8;
+        const MAX_VARIABLE_BYTE_SIZES: &'static [usize] = &[64];
+    }
+
+    /// Decodes `chars` and, when `expected` is given, checks the decoded bytes and their digest.
+    fn run(chars: &[u8], expected: Option<Vec<u8>>) -> Result<(), Vec<VerifyFailure>> {
+        prove::<Base64Shape, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let chars = chars
+                .iter()
+                .map(|char| gate.load_witness(ctx, Value::known(Fr::from(*char as u64))))
+                .collect_vec();
+            let result = chips.extra.digest_decoded(ctx, &mut chips.sha256, &chars)?;
+            if let Some(expected) = &expected {
+                gate.assert_is_const(ctx, &result.input_len, Fr::from(expected.len() as u64));
+                for (assigned, byte) in result.input_bytes.iter().zip(expected.iter()) {
+                    gate.assert_is_const(ctx, assigned, Fr::from(*byte as u64));
+                }
+                for (assigned, byte) in result
+                    .output_bytes
+                    .iter()
+                    .zip(Sha256::digest(expected).iter())
+                {
+                    gate.assert_is_const(ctx, assigned, Fr::from(*byte as u64));
+                }
+            }
+            Ok(vec![])
+        })
+    }
+
+    #[test]
+    fn test_base64_dkim_body_hash() {
+        // `bh=` of an empty body with the simple canonicalization, i.e. the base64 SHA-256 of "\r\n".
+        let bh = b"frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=";
+        assert_eq!(run(bh, Some(Sha256::digest(b"\r\n").to_vec())), Ok(()));
+    }
+
+    #[test]
+    fn test_base64_double_padding() {
+        let chars = b"zS7KNTV0HyeorkDDGwxB1AV6enuRKzO5rthkhdHIRg==";
+        assert_eq!(
+            run(chars, Some(Sha256::digest(b"hello\r\n")[..31].to_vec())),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_base64_rejects_invalid_char() {
+        let mut chars = b"frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=".to_vec();
+        chars[0] = b'*';
+        assert!(run(&chars, None).is_err());
+    }
+
+    #[test]
+    fn test_base64_rejects_padding_inside() {
+        let mut chars = b"frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=".to_vec();
+        chars[10] = BASE64_PAD;
+        assert!(run(&chars, None).is_err());
+        // `=` second to last must be followed by another `=`.
+        let mut chars = b"zS7KNTV0HyeorkDDGwxB1AV6enuRKzO5rthkhdHIRg==".to_vec();
+        chars[43] = b'A';
+        assert!(run(&chars, None).is_err());
+    }
+
+    #[test]
+    fn test_base64_rejects_non_canonical_padding() {
+        // `Z` differs from `Y` only in the low 2 bits of its sextet, which the single `=` drops.
+        let mut chars = b"frcCV1k9oG9oKj3dpUqdJg1PxRT2RSN/XKdLCPjaYaY=".to_vec();
+        chars[42] = b'Z';
+        assert!(run(&chars, Some(Sha256::digest(b"\r\n").to_vec())).is_err());
+        // `h` differs from `g` only in the low 4 bits of its sextet, which the double `==` drops.
+        let mut chars = b"zS7KNTV0HyeorkDDGwxB1AV6enuRKzO5rthkhdHIRg==".to_vec();
+        chars[41] = b'h';
+        assert!(run(&chars, Some(Sha256::digest(b"hello\r\n")[..31].to_vec())).is_err());
+    }
+}
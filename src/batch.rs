@@ -0,0 +1,254 @@
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+use crate::{
+    preimage::{skip_first_pass, Sha256PreimageConfig},
+    Sha256DynamicConfig,
+};
+use halo2_base::halo2_proofs::{
+    circuit::{Cell, FloorPlanner, Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2_base::{
+    gates::{
+        range::{RangeConfig, RangeStrategy::Vertical},
+        RangeInstructions,
+    },
+    utils::PrimeField,
+};
+use sha2::{Digest, Sha256};
+
+/// Collects up to `CAPACITY` messages of at most `MAX_BYTE_SIZE` bytes each and builds a `Sha256BatchCircuit` for them.
+#[derive(Debug, Clone, Default)]
+pub struct Sha256BatchBuilder<const MAX_BYTE_SIZE: usize, const CAPACITY: usize> {
+    messages: Vec<Vec<u8>>,
+}
+
+impl<const MAX_BYTE_SIZE: usize, const CAPACITY: usize>
+    Sha256BatchBuilder<MAX_BYTE_SIZE, CAPACITY>
+{
+    pub fn new() -> Self {
+        Self { messages: vec![] }
+    }
+
+    /// Adds `message` to the batch and returns its index, or returns `None` if the batch is full or the message is longer than `MAX_BYTE_SIZE`.
+    pub fn push(&mut self, message: Vec<u8>) -> Option<usize> {
+        if self.messages.len() == CAPACITY || message.len() > MAX_BYTE_SIZE {
+            return None;
+        }
+        self.messages.push(message);
+        Some(self.messages.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn remaining_capacity(&self) -> usize {
+        CAPACITY - self.messages.len()
+    }
+
+    /// Builds the circuit. The unused slots hash the empty message, so the circuit shape only depends on the const parameters.
+    pub fn build<F: PrimeField>(self) -> Sha256BatchCircuit<F, MAX_BYTE_SIZE, CAPACITY> {
+        self.build_with_planner()
+    }
+
+    /// Same as `build`, but lays the circuit out with the floor planner `P`, see `Sha256PreimageCircuit`.
+    pub fn build_with_planner<F: PrimeField, P: FloorPlanner + 'static>(
+        self,
+    ) -> Sha256BatchCircuit<F, MAX_BYTE_SIZE, CAPACITY, P> {
+        let mut messages = self.messages;
+        messages.resize(CAPACITY, vec![]);
+        Sha256BatchCircuit {
+            messages,
+            _f: PhantomData,
+        }
+    }
+}
+
+/// Proves the SHA-256 digests of `CAPACITY` private messages of at most `MAX_BYTE_SIZE` bytes each.
+/// All the hashes share one spread table, and the digest of the `i`-th message is in rows `32 * i..32 * (i + 1)`
+/// of the instance column, as returned by `instances`. `P` is the floor planner, as for `Sha256PreimageCircuit`.
+#[derive(Debug)]
+pub struct Sha256BatchCircuit<
+    F: PrimeField,
+    const MAX_BYTE_SIZE: usize,
+    const CAPACITY: usize,
+    P: FloorPlanner + 'static = SimpleFloorPlanner,
+> {
+    messages: Vec<Vec<u8>>,
+    _f: PhantomData<(F, P)>,
+}
+
+impl<
+        F: PrimeField,
+        const MAX_BYTE_SIZE: usize,
+        const CAPACITY: usize,
+        P: FloorPlanner + 'static,
+    > Clone for Sha256BatchCircuit<F, MAX_BYTE_SIZE, CAPACITY, P>
+{
+    fn clone(&self) -> Self {
+        Self {
+            messages: self.messages.clone(),
+            _f: PhantomData,
+        }
+    }
+}
+
+impl<
+        F: PrimeField,
+        const MAX_BYTE_SIZE: usize,
+        const CAPACITY: usize,
+        P: FloorPlanner + 'static,
+    > Sha256BatchCircuit<F, MAX_BYTE_SIZE, CAPACITY, P>
+{
+    pub const K: u32 = 17;
+    const NUM_FIXED: usize = 1;
+    const NUM_LOOKUP_ADVICE: usize = 1;
+    const LOOKUP_BITS: usize = 16;
+    /// Blocks whose spread lookups fit in one pair of spread columns at `K`.
+    const BLOCKS_PER_SPREAD_COLUMN: usize = 22;
+
+    /// Returns the public inputs, i.e., the digest bytes of every message.
+    pub fn instances(&self) -> Vec<Vec<F>> {
+        vec![self
+            .messages
+            .iter()
+            .flat_map(|message| Sha256::digest(message).to_vec())
+            .map(|byte| F::from(byte as u64))
+            .collect()]
+    }
+
+    pub fn max_padded_byte_size() -> usize {
+        (MAX_BYTE_SIZE + 9 + 63) / 64 * 64
+    }
+
+    /// Returns the number of blocks compressed by the circuit.
+    pub fn num_blocks() -> usize {
+        CAPACITY * Self::max_padded_byte_size() / 64
+    }
+
+    /// Returns the number of flex gate advice columns.
+    pub fn num_advice() -> usize {
+        (Self::num_blocks() + 1) / 2 + 1
+    }
+
+    /// Returns the number of pairs of spread columns.
+    pub fn num_spread_columns() -> usize {
+        Self::num_blocks() / Self::BLOCKS_PER_SPREAD_COLUMN + 1
+    }
+}
+
+impl<
+        F: PrimeField,
+        const MAX_BYTE_SIZE: usize,
+        const CAPACITY: usize,
+        P: FloorPlanner + 'static,
+    > Circuit<F> for Sha256BatchCircuit<F, MAX_BYTE_SIZE, CAPACITY, P>
+{
+    type Config = Sha256PreimageConfig<F>;
+    type FloorPlanner = P;
+
+    fn without_witnesses(&self) -> Self {
+        Sha256BatchBuilder::<MAX_BYTE_SIZE, CAPACITY>::new().build_with_planner()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let range_config = RangeConfig::configure(
+            meta,
+            Vertical,
+            &[Self::num_advice()],
+            &[Self::NUM_LOOKUP_ADVICE],
+            Self::NUM_FIXED,
+            Self::LOOKUP_BITS,
+            0,
+            Self::K as usize,
+        );
+        let digest_column = meta.instance_column();
+        meta.enable_equality(digest_column);
+        let sha256 = Sha256DynamicConfig::configure(
+            meta,
+            vec![Self::max_padded_byte_size(); CAPACITY],
+            range_config,
+            8,
+            Self::num_spread_columns(),
+            true,
+        );
+        Self::Config {
+            sha256,
+            digest_column,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let mut sha256 = config.sha256.clone();
+        let range = sha256.range().clone();
+        range.load_lookup_table(&mut layouter)?;
+        sha256.load(&mut layouter)?;
+        let mut first_pass = skip_first_pass::<P>();
+        let mut digest_cells: Vec<Cell> = vec![];
+        layouter.assign_region(
+            || "sha256 batch",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let ctx = &mut sha256.new_context(region);
+                digest_cells.clear();
+                for message in self.messages.iter() {
+                    let result = sha256.digest(ctx, message, None)?;
+                    digest_cells.extend(result.output_bytes.iter().map(|byte| byte.cell()));
+                }
+                range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        for (idx, cell) in digest_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.digest_column, idx)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::floor_planner::V1, dev::MockProver, halo2curves::bn256::Fr,
+    };
+
+    #[test]
+    fn test_sha256_batch_circuit() {
+        let mut builder = Sha256BatchBuilder::<100, 3>::new();
+        assert_eq!(builder.push(b"abc".to_vec()), Some(0));
+        assert_eq!(builder.push(vec![0x61; 101]), None);
+        assert_eq!(builder.push(vec![0x61; 100]), Some(1));
+        assert_eq!(builder.remaining_capacity(), 1);
+        let circuit = builder.build::<Fr>();
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), 3 * 32);
+        let prover =
+            MockProver::run(Sha256BatchCircuit::<Fr, 100, 3>::K, &circuit, instances).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_batch_circuit_v1() {
+        let mut builder = Sha256BatchBuilder::<100, 3>::new();
+        builder.push(b"abc".to_vec());
+        let circuit = builder.build_with_planner::<Fr, V1>();
+        let instances = circuit.instances();
+        let prover =
+            MockProver::run(Sha256BatchCircuit::<Fr, 100, 3>::K, &circuit, instances).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
@@ -0,0 +1,281 @@
+//! Conversions between field elements, integers and little-endian bit vectors.
+use alloc::{vec, vec::Vec};
+use core::convert::TryFrom;
+use core::fmt;
+use halo2_base::halo2_proofs::{circuit::Value, plonk::Error};
+use halo2_base::{
+    utils::PrimeField,
+    utils::{biguint_to_fe, fe_to_biguint},
+    AssignedValue,
+};
+use itertools::*;
+use num_bigint::BigUint;
+
+/// Error of a fallible conversion into bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitsError {
+    /// The bit vector does not have the expected number of bits.
+    WrongLength { expected: usize, actual: usize },
+    /// The value has a bit set at or above `width`, so it does not fit in `width` bits.
+    ValueExceedsWidth { width: usize, num_bits: usize },
+}
+
+impl fmt::Display for BitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongLength { expected, actual } => {
+                write!(f, "expected {} bits, got {}", expected, actual)
+            }
+            Self::ValueExceedsWidth { width, num_bits } => {
+                write!(
+                    f,
+                    "value of {} bits exceeds the width of {} bits",
+                    num_bits, width
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BitsError {}
+
+/// Little-endian bits of an integer of `LEN` bits, at most 64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bits<const LEN: usize>([bool; LEN]);
+
+impl<const LEN: usize> Bits<LEN> {
+    pub fn as_slice(&self) -> &[bool] {
+        &self.0
+    }
+}
+
+impl<const LEN: usize> TryFrom<&[bool]> for Bits<LEN> {
+    type Error = BitsError;
+
+    fn try_from(bits: &[bool]) -> Result<Self, BitsError> {
+        if bits.len() != LEN {
+            return Err(BitsError::WrongLength {
+                expected: LEN,
+                actual: bits.len(),
+            });
+        }
+        let mut array = [false; LEN];
+        array.copy_from_slice(bits);
+        Ok(Self(array))
+    }
+}
+
+impl<const LEN: usize> TryFrom<u64> for Bits<LEN> {
+    type Error = BitsError;
+
+    fn try_from(int: u64) -> Result<Self, BitsError> {
+        assert!(LEN <= 64);
+        let num_bits = (u64::BITS - int.leading_zeros()) as usize;
+        if num_bits > LEN {
+            return Err(BitsError::ValueExceedsWidth {
+                width: LEN,
+                num_bits,
+            });
+        }
+        Ok(Self(i2lebsp(int)))
+    }
+}
+
+impl<const LEN: usize> From<Bits<LEN>> for u64 {
+    fn from(bits: Bits<LEN>) -> u64 {
+        lebs2ip(&bits.0)
+    }
+}
+
+/// Returns the `size` least significant bits of `val` in little-endian order, or an error if `val` does not fit in
+/// `size` bits.
+pub fn try_fe_to_bits_le<F: PrimeField>(val: &F, size: usize) -> Result<Vec<bool>, BitsError> {
+    let num_bits = fe_to_biguint(val).bits() as usize;
+    if num_bits > size {
+        return Err(BitsError::ValueExceedsWidth {
+            width: size,
+            num_bits,
+        });
+    }
+    let val_bytes = fe_to_biguint(val).to_bytes_le();
+    let mut bits = val_bytes
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .collect_vec();
+    bits.resize(size, false);
+    Ok(bits)
+}
+
+/// Returns the `size` least significant bits of `val` in little-endian order.
+/// Panics if `val` does not fit in `size` bits; see `try_fe_to_bits_le`.
+pub fn fe_to_bits_le<F: PrimeField>(val: &F, size: usize) -> Vec<bool> {
+    try_fe_to_bits_le(val, size).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Returns the `size` bits of a witness value, or `Error::Synthesis` if the witness does not fit in `size` bits.
+/// Witness generation of a malformed input then fails with an error instead of a panic in the prover.
+pub fn value_to_bits_le<F: PrimeField>(
+    val: Value<&F>,
+    size: usize,
+) -> Result<Value<Vec<bool>>, Error> {
+    let mut result = Ok(());
+    let bits = val.map(|val| {
+        try_fe_to_bits_le(val, size).unwrap_or_else(|err| {
+            result = Err(err);
+            vec![false; size]
+        })
+    });
+    result.map_err(|err| {
+        log::error!("invalid witness: {}", err);
+        Error::Synthesis
+    })?;
+    Ok(bits)
+}
+
+/// Returns the field element whose little-endian bits are `bits`.
+pub fn bits_le_to_fe<F: PrimeField>(bits: &[bool]) -> F {
+    let bytes = bits
+        .chunks(8)
+        .map(|bits| {
+            let mut byte = 0u8;
+            for idx in 0..bits.len() {
+                if bits[idx] {
+                    byte += 1 << idx;
+                }
+            }
+            byte
+        })
+        .collect_vec();
+    biguint_to_fe(&BigUint::from_bytes_le(&bytes))
+}
+
+/// Returns the integer whose little-endian bits are `bits`. At most 64 bits are allowed.
+pub fn lebs2ip(bits: &[bool]) -> u64 {
+    assert!(bits.len() <= 64);
+    bits.iter()
+        .enumerate()
+        .fold(0u64, |acc, (idx, bit)| acc + ((*bit as u64) << idx))
+}
+
+/// Returns the `NUM_BITS` least significant bits of `int` in little-endian order.
+pub fn i2lebsp<const NUM_BITS: usize>(int: u64) -> [bool; NUM_BITS] {
+    assert!(NUM_BITS <= 64);
+    let mut bits = [false; NUM_BITS];
+    for (idx, bit) in bits.iter_mut().enumerate() {
+        *bit = (int >> idx) & 1 == 1;
+    }
+    bits
+}
+
+/// Interleaves a zero bit after every bit of `bits`, i.e. moves bit `i` to bit `2i`.
+pub fn spread_bits(bits: &[bool]) -> Vec<bool> {
+    let mut spread = vec![false; bits.len() * 2];
+    for (idx, bit) in bits.iter().enumerate() {
+        spread[2 * idx] = *bit;
+    }
+    spread
+}
+
+/// Returns the bits at even positions of `bits`, the inverse of `spread_bits`.
+pub fn even_bits(bits: &[bool]) -> Vec<bool> {
+    bits.iter().step_by(2).copied().collect_vec()
+}
+
+/// Returns the bits at odd positions of `bits`.
+pub fn odd_bits(bits: &[bool]) -> Vec<bool> {
+    bits.iter().skip(1).step_by(2).copied().collect_vec()
+}
+
+/// Returns the spread form of a 16-bit integer.
+/// Computed by interleaving with masks, so it is cheap enough to derive every table row on the fly.
+pub const fn spread_u16(int: u16) -> u32 {
+    let mut spread = int as u32;
+    spread = (spread | (spread << 8)) & 0x00ff_00ff;
+    spread = (spread | (spread << 4)) & 0x0f0f_0f0f;
+    spread = (spread | (spread << 2)) & 0x3333_3333;
+    spread = (spread | (spread << 1)) & 0x5555_5555;
+    spread
+}
+
+/// Returns the witnessed value of `assigned` truncated to a byte, or zero if it is unknown.
+pub fn assigned_to_u8<F: PrimeField>(assigned: &AssignedValue<F>) -> u8 {
+    let mut byte = 0;
+    assigned.value().map(|v| byte = v.get_lower_32() as u8);
+    byte
+}
+
+/// Returns the witnessed value of `assigned` truncated to 32 bits, or zero if it is unknown.
+pub fn assigned_to_u32<F: PrimeField>(assigned: &AssignedValue<F>) -> u32 {
+    let mut word = 0;
+    assigned.value().map(|v| word = v.get_lower_32());
+    word
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    #[test]
+    fn test_integer_bits_roundtrip() {
+        for int in [0u64, 1, 0x5a, 0xdead_beef, u64::MAX] {
+            assert_eq!(lebs2ip(&i2lebsp::<64>(int)), int);
+        }
+        assert_eq!(i2lebsp::<4>(0b1011), [true, true, false, true]);
+    }
+
+    #[test]
+    fn test_field_bits_roundtrip() {
+        let val = Fr::from(0x1234_5678u64);
+        let bits = fe_to_bits_le(&val, 32);
+        assert_eq!(lebs2ip(&bits), 0x1234_5678);
+        assert_eq!(bits_le_to_fe::<Fr>(&bits), val);
+        // the value fits in 12 bits even though its bytes span 16.
+        assert_eq!(fe_to_bits_le(&Fr::from(0x3ffu64), 12).len(), 12);
+    }
+
+    #[test]
+    fn test_fallible_conversions() {
+        assert_eq!(
+            try_fe_to_bits_le(&Fr::from(1u64 << 32), 32),
+            Err(BitsError::ValueExceedsWidth {
+                width: 32,
+                num_bits: 33
+            })
+        );
+        assert!(value_to_bits_le(Value::known(&Fr::from(1u64 << 32)), 32).is_err());
+        assert_eq!(
+            Bits::<8>::try_from(0x100u64),
+            Err(BitsError::ValueExceedsWidth {
+                width: 8,
+                num_bits: 9
+            })
+        );
+        assert_eq!(u64::from(Bits::<8>::try_from(0xa5u64).unwrap()), 0xa5);
+        assert_eq!(
+            Bits::<8>::try_from(&[true; 7][..]),
+            Err(BitsError::WrongLength {
+                expected: 8,
+                actual: 7
+            })
+        );
+    }
+
+    #[test]
+    fn test_spread_bits() {
+        assert_eq!(spread_u16(0b1011), 0b1000101);
+        assert_eq!(spread_u16(0xffff), 0x5555_5555);
+        let bits = i2lebsp::<16>(0xbeef);
+        assert_eq!(even_bits(&spread_bits(&bits)), bits.to_vec());
+        assert!(odd_bits(&spread_bits(&bits)).iter().all(|bit| !bit));
+    }
+
+    #[test]
+    fn test_spread_u16_matches_bits() {
+        for int in 0..=u16::MAX {
+            let expected = lebs2ip(&spread_bits(&i2lebsp::<16>(int as u64)));
+            assert_eq!(spread_u16(int) as u64, expected);
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! Bitwise operations on 32-bit words with the spread table.
+//!
+//! A word is looked up as the spreads of its two 16-bit halves, see `SpreadU32`. Adding the spreads of two or three
+//! words never carries into the next bit, so the even bits of the sum are the XOR of the words and the odd bits of the
+//! sum of two words are their AND. The operations take the `SpreadConfig` by mutable reference, so they share the
+//! table of `Sha256DynamicConfig` through `Sha256DynamicConfig::spread_config_mut`.
+//! Rotations and shifts need no spread: they split the word into two range-checked parts. `u32add` is the addition
+//! modulo `2^32` that the compression functions use, for checksum and counter circuits.
+use crate::compression::{mod_u32, state_to_spread_u32};
+use crate::spread::SpreadConfig;
+use crate::SpreadU32;
+use alloc::vec::Vec;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+
+/// Looks up the spread form of a 32-bit word. The lookups range-check `x` to 32 bits.
+pub fn spread_u32<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x: &AssignedValue<'a, F>,
+) -> Result<SpreadU32<'a, F>, Error> {
+    state_to_spread_u32(ctx, range, spread_config, x)
+}
+
+/// Returns `x ^ y` for 32-bit words.
+pub fn xor<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x: &AssignedValue<'a, F>,
+    y: &AssignedValue<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let x_spread = spread_u32(ctx, range, spread_config, x)?;
+    let y_spread = spread_u32(ctx, range, spread_config, y)?;
+    xor_spreads(ctx, range, spread_config, &[&x_spread, &y_spread])
+}
+
+/// Returns `x & y` for 32-bit words.
+pub fn and<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x: &AssignedValue<'a, F>,
+    y: &AssignedValue<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let x_spread = spread_u32(ctx, range, spread_config, x)?;
+    let y_spread = spread_u32(ctx, range, spread_config, y)?;
+    and_spreads(ctx, range, spread_config, &x_spread, &y_spread)
+}
+
+/// Returns `!x` for a 32-bit word, i.e. `0xffffffff - x`. `x` is range-checked, since the subtraction would
+/// otherwise wrap around the field for a wider value.
+pub fn not<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+) -> AssignedValue<'a, F> {
+    range.range_check(ctx, x, 32);
+    range.gate().sub(
+        ctx,
+        QuantumCell::Constant(F::from(u32::MAX as u64)),
+        QuantumCell::Existing(x),
+    )
+}
+
+/// Returns the XOR of two or three words given in spread form, as a dense value.
+pub fn xor_spreads<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    words: &[&SpreadU32<'a, F>],
+) -> Result<AssignedValue<'a, F>, Error> {
+    // a fourth spread could carry into the next bit.
+    debug_assert!(words.len() == 2 || words.len() == 3);
+    let gate = range.gate();
+    let mut out_limbs = Vec::with_capacity(2);
+    for limb_idx in 0..2 {
+        let sum = gate.sum(
+            ctx,
+            words
+                .iter()
+                .map(|(lo, hi)| QuantumCell::Existing(if limb_idx == 0 { lo } else { hi })),
+        );
+        let ((even, _), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+        out_limbs.push(even);
+    }
+    Ok(compose_limbs(ctx, range, &out_limbs[0], &out_limbs[1]))
+}
+
+/// Returns the AND of two words given in spread form, as a dense value.
+pub fn and_spreads<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x: &SpreadU32<'a, F>,
+    y: &SpreadU32<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let gate = range.gate();
+    let mut out_limbs = Vec::with_capacity(2);
+    for (x, y) in [(&x.0, &y.0), (&x.1, &y.1)] {
+        let sum = gate.add(ctx, QuantumCell::Existing(x), QuantumCell::Existing(y));
+        let ((_, odd), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+        out_limbs.push(odd);
+    }
+    Ok(compose_limbs(ctx, range, &out_limbs[0], &out_limbs[1]))
+}
+
+/// Rotates a 32-bit word `x` to the left by `rotation` bits.
+/// `x` is split at bit `32 - rotation` into two range-checked parts, which are recomposed in swapped order.
+pub fn rotl<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    rotation: usize,
+) -> AssignedValue<'a, F> {
+    debug_assert!(rotation > 0 && rotation < 32);
+    let gate = range.gate();
+    let lo_bits = 32 - rotation;
+    let lo = x
+        .value()
+        .map(|v| v.get_lower_32() & ((1u32 << lo_bits) - 1))
+        .map(|v| F::from(v as u64));
+    let hi = x
+        .value()
+        .map(|v| v.get_lower_32() >> lo_bits)
+        .map(|v| F::from(v as u64));
+    let assigned_lo = gate.load_witness(ctx, lo);
+    let assigned_hi = gate.load_witness(ctx, hi);
+    range.range_check(ctx, &assigned_lo, lo_bits);
+    range.range_check(ctx, &assigned_hi, rotation);
+    let composed = gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&assigned_hi),
+        QuantumCell::Constant(F::from(1u64 << lo_bits)),
+        QuantumCell::Existing(&assigned_lo),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(x),
+        QuantumCell::Existing(&composed),
+    );
+    gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&assigned_lo),
+        QuantumCell::Constant(F::from(1u64 << rotation)),
+        QuantumCell::Existing(&assigned_hi),
+    )
+}
+
+/// Rotates a 32-bit word `x` to the right by `rotation` bits.
+pub fn rotr<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    rotation: usize,
+) -> AssignedValue<'a, F> {
+    rotl(ctx, range, x, 32 - rotation)
+}
+
+/// Shifts a 32-bit word `x` to the right by `shift` bits.
+/// `x` is split at bit `shift` into two range-checked parts and the high part is returned.
+pub fn shr<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    shift: usize,
+) -> AssignedValue<'a, F> {
+    debug_assert!(shift > 0 && shift < 32);
+    let gate = range.gate();
+    let lo = x
+        .value()
+        .map(|v| v.get_lower_32() & ((1u32 << shift) - 1))
+        .map(|v| F::from(v as u64));
+    let hi = x
+        .value()
+        .map(|v| v.get_lower_32() >> shift)
+        .map(|v| F::from(v as u64));
+    let assigned_lo = gate.load_witness(ctx, lo);
+    let assigned_hi = gate.load_witness(ctx, hi);
+    range.range_check(ctx, &assigned_lo, shift);
+    range.range_check(ctx, &assigned_hi, 32 - shift);
+    let composed = gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&assigned_hi),
+        QuantumCell::Constant(F::from(1u64 << shift)),
+        QuantumCell::Existing(&assigned_lo),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(x),
+        QuantumCell::Existing(&composed),
+    );
+    assigned_hi
+}
+
+/// Returns the sum of two to seven 32-bit words modulo `2^32`.
+/// The words must be range-checked to 32 bits, e.g. as outputs of this module; the carry of at most 3 bits and the
+/// result are range-checked by `mod_u32`.
+pub fn u32add<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    words: &[&AssignedValue<'a, F>],
+) -> AssignedValue<'a, F> {
+    assert!(
+        (2..=7).contains(&words.len()),
+        "u32add takes 2 to 7 words, got {}",
+        words.len()
+    );
+    let sum = range
+        .gate()
+        .sum(ctx, words.iter().map(|word| QuantumCell::Existing(*word)));
+    mod_u32(ctx, range, &sum)
+}
+
+/// Splits a sum of spread limbs into its even and odd bits.
+/// Returns the dense values `(even, odd)` and their spread values `(even_spread, odd_spread)`.
+pub fn even_and_odd<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    sum: &AssignedValue<F>,
+) -> Result<(SpreadU32<'a, F>, SpreadU32<'a, F>), Error> {
+    let gate = range.gate();
+    let (even, odd) = spread_config.decompose_even_and_odd_unchecked(ctx, range, sum)?;
+    let even_spread = spread_config.spread(ctx, range, &even)?;
+    let odd_spread = spread_config.spread(ctx, range, &odd)?;
+    let composed = gate.mul_add(
+        ctx,
+        QuantumCell::Constant(F::from(2)),
+        QuantumCell::Existing(&odd_spread),
+        QuantumCell::Existing(&even_spread),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(&composed),
+        QuantumCell::Existing(sum),
+    );
+    Ok(((even, odd), (even_spread, odd_spread)))
+}
+
+/// Returns `hi * 2^16 + lo` for dense 16-bit limbs.
+fn compose_limbs<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    lo: &AssignedValue<'a, F>,
+    hi: &AssignedValue<'a, F>,
+) -> AssignedValue<'a, F> {
+    range.gate().mul_add(
+        ctx,
+        QuantumCell::Existing(hi),
+        QuantumCell::Constant(F::from(1u64 << 16)),
+        QuantumCell::Existing(lo),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::Sha256DynamicConfig;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        x: u32,
+        y: u32,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[6], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![64], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config;
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "bitwise test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let x = gate.load_witness(ctx, Value::known(F::from(self.x as u64)));
+                    let y = gate.load_witness(ctx, Value::known(F::from(self.y as u64)));
+                    let spread_config = sha256.spread_config_mut();
+                    let xor_xy = xor(ctx, &range, spread_config, &x, &y)?;
+                    let and_xy = and(ctx, &range, spread_config, &x, &y)?;
+                    let not_x = not(ctx, &range, &x);
+                    let rotr_x = rotr(ctx, &range, &x, 7);
+                    let shr_x = shr(ctx, &range, &x, 3);
+                    let sum = u32add(ctx, &range, &[&x, &y, &xor_xy, &and_xy, &not_x]);
+                    gate.assert_is_const(ctx, &xor_xy, F::from((self.x ^ self.y) as u64));
+                    gate.assert_is_const(ctx, &and_xy, F::from((self.x & self.y) as u64));
+                    gate.assert_is_const(ctx, &not_x, F::from(!self.x as u64));
+                    gate.assert_is_const(ctx, &rotr_x, F::from(self.x.rotate_right(7) as u64));
+                    gate.assert_is_const(ctx, &shr_x, F::from((self.x >> 3) as u64));
+                    let expected_sum = [self.y, self.x ^ self.y, self.x & self.y, !self.x]
+                        .iter()
+                        .fold(self.x, |acc, word| acc.wrapping_add(*word));
+                    gate.assert_is_const(ctx, &sum, F::from(expected_sum as u64));
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_bitwise() {
+        for (x, y) in [
+            (0xdeadbeef, 0x0badf00d),
+            (0, u32::MAX),
+            (u32::MAX, u32::MAX),
+        ] {
+            let circuit = TestCircuit::<Fr> {
+                x,
+                y,
+                _f: PhantomData,
+            };
+            let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,360 @@
+//! BLAKE2s-256 without a key.
+//! XORs are evaluated as the even bits of a sum of spread words and rotations by decomposing the word, so the chip
+//! needs the same spread table as SHA-256. `blake2s_digest` takes the `SpreadConfig` by mutable reference, so that a
+//! circuit hashing with both can share the table, e.g. through `Sha256DynamicConfig::spread_config_mut`.
+use crate::bitwise::{rotr, spread_u32, xor_spreads};
+use crate::compression::mod_u32;
+use crate::endian::word_from_le_bytes;
+use crate::spread::SpreadConfig;
+use crate::{INIT_STATE, NUM_STATE_WORD};
+use alloc::{vec, vec::Vec};
+use halo2_base::halo2_proofs::{
+    circuit::Layouter,
+    plonk::{ConstraintSystem, Error},
+};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+const NUM_BLAKE2S_ROUND: usize = 10;
+const BLOCK_BYTES: usize = 64;
+pub const BLAKE2S_OUTPUT_BYTES: usize = 32;
+const SIGMA: [[usize; 16]; NUM_BLAKE2S_ROUND] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+/// Indexes `(a, b, c, d)` of the working vector mixed by each `G` of a round: the columns, then the diagonals.
+const G_INDEXES: [[usize; 4]; 8] = [
+    [0, 4, 8, 12],
+    [1, 5, 9, 13],
+    [2, 6, 10, 14],
+    [3, 7, 11, 15],
+    [0, 5, 10, 15],
+    [1, 6, 11, 12],
+    [2, 7, 8, 13],
+    [3, 4, 9, 14],
+];
+
+/// BLAKE2s-256 chip for inputs of a static length, with its own spread table.
+#[derive(Debug, Clone)]
+pub struct Blake2sConfig<F: PrimeField> {
+    range: RangeConfig<F>,
+    spread_config: SpreadConfig<F>,
+}
+
+impl<F: PrimeField> Blake2sConfig<F> {
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        range: RangeConfig<F>,
+        num_bits_lookup: usize,
+        num_advice_columns: usize,
+    ) -> Self {
+        let spread_config = SpreadConfig::configure(meta, num_bits_lookup, num_advice_columns);
+        Self {
+            range,
+            spread_config,
+        }
+    }
+
+    /// Returns the 32 bytes of the BLAKE2s-256 digest of `input`.
+    pub fn digest<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        blake2s_digest(ctx, &self.range, &mut self.spread_config, input)
+    }
+
+    pub fn range(&self) -> &RangeConfig<F> {
+        &self.range
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.spread_config.load(layouter)
+    }
+}
+
+/// Returns the 32 bytes of the BLAKE2s-256 digest of `input`, using the lookups of `spread_config`.
+/// `input` must be range-checked bytes. As the length is static, the block counters and the final block flag are
+/// constants, and so are the zero bytes padding the last block.
+pub fn blake2s_digest<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    input: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let gate = range.gate();
+    let num_block = ((input.len() + BLOCK_BYTES - 1) / BLOCK_BYTES).max(1);
+    let mut padded_input = input.to_vec();
+    while padded_input.len() < num_block * BLOCK_BYTES {
+        padded_input.push(gate.load_zero(ctx));
+    }
+
+    // The parameter block only sets the digest length, the key length (zero), the fanout and the depth.
+    let mut init_state = INIT_STATE;
+    init_state[0] ^= 0x01010000 ^ BLAKE2S_OUTPUT_BYTES as u32;
+    let mut state = init_state
+        .iter()
+        .map(|word| gate.load_constant(ctx, F::from(*word as u64)))
+        .collect_vec();
+    for (idx, block) in padded_input.chunks(BLOCK_BYTES).enumerate() {
+        let is_last = idx == num_block - 1;
+        let counter = if is_last {
+            input.len() as u64
+        } else {
+            (BLOCK_BYTES * (idx + 1)) as u64
+        };
+        state = blake2s_compression(ctx, range, spread_config, block, &state, counter, is_last)?;
+    }
+
+    let mut output_bytes = vec![];
+    for word in state.iter() {
+        let word_val = word.value().map(|v| v.get_lower_32());
+        let bytes = (0..4)
+            .map(|idx| {
+                let byte = gate.load_witness(
+                    ctx,
+                    word_val.map(|v| F::from(((v >> (8 * idx)) & 0xff) as u64)),
+                );
+                range.range_check(ctx, &byte, 8);
+                byte
+            })
+            .collect_vec();
+        let recomposed = gate.inner_product(
+            ctx,
+            bytes.iter().map(QuantumCell::Existing),
+            (0..4).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * idx)))),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(word),
+            QuantumCell::Existing(&recomposed),
+        );
+        output_bytes.extend(bytes);
+    }
+    Ok(output_bytes)
+}
+
+/// Compresses a 64-byte block, where `counter` is the number of input bytes up to the end of the block.
+pub fn blake2s_compression<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+    counter: u64,
+    is_last: bool,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    debug_assert_eq!(assigned_input_bytes.len(), BLOCK_BYTES);
+    debug_assert_eq!(pre_state_words.len(), NUM_STATE_WORD);
+    let gate = range.gate();
+    // message words are little-endian.
+    let message_u32s = assigned_input_bytes
+        .chunks(4)
+        .map(|bytes| word_from_le_bytes(ctx, gate, bytes))
+        .collect_vec();
+
+    let mut iv = INIT_STATE;
+    iv[4] ^= counter as u32;
+    iv[5] ^= (counter >> 32) as u32;
+    if is_last {
+        iv[6] = !iv[6];
+    }
+    let mut v = pre_state_words.to_vec();
+    v.extend(
+        iv.iter()
+            .map(|word| gate.load_constant(ctx, F::from(*word as u64))),
+    );
+    for (round_idx, sigma) in SIGMA.iter().enumerate() {
+        for (g_idx, [a, b, c, d]) in G_INDEXES.iter().enumerate() {
+            spread_config.set_annotation("BLAKE2s round", 8 * round_idx + g_idx);
+            let x = &message_u32s[sigma[2 * g_idx]];
+            let y = &message_u32s[sigma[2 * g_idx + 1]];
+            let [new_a, new_b, new_c, new_d] = mix(
+                ctx,
+                range,
+                spread_config,
+                [&v[*a], &v[*b], &v[*c], &v[*d]],
+                x,
+                y,
+            )?;
+            v[*a] = new_a;
+            v[*b] = new_b;
+            v[*c] = new_c;
+            v[*d] = new_d;
+        }
+    }
+
+    (0..NUM_STATE_WORD)
+        .map(|idx| {
+            xor(
+                ctx,
+                range,
+                spread_config,
+                &[&pre_state_words[idx], &v[idx], &v[idx + 8]],
+            )
+        })
+        .collect()
+}
+
+/// The `G` function, mixing `[a, b, c, d]` with the message words `x` and `y`.
+fn mix<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    [a, b, c, d]: [&AssignedValue<'a, F>; 4],
+    x: &AssignedValue<'a, F>,
+    y: &AssignedValue<'a, F>,
+) -> Result<[AssignedValue<'a, F>; 4], Error> {
+    let gate = range.gate();
+    let add3 = |ctx: &mut Context<'b, F>,
+                lhs: &AssignedValue<'a, F>,
+                rhs: &AssignedValue<'a, F>,
+                word: Option<&AssignedValue<'a, F>>| {
+        let mut sum = gate.add(ctx, QuantumCell::Existing(lhs), QuantumCell::Existing(rhs));
+        if let Some(word) = word {
+            sum = gate.add(
+                ctx,
+                QuantumCell::Existing(&sum),
+                QuantumCell::Existing(word),
+            );
+        }
+        mod_u32(ctx, range, &sum)
+    };
+    let a = add3(ctx, a, b, Some(x));
+    let d = xor(ctx, range, spread_config, &[d, &a])?;
+    let d = rotr(ctx, range, &d, 16);
+    let c = add3(ctx, c, &d, None);
+    let b = xor(ctx, range, spread_config, &[b, &c])?;
+    let b = rotr(ctx, range, &b, 12);
+    let a = add3(ctx, &a, &b, Some(y));
+    let d = xor(ctx, range, spread_config, &[&d, &a])?;
+    let d = rotr(ctx, range, &d, 8);
+    let c = add3(ctx, &c, &d, None);
+    let b = xor(ctx, range, spread_config, &[&b, &c])?;
+    let b = rotr(ctx, range, &b, 7);
+    Ok([a, b, c, d])
+}
+
+/// Returns the XOR of two or three 32-bit words as a dense value: the even bits of the sum of their spreads.
+fn xor<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    words: &[&AssignedValue<'a, F>],
+) -> Result<AssignedValue<'a, F>, Error> {
+    let spreads = words
+        .iter()
+        .map(|word| spread_u32(ctx, range, spread_config, word))
+        .collect::<Result<Vec<_>, Error>>()?;
+    xor_spreads(
+        ctx,
+        range,
+        spread_config,
+        &spreads.iter().collect::<Vec<_>>(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::Sha256DynamicConfig;
+    use halo2_base::halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::Circuit,
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        test_input: Vec<u8>,
+        expected_output: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[6], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "blake2s test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let input = self
+                        .test_input
+                        .iter()
+                        .map(|byte| {
+                            let byte = gate.load_witness(ctx, Value::known(F::from(*byte as u64)));
+                            range.range_check(ctx, &byte, 8);
+                            byte
+                        })
+                        .collect_vec();
+                    // The BLAKE2s lookups go to the spread table of the SHA-256 chip.
+                    let output_bytes =
+                        blake2s_digest(ctx, &range, sha256.spread_config_mut(), &input)?;
+                    for (assigned, expected) in output_bytes.iter().zip(self.expected_output.iter())
+                    {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_blake2s_abc() {
+        let circuit = TestCircuit::<Fr> {
+            test_input: b"abc".to_vec(),
+            expected_output: hex::decode(
+                "508c5e8c327c14e2e1a72ba34eeb452f37458b209ed63a294d999b4c86675982",
+            )
+            .unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
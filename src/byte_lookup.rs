@@ -0,0 +1,149 @@
+use alloc::{format, vec};
+use core::marker::PhantomData;
+
+use halo2_base::halo2_proofs::{
+    circuit::Value,
+    plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector},
+    poly::Rotation,
+};
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+
+/// Binds assigned bytes to a dynamic table of `(index, byte)` rows owned by the caller, e.g. the calldata bytes that
+/// another sub-circuit processed, with a `lookup_any` argument.
+/// Each byte is copied next to its index, a constant of the circuit, and the pair is looked up in the table, so the
+/// `i`-th byte is proven equal to the table byte at index `i`.
+/// Rows without the selector look up `(0, 0)`, so the table must contain that row; unassigned rows of the table
+/// columns do.
+#[derive(Debug, Clone)]
+pub struct ByteLookupConfig<F: PrimeField> {
+    q_lookup: Selector,
+    indexes: Column<Fixed>,
+    bytes: Column<Advice>,
+    row_offset: usize,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField> ByteLookupConfig<F> {
+    /// `table_indexes` and `table_bytes` are the columns of the caller's table.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        table_indexes: Column<Advice>,
+        table_bytes: Column<Advice>,
+    ) -> Self {
+        let q_lookup = meta.complex_selector();
+        let indexes = meta.fixed_column();
+        let bytes = meta.advice_column();
+        meta.enable_equality(bytes);
+
+        meta.lookup_any("bytes in external table", |meta| {
+            let q = meta.query_selector(q_lookup);
+            let index = meta.query_fixed(indexes, Rotation::cur());
+            let byte = meta.query_advice(bytes, Rotation::cur());
+            let table_index = meta.query_advice(table_indexes, Rotation::cur());
+            let table_byte = meta.query_advice(table_bytes, Rotation::cur());
+            vec![(q.clone() * index, table_index), (q * byte, table_byte)]
+        });
+        Self {
+            q_lookup,
+            indexes,
+            bytes,
+            row_offset: 0,
+            _f: PhantomData,
+        }
+    }
+
+    /// Looks up `bytes[i]` at index `first_index + i` of the table.
+    /// For a message of dynamic length, pass only the bytes that must match the table, e.g. up to the maximum length
+    /// of the table, and bind the lengths separately.
+    pub fn assign_lookup<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        bytes: &[AssignedValue<'a, F>],
+        first_index: usize,
+    ) -> Result<(), Error> {
+        for (idx, byte) in bytes.iter().enumerate() {
+            self.q_lookup.enable(&mut ctx.region, self.row_offset)?;
+            ctx.region.assign_fixed(
+                || format!("lookup index at offset {}", self.row_offset),
+                self.indexes,
+                self.row_offset,
+                || Value::known(F::from((first_index + idx) as u64)),
+            )?;
+            let assigned_byte = ctx.region.assign_advice(
+                || format!("lookup byte at offset {}", self.row_offset),
+                self.bytes,
+                self.row_offset,
+                || byte.value,
+            )?;
+            ctx.region
+                .constrain_equal(assigned_byte.cell(), byte.cell())?;
+            self.row_offset += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{prove, TestChip, TestShape};
+    use halo2_base::gates::range::RangeConfig;
+    use halo2_base::halo2_proofs::{dev::VerifyFailure, halo2curves::bn256::Fr};
+
+    #[derive(Debug, Clone)]
+    struct LookupTestChip<F: PrimeField> {
+        lookup: ByteLookupConfig<F>,
+        table: [Column<Advice>; 2],
+    }
+
+    impl<F: PrimeField> TestChip<F> for LookupTestChip<F> {
+        fn configure(meta: &mut ConstraintSystem<F>, _: &RangeConfig<F>) -> Self {
+            let table = [meta.advice_column(), meta.advice_column()];
+            let lookup = ByteLookupConfig::configure(meta, table[0], table[1]);
+            Self { lookup, table }
+        }
+    }
+
+    struct LookupShape;
+
+    impl TestShape<Fr> for LookupShape {
+        type Extra = LookupTestChip<Fr>;
+        const NUM_ADVICE: usize = 3;
+        const MAX_VARIABLE_BYTE_SIZES: &'static [usize] = &[128];
+    }
+
+    fn run(test_input: &[u8], calldata: &[u8]) -> Result<(), Vec<VerifyFailure>> {
+        prove::<LookupShape, _>(vec![], |ctx, chips| {
+            let LookupTestChip { lookup, table } = &mut chips.extra;
+            // the calldata table of another chip, at indexes starting from 1 so that its rows differ from `(0, 0)`.
+            for (idx, byte) in calldata.iter().enumerate() {
+                ctx.region.assign_advice(
+                    || "calldata index",
+                    table[0],
+                    idx,
+                    || Value::known(Fr::from(idx as u64 + 1)),
+                )?;
+                ctx.region.assign_advice(
+                    || "calldata byte",
+                    table[1],
+                    idx,
+                    || Value::known(Fr::from(*byte as u64)),
+                )?;
+            }
+            let result = chips.sha256.digest(ctx, test_input, None)?;
+            lookup.assign_lookup(ctx, &result.input_bytes[..test_input.len()], 1)?;
+            Ok(vec![])
+        })
+    }
+
+    #[test]
+    fn test_byte_lookup() {
+        let test_input = b"transfer(address,uint256)".to_vec();
+        for (calldata, is_ok) in [
+            (test_input.clone(), true),
+            (b"transfer(address,uint128)".to_vec(), false),
+        ] {
+            assert_eq!(run(&test_input, &calldata).is_ok(), is_ok);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use crate::{AssignedHashResult, Sha256DynamicConfig};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+use sha2::{Digest, Sha256};
+
+pub const SALT_BYTES: usize = 32;
+
+/// Computes the hiding commitment `SHA256(msg || salt)` out of the circuit.
+pub fn compute_commitment(msg: &[u8], salt: &[u8; SALT_BYTES]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(msg);
+    hasher.update(salt);
+    hasher.finalize().into()
+}
+
+/// Commits to `msg` as `SHA256(msg || salt)` with a uniformly random 32-byte `salt`.
+/// Only `output_bytes` of the result should be exposed publicly; `msg` and `salt` stay private.
+pub fn commit<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    msg: &[AssignedValue<'b, F>],
+    salt: &[AssignedValue<'b, F>],
+) -> Result<AssignedHashResult<'b, F>, Error> {
+    assert_eq!(salt.len(), SALT_BYTES);
+    let mut input = msg.to_vec();
+    input.extend_from_slice(salt);
+    sha256.digest_assigned(ctx, &input, None)
+}
+
+/// Opens a commitment created by `commit`, possibly in another circuit, by constraining `SHA256(msg || salt)` to be equal to `commitment`.
+pub fn open<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    msg: &[AssignedValue<'b, F>],
+    salt: &[AssignedValue<'b, F>],
+    commitment: &[AssignedValue<'b, F>],
+) -> Result<AssignedHashResult<'b, F>, Error> {
+    assert_eq!(commitment.len(), 32);
+    let result = commit(ctx, sha256, msg, salt)?;
+    sha256.constrain_digest_equal(ctx, &result, commitment)?;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{
+        gates::{
+            range::{RangeConfig, RangeStrategy::Vertical},
+            GateInstructions, RangeInstructions,
+        },
+        SKIP_FIRST_PASS,
+    };
+    use itertools::Itertools;
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        msg: Vec<u8>,
+        salt: [u8; SALT_BYTES],
+        commitment: [u8; 32],
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[3], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![128], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "commitment test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let load_bytes = |ctx: &mut Context<F>, bytes: &[u8]| {
+                        bytes
+                            .iter()
+                            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                            .collect_vec()
+                    };
+                    let msg = load_bytes(ctx, &self.msg);
+                    let salt = load_bytes(ctx, &self.salt);
+                    let commitment = load_bytes(ctx, &self.commitment);
+                    open(ctx, &mut sha256, &msg, &salt, &commitment)?;
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_commitment_open() {
+        let msg = b"the vote is yes".to_vec();
+        let salt = [0x5c; SALT_BYTES];
+        let commitment = compute_commitment(&msg, &salt);
+        assert_eq!(
+            commitment.to_vec(),
+            Sha256::digest([msg.as_slice(), &salt].concat()).to_vec()
+        );
+        let circuit = TestCircuit::<Fr> {
+            msg,
+            salt,
+            commitment,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_commitment_rejects_other_salt() {
+        let msg = b"the vote is yes".to_vec();
+        let mut salt = [0x5c; SALT_BYTES];
+        let commitment = compute_commitment(&msg, &salt);
+        salt[0] ^= 1;
+        let circuit = TestCircuit::<Fr> {
+            msg,
+            salt,
+            commitment,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
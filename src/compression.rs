@@ -1,5 +1,6 @@
+use crate::bits::{bits_le_to_fe, value_to_bits_le};
 use crate::spread::SpreadConfig;
-use crate::utils::{bits_le_to_fe, fe_to_bits_le};
+use alloc::{vec, vec::Vec};
 use halo2_base::halo2_proofs::halo2curves::FieldExt;
 use halo2_base::halo2_proofs::{
     circuit::{AssignedCell, Cell, Layouter, Region, SimpleFloorPlanner, Value},
@@ -26,6 +27,58 @@ use sha2::{Digest, Sha256};
 
 pub type SpreadU32<'a, F> = (AssignedValue<'a, F>, AssignedValue<'a, F>);
 
+/// Eight 32-bit state words of SHA-256, as consumed and produced by `Sha256DynamicConfig::compress_block`.
+#[derive(Debug, Clone)]
+pub struct State<'a, F: PrimeField> {
+    words: Vec<AssignedValue<'a, F>>,
+}
+
+impl<'a, F: PrimeField> State<'a, F> {
+    /// Wraps eight assigned words. The caller must ensure they are 32-bit values, e.g. by taking them from the output
+    /// of a compression.
+    pub fn new(words: Vec<AssignedValue<'a, F>>) -> Self {
+        assert_eq!(words.len(), NUM_STATE_WORD);
+        Self { words }
+    }
+
+    /// Assigns `words` as constants, e.g. a custom initial value.
+    pub fn constant<'b: 'a>(
+        ctx: &mut Context<'b, F>,
+        gate: &FlexGateConfig<F>,
+        words: &[u32; NUM_STATE_WORD],
+    ) -> Self {
+        Self::new(
+            words
+                .iter()
+                .map(|word| gate.load_constant(ctx, F::from(*word as u64)))
+                .collect_vec(),
+        )
+    }
+
+    /// Assigns the SHA-256 IV as constants.
+    pub fn iv<'b: 'a>(ctx: &mut Context<'b, F>, gate: &FlexGateConfig<F>) -> Self {
+        Self::constant(ctx, gate, &INIT_STATE)
+    }
+
+    pub fn words(&self) -> &[AssignedValue<'a, F>] {
+        &self.words
+    }
+
+    pub fn into_words(self) -> Vec<AssignedValue<'a, F>> {
+        self.words
+    }
+}
+
+/// Applies the SHA-256 compression function to a 64-byte block and the 8 words of `pre_state_words`.
+///
+/// Every arithmetic cell is assigned in the flex gate columns of `range`; the columns of `spread_config` only hold
+/// the inputs of the spread lookup, one limb per row and column pair. There are no lookup selectors, so those columns
+/// cannot take other assignments. The smallest area is obtained with a single pair of spread columns and the number of
+/// flex gate columns that fits the target `k`.
+/// The message schedule and the rounds share one context and the flex gate packs cells densely, so no cells are left
+/// unused between them and interleaving the two would not reduce the number of rows.
+/// The sequence of assignments does not depend on the witness, so every block assigns the same number of cells and
+/// spread lookups, and consecutive blocks are laid out at a constant stride.
 pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
     ctx: &mut Context<'b, F>,
     range: &RangeConfig<F>,
@@ -33,10 +86,40 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
     assigned_input_bytes: &[AssignedValue<'a, F>],
     pre_state_words: &[AssignedValue<'a, F>],
 ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    sha256_compression_with_cells(
+        ctx,
+        range,
+        spread_config,
+        assigned_input_bytes,
+        pre_state_words,
+        None,
+    )
+}
+
+/// Cells of the message schedule and of the working variables, as recorded by `sha256_compression_with_cells`.
+/// They line up with `reference::BlockTrace`.
+#[derive(Debug, Clone)]
+pub struct CompressionCells<'a, F: PrimeField> {
+    /// Words `W_0..W_63` of the message schedule.
+    pub schedule: Vec<AssignedValue<'a, F>>,
+    /// Spread forms of `schedule`, as looked up by the schedule and the σ functions. External gadgets evaluating
+    /// bitwise functions of the schedule words can use them without another lookup.
+    pub schedule_spreads: Vec<SpreadU32<'a, F>>,
+    /// Working variables `[a, b, c, d, e, f, g, h]` after each round.
+    pub rounds: Vec<Vec<AssignedValue<'a, F>>>,
+}
+
+/// Expands a 64-byte block into the 64 words of the message schedule, returned with their spread forms.
+pub(crate) fn message_schedule<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+) -> Result<(Vec<AssignedValue<'a, F>>, Vec<SpreadU32<'a, F>>), Error> {
     debug_assert_eq!(assigned_input_bytes.len(), 64);
-    debug_assert_eq!(pre_state_words.len(), 8);
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("sha256 message schedule").entered();
     let gate = range.gate();
-    // message schedule.
     let mut i = 0;
     let mut message_u32s = assigned_input_bytes
         .chunks(4)
@@ -62,13 +145,33 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
     //     .collect_vec();
     let mut message_spreads = message_u32s
         .iter()
-        .map(|dense| state_to_spread_u32(ctx, range, spread_config, dense))
+        .enumerate()
+        .map(|(idx, dense)| {
+            spread_config.set_annotation("message schedule word", idx);
+            state_to_spread_u32(ctx, range, spread_config, dense)
+        })
         .collect::<Result<Vec<SpreadU32<F>>, Error>>()?;
+    // the words 14 to 48 go through both σ1 (for the word two after them) and σ0 (for the word 15 after them), so
+    // both are computed from one decomposition and σ0 is kept until it is needed.
+    let mut sigma0s = Vec::with_capacity(48 - 14 + 1);
     for idx in 16..64 {
         // let w_2_spread = state_to_spread_u32(ctx, range, spread_config, &message_u32s[idx - 2])?;
         // let w_15_spread = state_to_spread_u32(ctx, range, spread_config, &message_u32s[idx - 15])?;
-        let term1 = sigma_lower1(ctx, range, spread_config, &message_spreads[idx - 2])?;
-        let term3 = sigma_lower0(ctx, range, spread_config, &message_spreads[idx - 15])?;
+        spread_config.set_annotation("σ1 for message schedule word", idx);
+        let term1 = if idx - 2 <= 48 {
+            let (sigma0, sigma1) =
+                sigma_lower01(ctx, range, spread_config, &message_spreads[idx - 2])?;
+            sigma0s.push(sigma0);
+            sigma1
+        } else {
+            sigma_lower1(ctx, range, spread_config, &message_spreads[idx - 2])?
+        };
+        spread_config.set_annotation("σ0 for message schedule word", idx);
+        let term3 = if idx - 15 >= 14 {
+            sigma0s[idx - 15 - 14].clone()
+        } else {
+            sigma_lower0(ctx, range, spread_config, &message_spreads[idx - 15])?
+        };
         // let term1_u32 = bits2u32(ctx, gate, &term1_bits);
         // let term3_u32 = bits2u32(ctx, gate, &term3_bits);
         let new_w = {
@@ -97,6 +200,7 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
         //     new_w.value()
         // );
         message_u32s.push(new_w.clone());
+        spread_config.set_annotation("message schedule word", idx);
         let new_w_spread = state_to_spread_u32(ctx, range, spread_config, &new_w)?;
         message_spreads.push(new_w_spread);
         // if idx <= 61 {
@@ -104,6 +208,30 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
         //     message_bits.push(new_w_bits);
         // }
     }
+    Ok((message_u32s, message_spreads))
+}
+
+/// Same as `sha256_compression`, but also records the intermediate cells into `cells` if it is given.
+/// Recording does not change the assigned cells.
+pub fn sha256_compression_with_cells<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+    mut cells: Option<&mut CompressionCells<'a, F>>,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    debug_assert_eq!(assigned_input_bytes.len(), 64);
+    debug_assert_eq!(pre_state_words.len(), 8);
+    let gate = range.gate();
+    let (message_u32s, message_spreads) =
+        message_schedule(ctx, range, spread_config, assigned_input_bytes)?;
+
+    if let Some(cells) = &mut cells {
+        cells.schedule = message_u32s.clone();
+        cells.schedule_spreads = message_spreads.clone();
+        cells.rounds.clear();
+    }
 
     // compression
     let (mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h) = (
@@ -116,12 +244,18 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
         pre_state_words[6].clone(),
         pre_state_words[7].clone(),
     );
+    spread_config.set_annotation("input state word", 0);
     let mut a_spread = state_to_spread_u32(ctx, range, spread_config, &a)?;
+    spread_config.set_annotation("input state word", 1);
     let mut b_spread = state_to_spread_u32(ctx, range, spread_config, &b)?;
+    spread_config.set_annotation("input state word", 2);
     let mut c_spread = state_to_spread_u32(ctx, range, spread_config, &c)?;
     // let mut d_spread = state_to_spread_u32(ctx, range, spread_config, &d)?;
+    spread_config.set_annotation("input state word", 4);
     let mut e_spread = state_to_spread_u32(ctx, range, spread_config, &e)?;
+    spread_config.set_annotation("input state word", 5);
     let mut f_spread = state_to_spread_u32(ctx, range, spread_config, &f)?;
+    spread_config.set_annotation("input state word", 6);
     let mut g_spread = state_to_spread_u32(ctx, range, spread_config, &g)?;
     // let mut h_spread = state_to_spread_u32(ctx, range, spread_config, &h)?;
     // let mut a_bits = gate.num_to_bits(ctx, &a, 32);
@@ -130,21 +264,14 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
     // let mut e_bits = gate.num_to_bits(ctx, &e, 32);
     // let mut f_bits = gate.num_to_bits(ctx, &f, 32);
     // let mut g_bits = gate.num_to_bits(ctx, &g, 32);
-    let mut t1 = gate.load_zero(ctx);
-    let mut t2 = gate.load_zero(ctx);
     for idx in 0..64 {
-        t1 = {
-            // let e_spread = state_to_spread_u32(ctx, range, spread_config, &e)?;
-            // let f_spread = state_to_spread_u32(ctx, range, spread_config, &f)?;
-            // let g_spread = state_to_spread_u32(ctx, range, spread_config, &g)?;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!("sha256 compression round", round = idx).entered();
+        let t1 = {
+            spread_config.set_annotation("Σ1 in compression round", idx);
             let sigma_term = sigma_upper1(ctx, range, spread_config, &e_spread)?;
+            spread_config.set_annotation("Ch in compression round", idx);
             let ch_term = ch(ctx, range, spread_config, &e_spread, &f_spread, &g_spread)?;
-            // println!(
-            //     "idx {} sigma {:?} ch {:?}",
-            //     idx,
-            //     sigma_term.value(),
-            //     ch_term.value()
-            // );
             let add1 = gate.add(
                 ctx,
                 QuantumCell::Existing(&h),
@@ -165,13 +292,12 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
                 QuantumCell::Existing(&add3),
                 QuantumCell::Existing(&message_u32s[idx]),
             );
-            mod_u32(ctx, &range, &add4)
+            mod_u32(ctx, range, &add4)
         };
-        t2 = {
-            // let a_spread = state_to_spread_u32(ctx, range, spread_config, &a)?;
-            // let b_spread = state_to_spread_u32(ctx, range, spread_config, &b)?;
-            // let c_spread = state_to_spread_u32(ctx, range, spread_config, &c)?;
+        let t2 = {
+            spread_config.set_annotation("Σ0 in compression round", idx);
             let sigma_term = sigma_upper0(ctx, range, spread_config, &a_spread)?;
+            spread_config.set_annotation("Maj in compression round", idx);
             let maj_term = maj(ctx, range, spread_config, &a_spread, &b_spread, &c_spread)?;
             let add = gate.add(
                 ctx,
@@ -180,7 +306,6 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
             );
             mod_u32(ctx, range, &add)
         };
-        // println!("idx {}, t1 {:?}, t2 {:?}", idx, t1.value(), t2.value());
         h = g;
         // h_spread = g_spread;
         g = f;
@@ -191,6 +316,7 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
             let add = gate.add(ctx, QuantumCell::Existing(&d), QuantumCell::Existing(&t1));
             mod_u32(ctx, range, &add)
         };
+        spread_config.set_annotation("new e in compression round", idx);
         e_spread = state_to_spread_u32(ctx, range, spread_config, &e)?;
         d = c;
         // d_spread = c_spread;
@@ -202,7 +328,20 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
             let add = gate.add(ctx, QuantumCell::Existing(&t1), QuantumCell::Existing(&t2));
             mod_u32(ctx, range, &add)
         };
+        spread_config.set_annotation("new a in compression round", idx);
         a_spread = state_to_spread_u32(ctx, range, spread_config, &a)?;
+        if let Some(cells) = &mut cells {
+            cells.rounds.push(vec![
+                a.clone(),
+                b.clone(),
+                c.clone(),
+                d.clone(),
+                e.clone(),
+                f.clone(),
+                g.clone(),
+                h.clone(),
+            ]);
+        }
     }
     let new_states = vec![a, b, c, d, e, f, g, h];
     let next_state_words = new_states
@@ -222,34 +361,19 @@ pub fn sha256_compression<'a, 'b: 'a, F: PrimeField>(
     Ok(next_state_words)
 }
 
-fn state_to_spread_u32<'a, 'b: 'a, F: PrimeField>(
+pub(crate) fn state_to_spread_u32<'a, 'b: 'a, F: PrimeField>(
     ctx: &mut Context<'b, F>,
     range: &RangeConfig<F>,
     spread_config: &mut SpreadConfig<F>,
     x: &AssignedValue<F>,
 ) -> Result<SpreadU32<'a, F>, Error> {
-    let gate = range.gate();
-    let lo = x
-        .value()
-        .map(|v| v.get_lower_32() & ((1 << 16) - 1))
-        .map(|v| F::from(v as u64));
-    let hi = x
-        .value()
-        .map(|v| (v.get_lower_32() >> 16))
-        .map(|v| F::from(v as u64));
-    let assigned_lo = gate.load_witness(ctx, lo);
-    let assigned_hi = gate.load_witness(ctx, hi);
-    let composed = gate.mul_add(
-        ctx,
-        QuantumCell::Existing(&assigned_hi),
-        QuantumCell::Constant(F::from(1u64 << 16)),
-        QuantumCell::Existing(&assigned_lo),
-    );
-    gate.assert_equal(
-        ctx,
-        QuantumCell::Existing(&x),
-        QuantumCell::Existing(&composed),
-    );
+    let (assigned_lo, assigned_hi) = split_unchecked(ctx, range.gate(), x, 16);
+    // the spread lookups below already bound both halves to 16 bits, see `test_spread_implies_range_check`.
+    #[cfg(feature = "paranoid")]
+    {
+        range.range_check(ctx, &assigned_lo, 16);
+        range.range_check(ctx, &assigned_hi, 16);
+    }
     let lo_spread = spread_config.spread(ctx, range, &assigned_lo)?;
     let hi_spread = spread_config.spread(ctx, range, &assigned_hi)?;
     Ok((lo_spread, hi_spread))
@@ -273,35 +397,74 @@ fn state_to_spread_u32<'a, 'b: 'a, F: PrimeField>(
 //     sum
 // }
 
-fn mod_u32<'a, 'b: 'a, F: FieldExt>(
+/// Number of bits of the carry of `mod_u32`, i.e., its input must be a sum of fewer than `2^MAX_CARRY_BITS` 32-bit words.
+const MAX_CARRY_BITS: usize = 8;
+
+/// Reduces `x` modulo `2^32`. Both the result and the carry are range-checked, so the decomposition is unique.
+pub(crate) fn mod_u32<'a, 'b: 'a, F: FieldExt>(
     ctx: &mut Context<'b, F>,
     range: &RangeConfig<F>,
     x: &AssignedValue<'a, F>,
 ) -> AssignedValue<'a, F> {
-    let gate = range.gate();
+    let (assigned_lo, assigned_hi) = split_unchecked(ctx, range.gate(), x, 32);
+    range.range_check(ctx, &assigned_lo, 32);
+    range.range_check(ctx, &assigned_hi, MAX_CARRY_BITS);
+    assigned_lo
+}
+
+/// Witnesses `lo` and `hi` such that `x = lo + 2^lo_bits * hi`, without range-checking them.
+/// Both halves are assigned in the row of the gate that composes them rather than loaded first and copied in, so
+/// the only copies are those of `x` and of the constant `2^lo_bits`: two fewer than `load_witness` followed by
+/// `mul_add` and `assert_equal`, see `test_split_unchecked`.
+pub(crate) fn split_unchecked<'a, 'b: 'a, F: FieldExt>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    x: &AssignedValue<'a, F>,
+    lo_bits: usize,
+) -> (AssignedValue<'a, F>, AssignedValue<'a, F>) {
     let lo = x
         .value()
-        .map(|v| v.get_lower_32())
+        .map(|v| v.get_lower_128() & ((1u128 << lo_bits) - 1))
         .map(|v| F::from(v as u64));
     let hi = x
         .value()
-        .map(|v| (v.get_lower_128() >> 32) & ((1u128 << 32) - 1))
+        .map(|v| v.get_lower_128() >> lo_bits)
         .map(|v| F::from(v as u64));
-    let assigned_lo = gate.load_witness(ctx, lo);
-    let assigned_hi = gate.load_witness(ctx, hi);
-    range.range_check(ctx, &assigned_lo, 32);
-    let composed = gate.mul_add(
+    // one row of the flex gate: lo + hi * 2^lo_bits = x.
+    let cells = gate.assign_region(
         ctx,
-        QuantumCell::Existing(&assigned_hi),
-        QuantumCell::Constant(F::from(1u64 << 32)),
-        QuantumCell::Existing(&assigned_lo),
+        vec![
+            QuantumCell::Witness(lo),
+            QuantumCell::Witness(hi),
+            QuantumCell::Constant(F::from(1u64 << lo_bits)),
+            QuantumCell::Existing(x),
+        ],
+        vec![(0, None)],
     );
-    gate.assert_equal(
+    (cells[0].clone(), cells[1].clone())
+}
+
+/// Constrains `x`, which a spread lookup already bounds to 16 bits, to `num_bits` bits.
+/// `range_check` looks up both `x` and `x * 2^(lookup_bits - num_bits)`; given the bound on `x`, the weighted value
+/// alone implies the range, so this costs one lookup instead of two.
+#[cfg_attr(feature = "paranoid", allow(dead_code))]
+fn range_check_short<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    num_bits: usize,
+) {
+    let lookup_bits = range.lookup_bits();
+    if num_bits >= lookup_bits {
+        range.range_check(ctx, x, num_bits);
+        return;
+    }
+    let weighted = range.gate().mul(
         ctx,
-        QuantumCell::Existing(&x),
-        QuantumCell::Existing(&composed),
+        QuantumCell::Existing(x),
+        QuantumCell::Constant(F::from(1u64 << (lookup_bits - num_bits))),
     );
-    assigned_lo
+    range.range_check(ctx, &weighted, lookup_bits);
 }
 
 fn ch<'a, 'b: 'a, F: PrimeField>(
@@ -552,7 +715,7 @@ fn maj<'a, 'b: 'a, F: PrimeField>(
     Ok(m)
 }
 
-fn three_add<'a, 'b: 'a, F: PrimeField>(
+pub(crate) fn three_add<'a, 'b: 'a, F: PrimeField>(
     ctx: &mut Context<'b, F>,
     gate: &FlexGateConfig<F>,
     x: QuantumCell<'a, 'a, F>,
@@ -633,23 +796,13 @@ fn sigma_upper0<'a, 'b: 'a, F: PrimeField>(
 ) -> Result<AssignedValue<'a, F>, Error> {
     const STARTS: [usize; 4] = [0, 2, 13, 22];
     const ENDS: [usize; 4] = [2, 13, 22, 32];
-    const PADDINGS: [usize; 4] = [6, 5, 7, 6];
     let coeffs = [
         F::from((1u64 << 60) + (1u64 << 38) + (1u64 << 20)),
         F::from((1u64 << 0) + (1u64 << 42) + (1u64 << 24)),
         F::from((1u64 << 22) + (1u64 << 0) + (1u64 << 46)),
         F::from((1u64 << 40) + (1u64 << 18) + (1u64 << 0)),
     ];
-    sigma_generic(
-        ctx,
-        range,
-        spread_config,
-        x_spread,
-        &STARTS,
-        &ENDS,
-        &PADDINGS,
-        &coeffs,
-    )
+    sigma_generic(ctx, range, spread_config, x_spread, &STARTS, &ENDS, &coeffs)
 }
 
 fn sigma_upper1<'a, 'b: 'a, F: PrimeField>(
@@ -660,23 +813,13 @@ fn sigma_upper1<'a, 'b: 'a, F: PrimeField>(
 ) -> Result<AssignedValue<'a, F>, Error> {
     const STARTS: [usize; 4] = [0, 6, 11, 25];
     const ENDS: [usize; 4] = [6, 11, 25, 32];
-    const PADDINGS: [usize; 4] = [2, 3, 2, 1];
     let coeffs = [
         F::from((1u64 << 52) + (1u64 << 42) + (1u64 << 14)),
         F::from((1u64 << 0) + (1u64 << 54) + (1u64 << 26)),
         F::from((1u64 << 10) + (1u64 << 0) + (1u64 << 36)),
         F::from((1u64 << 38) + (1u64 << 28) + (1u64 << 0)),
     ];
-    sigma_generic(
-        ctx,
-        range,
-        spread_config,
-        x_spread,
-        &STARTS,
-        &ENDS,
-        &PADDINGS,
-        &coeffs,
-    )
+    sigma_generic(ctx, range, spread_config, x_spread, &STARTS, &ENDS, &coeffs)
 }
 
 fn sigma_lower0<'a, 'b: 'a, F: PrimeField>(
@@ -687,23 +830,13 @@ fn sigma_lower0<'a, 'b: 'a, F: PrimeField>(
 ) -> Result<AssignedValue<'a, F>, Error> {
     const STARTS: [usize; 4] = [0, 3, 7, 18];
     const ENDS: [usize; 4] = [3, 7, 18, 32];
-    const PADDINGS: [usize; 4] = [5, 4, 5, 2];
     let coeffs = [
         F::from((1u64 << 50) + (1u64 << 28)),
         F::from((1u64 << 0) + (1u64 << 56) + (1u64 << 34)),
         F::from((1u64 << 8) + (1u64 << 0) + (1u64 << 42)),
         F::from((1u64 << 30) + (1u64 << 22) + (1u64 << 0)),
     ];
-    sigma_generic(
-        ctx,
-        range,
-        spread_config,
-        x_spread,
-        &STARTS,
-        &ENDS,
-        &PADDINGS,
-        &coeffs,
-    )
+    sigma_generic(ctx, range, spread_config, x_spread, &STARTS, &ENDS, &coeffs)
 }
 
 fn sigma_lower1<'a, 'b: 'a, F: PrimeField>(
@@ -714,23 +847,47 @@ fn sigma_lower1<'a, 'b: 'a, F: PrimeField>(
 ) -> Result<AssignedValue<'a, F>, Error> {
     const STARTS: [usize; 4] = [0, 10, 17, 19];
     const ENDS: [usize; 4] = [10, 17, 19, 32];
-    const PADDINGS: [usize; 4] = [6, 1, 6, 3];
     let coeffs = [
         F::from((1u64 << 30) + (1u64 << 26)),
         F::from((1u64 << 0) + (1u64 << 50) + (1u64 << 46)),
         F::from((1u64 << 14) + (1u64 << 0) + (1u64 << 60)),
         F::from((1u64 << 18) + (1u64 << 4) + (1u64 << 0)),
     ];
-    sigma_generic(
-        ctx,
-        range,
-        spread_config,
-        x_spread,
-        &STARTS,
-        &ENDS,
-        &PADDINGS,
-        &coeffs,
-    )
+    sigma_generic(ctx, range, spread_config, x_spread, &STARTS, &ENDS, &coeffs)
+}
+
+/// Returns σ0 and σ1 of the same word. The pieces split `x` at the boundaries of both functions, so they are looked up
+/// and composed once for both instead of once per function.
+fn sigma_lower01<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x_spread: &SpreadU32<F>,
+) -> Result<(AssignedValue<'a, F>, AssignedValue<'a, F>), Error> {
+    const STARTS: [usize; 7] = [0, 3, 7, 10, 17, 18, 19];
+    const ENDS: [usize; 7] = [3, 7, 10, 17, 18, 19, 32];
+    let coeffs0 = [
+        F::from((1u64 << 50) + (1u64 << 28)),
+        F::from((1u64 << 56) + (1u64 << 34) + (1u64 << 0)),
+        F::from((1u64 << 0) + (1u64 << 42) + (1u64 << 8)),
+        F::from((1u64 << 6) + (1u64 << 48) + (1u64 << 14)),
+        F::from((1u64 << 20) + (1u64 << 62) + (1u64 << 28)),
+        F::from((1u64 << 22) + (1u64 << 0) + (1u64 << 30)),
+        F::from((1u64 << 24) + (1u64 << 2) + (1u64 << 32)),
+    ];
+    let coeffs1 = [
+        F::from((1u64 << 30) + (1u64 << 26)),
+        F::from((1u64 << 36) + (1u64 << 32)),
+        F::from((1u64 << 44) + (1u64 << 40)),
+        F::from((1u64 << 50) + (1u64 << 46) + (1u64 << 0)),
+        F::from((1u64 << 0) + (1u64 << 60) + (1u64 << 14)),
+        F::from((1u64 << 2) + (1u64 << 62) + (1u64 << 16)),
+        F::from((1u64 << 4) + (1u64 << 0) + (1u64 << 18)),
+    ];
+    let pieces = sigma_pieces(ctx, range, spread_config, x_spread, &STARTS, &ENDS)?;
+    let sigma0 = sigma_from_pieces(ctx, range, spread_config, &pieces, &coeffs0)?;
+    let sigma1 = sigma_from_pieces(ctx, range, spread_config, &pieces, &coeffs1)?;
+    Ok((sigma0, sigma1))
 }
 
 fn sigma_generic<'a, 'b: 'a, F: PrimeField>(
@@ -738,54 +895,65 @@ fn sigma_generic<'a, 'b: 'a, F: PrimeField>(
     range: &RangeConfig<F>,
     spread_config: &mut SpreadConfig<F>,
     x_spread: &SpreadU32<F>,
-    starts: &[usize; 4],
-    ends: &[usize; 4],
-    paddings: &[usize; 4],
-    coeffs: &[F; 4],
+    starts: &[usize],
+    ends: &[usize],
+    coeffs: &[F],
 ) -> Result<AssignedValue<'a, F>, Error> {
+    let pieces = sigma_pieces(ctx, range, spread_config, x_spread, starts, ends)?;
+    sigma_from_pieces(ctx, range, spread_config, &pieces, coeffs)
+}
+
+/// Decomposes `x_spread` into the spreads of the bits `starts[idx]..ends[idx]` of `x`, which must cover its 32 bits
+/// in order.
+fn sigma_pieces<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x_spread: &SpreadU32<F>,
+    starts: &[usize],
+    ends: &[usize],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    debug_assert_eq!(starts.len(), ends.len());
     let gate = range.gate();
     // let x_spread = spread_config.spread(ctx, range, x)?;
-    let bits_val = x_spread.0.value().zip(x_spread.1.value()).map(|(lo, hi)| {
-        let mut bits = fe_to_bits_le(lo, 32);
-        bits.append(&mut fe_to_bits_le(hi, 32));
-        bits
-    });
-    let mut assign_bits =
-        |bits_val: &Value<Vec<bool>>, start: usize, end: usize, padding: usize| {
-            let fe_val: Value<F> = bits_val.as_ref().map(|bits| {
-                let mut bits = bits[(2 * start)..(2 * end)].to_vec();
-                bits.extend_from_slice(&vec![false; 64 - bits.len()]);
-                bits_le_to_fe(&bits)
-            });
-            let assigned = gate.load_witness(ctx, fe_val);
-            // let assigned_spread = spread_config.spread(ctx, range, &assigned_dense)?;
-            // let result: Result<AssignedValue<F>, Error> = Ok(assigned_spread);
-            assigned
-        };
-    let assigned_a = assign_bits(&bits_val, starts[0], ends[0], paddings[0]);
-    let assigned_b = assign_bits(&bits_val, starts[1], ends[1], paddings[1]);
-    let assigned_c = assign_bits(&bits_val, starts[2], ends[2], paddings[2]);
-    let assigned_d = assign_bits(&bits_val, starts[3], ends[3], paddings[3]);
+    let bits_val = value_to_bits_le(x_spread.0.value(), 32)?
+        .zip(value_to_bits_le(x_spread.1.value(), 32)?)
+        .map(|(mut lo, mut hi)| {
+            lo.append(&mut hi);
+            lo
+        });
+    // each piece is the spread of a dense value of `ends[idx] - starts[idx]` bits; otherwise a piece could borrow bits
+    // from its neighbour and the decomposition of `x` would not be unique. The spread lookup already bounds a piece to
+    // 16 bits, and once the lower pieces are bounded, `x_spread` bounds the most significant one to the remaining bits.
+    let mut pieces = Vec::with_capacity(starts.len());
+    for idx in 0..starts.len() {
+        let dense_val: Value<F> = bits_val.as_ref().map(|bits| {
+            let dense_bits = bits[(2 * starts[idx])..(2 * ends[idx])]
+                .iter()
+                .step_by(2)
+                .copied()
+                .collect_vec();
+            bits_le_to_fe(&dense_bits)
+        });
+        let assigned_dense = gate.load_witness(ctx, dense_val);
+        pieces.push(spread_config.spread(ctx, range, &assigned_dense)?);
+        #[cfg(feature = "paranoid")]
+        range.range_check(ctx, &assigned_dense, ends[idx] - starts[idx]);
+        #[cfg(not(feature = "paranoid"))]
+        if idx + 1 < starts.len() {
+            range_check_short(ctx, range, &assigned_dense, ends[idx] - starts[idx]);
+        }
+    }
     {
-        let mut sum = assigned_a.clone();
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Existing(&assigned_b),
-            QuantumCell::Constant(F::from(1 << (2 * starts[1]))),
-            QuantumCell::Existing(&sum),
-        );
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Existing(&assigned_c),
-            QuantumCell::Constant(F::from(1 << (2 * starts[2]))),
-            QuantumCell::Existing(&sum),
-        );
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Existing(&assigned_d),
-            QuantumCell::Constant(F::from(1 << (2 * starts[3]))),
-            QuantumCell::Existing(&sum),
-        );
+        let mut sum = pieces[0].clone();
+        for idx in 1..pieces.len() {
+            sum = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&pieces[idx]),
+                QuantumCell::Constant(F::from(1 << (2 * starts[idx]))),
+                QuantumCell::Existing(&sum),
+            );
+        }
         let x_composed = gate.mul_add(
             ctx,
             QuantumCell::Existing(&x_spread.1),
@@ -798,74 +966,36 @@ fn sigma_generic<'a, 'b: 'a, F: PrimeField>(
             QuantumCell::Existing(&sum),
         );
     };
-    // println!(
-    //     "x {:?}, a {:?}, b {:?}, c {:?}, d {:?}",
-    //     x.value(),
-    //     assigned_a,
-    //     assigned_b,
-    //     assigned_c,
-    //     assigned_d
-    // );
+    Ok(pieces)
+}
+
+/// Returns the sum of the rotations and shifts of `x` whose spread is `coeffs[idx] * pieces[idx]` summed over the
+/// pieces, i.e., the XOR of the terms of a σ or Σ function.
+fn sigma_from_pieces<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    pieces: &[AssignedValue<'a, F>],
+    coeffs: &[F],
+) -> Result<AssignedValue<'a, F>, Error> {
+    debug_assert_eq!(pieces.len(), coeffs.len());
+    let gate = range.gate();
     let r_spread = {
-        // let a_coeff = F::from(1u64 << 60 + 1u64 << 38 + 1u64 << 20);
-        // let b_coeff = F::from(1u64 << 0 + 1u64 << 42 + 1u64 << 24);
-        // let c_coeff = F::from(1u64 << 22 + 1u64 << 0 + 1u64 << 46);
-        // let d_coeff = F::from(1u64 << 40 + 1u64 << 18 + 1u64 << 0);
         let mut sum = gate.load_zero(ctx);
-        // let assigned_a_spread = spread_config.spread(ctx, range, &assigned_a)?;
-        // let assigned_b_spread = spread_config.spread(ctx, range, &assigned_b)?;
-        // let assigned_c_spread = spread_config.spread(ctx, range, &assigned_c)?;
-        // let assigned_d_spread = spread_config.spread(ctx, range, &assigned_d)?;
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Constant(coeffs[0]),
-            QuantumCell::Existing(&assigned_a),
-            QuantumCell::Existing(&sum),
-        );
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Constant(coeffs[1]),
-            QuantumCell::Existing(&assigned_b),
-            QuantumCell::Existing(&sum),
-        );
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Constant(coeffs[2]),
-            QuantumCell::Existing(&assigned_c),
-            QuantumCell::Existing(&sum),
-        );
-        sum = gate.mul_add(
-            ctx,
-            QuantumCell::Constant(coeffs[3]),
-            QuantumCell::Existing(&assigned_d),
-            QuantumCell::Existing(&sum),
-        );
+        for (piece, coeff) in pieces.iter().zip(coeffs.iter()) {
+            sum = gate.mul_add(
+                ctx,
+                QuantumCell::Constant(*coeff),
+                QuantumCell::Existing(piece),
+                QuantumCell::Existing(&sum),
+            );
+        }
         sum
     };
     let (r_lo, r_hi) = {
-        let lo = r_spread
-            .value()
-            .map(|v| v.get_lower_32())
-            .map(|v| F::from(v as u64));
-        let hi = r_spread
-            .value()
-            .map(|v| (v.get_lower_128() >> 32) & ((1u128 << 32) - 1))
-            .map(|v| F::from(v as u64));
-        let assigned_lo = gate.load_witness(ctx, lo);
-        let assigned_hi = gate.load_witness(ctx, hi);
+        let (assigned_lo, assigned_hi) = split_unchecked(ctx, gate, &r_spread, 32);
         range.range_check(ctx, &assigned_lo, 32);
         range.range_check(ctx, &assigned_hi, 32);
-        let composed = gate.mul_add(
-            ctx,
-            QuantumCell::Existing(&assigned_hi),
-            QuantumCell::Constant(F::from(1u64 << 32)),
-            QuantumCell::Existing(&assigned_lo),
-        );
-        gate.assert_equal(
-            ctx,
-            QuantumCell::Existing(&r_spread),
-            QuantumCell::Existing(&composed),
-        );
         (assigned_lo, assigned_hi)
     };
     // println!(
@@ -1031,7 +1161,7 @@ fn sigma_generic<'a, 'b: 'a, F: PrimeField>(
 
 pub const NUM_ROUND: usize = 64;
 pub const NUM_STATE_WORD: usize = 8;
-const ROUND_CONSTANTS: [u32; NUM_ROUND] = [
+pub(crate) const ROUND_CONSTANTS: [u32; NUM_ROUND] = [
     0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
     0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
     0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
@@ -1052,3 +1182,237 @@ pub const INIT_STATE: [u32; NUM_STATE_WORD] = [
     0x1f83_d9ab,
     0x5be0_cd19,
 ];
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::Sha256DynamicConfig;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct SplitTestCircuit<F: PrimeField> {
+        x: u64,
+        // advice cells of the split with `load_witness`, `mul_add` and `assert_equal`, and of `split_unchecked`.
+        cost: RefCell<(usize, usize)>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for SplitTestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[3], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![64], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let sha256 = config;
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "split test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let x = gate.load_witness(ctx, Value::known(F::from(self.x)));
+                    let (lo, hi) = (self.x & 0xffffffff, self.x >> 32);
+
+                    let num_advice = ctx.total_advice;
+                    let loaded_lo = gate.load_witness(ctx, Value::known(F::from(lo)));
+                    let loaded_hi = gate.load_witness(ctx, Value::known(F::from(hi)));
+                    let composed = gate.mul_add(
+                        ctx,
+                        QuantumCell::Existing(&loaded_hi),
+                        QuantumCell::Constant(F::from(1u64 << 32)),
+                        QuantumCell::Existing(&loaded_lo),
+                    );
+                    gate.assert_equal(
+                        ctx,
+                        QuantumCell::Existing(&x),
+                        QuantumCell::Existing(&composed),
+                    );
+                    let loaded_cost = ctx.total_advice - num_advice;
+
+                    let num_advice = ctx.total_advice;
+                    let (split_lo, split_hi) = split_unchecked(ctx, gate, &x, 32);
+                    let split_cost = ctx.total_advice - num_advice;
+
+                    gate.assert_is_const(ctx, &split_lo, F::from(lo));
+                    gate.assert_is_const(ctx, &split_hi, F::from(hi));
+                    *self.cost.borrow_mut() = (loaded_cost, split_cost);
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_split_unchecked() {
+        let circuit = SplitTestCircuit::<Fr> {
+            x: 0x2a_deadbeef,
+            cost: RefCell::new((0, 0)),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let (loaded_cost, split_cost) = *circuit.cost.borrow();
+        // the loaded halves are copied into the row of `mul_add`; `split_unchecked` assigns them there directly.
+        assert_eq!(loaded_cost, 6);
+        assert_eq!(split_cost, 4);
+    }
+
+    #[derive(Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "paranoid", allow(dead_code))]
+    struct SigmaCost {
+        // advice cells and spread lookups of σ0 and σ1 computed separately and of `sigma_lower01`.
+        separate_cells: usize,
+        fused_cells: usize,
+        separate_spread: usize,
+        fused_spread: usize,
+        // range lookups of the pieces of σ0.
+        piece_lookups: usize,
+    }
+
+    #[derive(Debug, Clone)]
+    struct SigmaTestCircuit<F: PrimeField> {
+        x: u32,
+        cost: RefCell<SigmaCost>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for SigmaTestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            SplitTestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config;
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "sigma test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let x = gate.load_witness(ctx, Value::known(F::from(self.x as u64)));
+                    let spread_config = sha256.spread_config_mut();
+                    let x_spread = state_to_spread_u32(ctx, &range, spread_config, &x)?;
+
+                    let num_advice = ctx.total_advice;
+                    let num_spread = spread_config.num_lookups();
+                    let sigma0 = sigma_lower0(ctx, &range, spread_config, &x_spread)?;
+                    let sigma1 = sigma_lower1(ctx, &range, spread_config, &x_spread)?;
+                    let separate_cells = ctx.total_advice - num_advice;
+                    let separate_spread = spread_config.num_lookups() - num_spread;
+
+                    let num_advice = ctx.total_advice;
+                    let num_spread = spread_config.num_lookups();
+                    let (fused0, fused1) = sigma_lower01(ctx, &range, spread_config, &x_spread)?;
+                    let fused_cells = ctx.total_advice - num_advice;
+                    let fused_spread = spread_config.num_lookups() - num_spread;
+
+                    let num_lookup = ctx.cells_to_lookup.len();
+                    sigma_pieces(
+                        ctx,
+                        &range,
+                        spread_config,
+                        &x_spread,
+                        &[0, 3, 7, 18],
+                        &[3, 7, 18, 32],
+                    )?;
+                    let piece_lookups = ctx.cells_to_lookup.len() - num_lookup;
+
+                    let x = self.x;
+                    let expected0 = x.rotate_right(7) ^ x.rotate_right(18) ^ (x >> 3);
+                    let expected1 = x.rotate_right(17) ^ x.rotate_right(19) ^ (x >> 10);
+                    for (sigma, expected) in [
+                        (&sigma0, expected0),
+                        (&fused0, expected0),
+                        (&sigma1, expected1),
+                        (&fused1, expected1),
+                    ]
+                    .iter()
+                    {
+                        gate.assert_is_const(ctx, sigma, F::from(*expected as u64));
+                    }
+                    *self.cost.borrow_mut() = SigmaCost {
+                        separate_cells,
+                        fused_cells,
+                        separate_spread,
+                        fused_spread,
+                        piece_lookups,
+                    };
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sigma_lower01() {
+        for x in [0xdeadbeef, 0, u32::MAX].iter() {
+            let circuit = SigmaTestCircuit::<Fr> {
+                x: *x,
+                cost: RefCell::new(SigmaCost::default()),
+                _f: PhantomData,
+            };
+            let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            let cost = *circuit.cost.borrow();
+            // one weighted lookup for each piece but the most significant one, where `range_check` would spend two on
+            // every piece.
+            #[cfg(not(feature = "paranoid"))]
+            assert_eq!(cost.piece_lookups, 3);
+            // one decomposition of `x` instead of two, with 7 pieces instead of 8. Each piece is spread with 2 limb
+            // lookups and each σ spreads 4 words from its pieces: 2 * (8 + 8) limbs instead of 2 * (7 + 8).
+            assert_eq!((cost.separate_spread, cost.fused_spread), (32, 30));
+            // decomposing into n pieces takes 29n - 4 cells and a σ from n pieces 4n + 108, so the separate functions
+            // take 2 * (112 + 124) cells and the fused one 199 + 2 * 136: the extra pieces cost all but one cell of
+            // the saved decomposition.
+            #[cfg(not(feature = "paranoid"))]
+            assert_eq!((cost.separate_cells, cost.fused_cells), (472, 471));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::bits::assigned_to_u8;
+use crate::NUM_STATE_WORD;
+use alloc::vec::Vec;
+use halo2_base::{utils::PrimeField, AssignedValue};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Layout of a `Sha256DynamicConfig`, so that circuit metadata can be shipped between processes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sha256ConfigDescriptor {
+    pub max_variable_byte_sizes: Vec<usize>,
+    pub max_blocks: Vec<usize>,
+    pub num_bits_lookup: usize,
+    pub spread_table_size: usize,
+    pub dense_columns: Vec<usize>,
+    pub spread_columns: Vec<usize>,
+    pub is_input_range_check: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sha256Digest(pub [u8; 32]);
+
+impl Sha256Digest {
+    /// Reads the witness values of assigned digest bytes, e.g. `AssignedHashResult::output_bytes`.
+    pub fn from_assigned<F: PrimeField>(output_bytes: &[AssignedValue<F>]) -> Self {
+        assert_eq!(output_bytes.len(), 32);
+        let mut bytes = [0u8; 32];
+        for (byte, assigned) in bytes.iter_mut().zip(output_bytes.iter()) {
+            *byte = assigned_to_u8(assigned);
+        }
+        Self(bytes)
+    }
+}
+
+/// The state words after compressing the first `num_bytes` bytes, a multiple of 64.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Sha256Midstate {
+    pub state: [u32; NUM_STATE_WORD],
+    pub num_bytes: usize,
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::*;
+    use crate::compression::INIT_STATE;
+
+    #[test]
+    fn test_serde_round_trip() {
+        let descriptor = Sha256ConfigDescriptor {
+            max_variable_byte_sizes: vec![128, 256],
+            max_blocks: vec![2, 4],
+            num_bits_lookup: 16,
+            spread_table_size: 8,
+            dense_columns: vec![4, 5],
+            spread_columns: vec![6, 7],
+            is_input_range_check: true,
+        };
+        let json = serde_json::to_string(&descriptor).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Sha256ConfigDescriptor>(&json).unwrap(),
+            descriptor
+        );
+
+        let mut bytes = [0u8; 32];
+        bytes[0] = 0xba;
+        bytes[31] = 0xad;
+        let digest = Sha256Digest(bytes);
+        let json = serde_json::to_string(&digest).unwrap();
+        // a digest is an array of 32 byte values, not a hex string.
+        assert_eq!(json, format!("[186,{}173]", "0,".repeat(30)));
+        assert_eq!(serde_json::from_str::<Sha256Digest>(&json).unwrap(), digest);
+
+        let midstate = Sha256Midstate {
+            state: INIT_STATE,
+            num_bytes: 64,
+        };
+        let json = serde_json::to_string(&midstate).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Sha256Midstate>(&json).unwrap(),
+            midstate
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Byte order of words and digests.
+//!
+//! SHA-256 reads message words and writes the digest in big-endian order, while RIPEMD-160 and BLAKE2s are
+//! little-endian. The native helpers are for witnesses and expected values; the in-circuit helpers compose assigned
+//! bytes, which the caller must have range-checked, into words.
+use crate::NUM_STATE_WORD;
+use alloc::vec::Vec;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+/// Returns the 32-byte digest serialized from the state words, each in big-endian order.
+pub fn digest_to_be_bytes(words: &[u32; NUM_STATE_WORD]) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    for (idx, word) in words.iter().enumerate() {
+        digest[4 * idx..4 * (idx + 1)].copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+/// Returns the state words of a 32-byte digest, the inverse of `digest_to_be_bytes`.
+pub fn digest_from_be_bytes(digest: &[u8; 32]) -> [u32; NUM_STATE_WORD] {
+    let mut words = [0u32; NUM_STATE_WORD];
+    for (word, bytes) in words.iter_mut().zip(digest.chunks(4)) {
+        *word = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    words
+}
+
+/// Returns the big-endian words of `bytes`, whose length must be a multiple of 4.
+pub fn words_from_be_bytes(bytes: &[u8]) -> Vec<u32> {
+    assert_eq!(bytes.len() % 4, 0);
+    bytes
+        .chunks(4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .collect()
+}
+
+/// Composes four assigned bytes into a word, the first byte being the most significant one.
+pub fn word_from_be_bytes<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    bytes: &[AssignedValue<'a, F>],
+) -> AssignedValue<'a, F> {
+    assert_eq!(bytes.len(), 4);
+    gate.inner_product(
+        ctx,
+        bytes.iter().map(QuantumCell::Existing),
+        (0..4).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (3 - idx))))),
+    )
+}
+
+/// Composes four assigned bytes into a word, the first byte being the least significant one.
+pub fn word_from_le_bytes<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    bytes: &[AssignedValue<'a, F>],
+) -> AssignedValue<'a, F> {
+    assert_eq!(bytes.len(), 4);
+    gate.inner_product(
+        ctx,
+        bytes.iter().map(QuantumCell::Existing),
+        (0..4).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * idx)))),
+    )
+}
+
+/// Composes the 32 assigned bytes of a digest into its state words, the inverse of
+/// `Sha256DynamicConfig::digest_bytes`. This is how a digest given as bytes, e.g. in public inputs, is compared to the
+/// output words of a compression.
+pub fn digest_words_from_be_bytes<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    digest: &[AssignedValue<'a, F>],
+) -> Vec<AssignedValue<'a, F>> {
+    assert_eq!(digest.len(), 32);
+    digest
+        .chunks(4)
+        .map(|bytes| word_from_be_bytes(ctx, gate, bytes))
+        .collect_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_digest_byte_order() {
+        let digest: [u8; 32] = Sha256::digest(b"abc").into();
+        let words = digest_from_be_bytes(&digest);
+        // the first word of SHA-256("abc") is 0xba7816bf, serialized as 0xba first.
+        assert_eq!(words[0], 0xba7816bf);
+        assert_eq!(digest_to_be_bytes(&words), digest);
+        assert_eq!(words_from_be_bytes(&digest), words.to_vec());
+    }
+}
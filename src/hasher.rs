@@ -0,0 +1,164 @@
+use crate::Sha256DynamicConfig;
+use alloc::vec::Vec;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+use itertools::Itertools;
+
+/// Hashes two child nodes into their parent, so that Merkle tree gadgets can be generic over the node hash.
+pub trait CircuitHasher<F: PrimeField> {
+    /// Number of assigned cells of a node.
+    const NODE_LEN: usize;
+
+    /// Returns the hash of `left` and `right`, both of `NODE_LEN` cells.
+    fn hash_two<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        left: &[AssignedValue<'a, F>],
+        right: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error>;
+}
+
+/// SHA-256 of `left || right`, where both nodes are 32 bytes. Unlike `mmr::hash_node`, this is the standard digest:
+/// the second block only holds the padding of a 64-byte message and is assigned as constants.
+#[derive(Debug)]
+pub struct Sha256TwoToOne<'c, F: PrimeField> {
+    sha256: &'c mut Sha256DynamicConfig<F>,
+}
+
+impl<'c, F: PrimeField> Sha256TwoToOne<'c, F> {
+    pub fn new(sha256: &'c mut Sha256DynamicConfig<F>) -> Self {
+        Self { sha256 }
+    }
+}
+
+impl<'c, F: PrimeField> CircuitHasher<F> for Sha256TwoToOne<'c, F> {
+    const NODE_LEN: usize = 32;
+
+    fn hash_two<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        left: &[AssignedValue<'a, F>],
+        right: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        assert_eq!(left.len(), Self::NODE_LEN);
+        assert_eq!(right.len(), Self::NODE_LEN);
+        let input = left.iter().chain(right.iter()).cloned().collect_vec();
+        self.sha256.digest_fixed(ctx, &input)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::gates::{range::RangeConfig, GateInstructions, RangeInstructions};
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
+    use sha2::{Digest, Sha256};
+
+    /// Folds `leaves` into a Merkle root with any `CircuitHasher`.
+    fn merkle_root<'a, 'b: 'a, F: PrimeField, H: CircuitHasher<F>>(
+        ctx: &mut Context<'b, F>,
+        hasher: &mut H,
+        leaves: &[Vec<AssignedValue<'a, F>>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let mut nodes = leaves.to_vec();
+        while nodes.len() > 1 {
+            nodes = nodes
+                .chunks(2)
+                .map(|pair| hasher.hash_two(ctx, &pair[0], &pair[1]))
+                .collect::<Result<Vec<_>, Error>>()?;
+        }
+        Ok(nodes.remove(0))
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        leaves: Vec<[u8; 32]>,
+        expected_root: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[8], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "two to one test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let leaves = self
+                        .leaves
+                        .iter()
+                        .map(|leaf| {
+                            leaf.iter()
+                                .map(|byte| {
+                                    gate.load_witness(ctx, Value::known(F::from(*byte as u64)))
+                                })
+                                .collect_vec()
+                        })
+                        .collect_vec();
+                    let root = merkle_root(ctx, &mut Sha256TwoToOne::new(&mut sha256), &leaves)?;
+                    for (assigned, expected) in root.iter().zip(self.expected_root.iter()) {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sha256_two_to_one() {
+        let leaves = (0..4u8).map(|idx| [idx; 32]).collect_vec();
+        let hash_two = |left: &[u8], right: &[u8]| {
+            let mut hasher = Sha256::new();
+            hasher.update(left);
+            hasher.update(right);
+            hasher.finalize().to_vec()
+        };
+        let expected_root = hash_two(
+            &hash_two(&leaves[0], &leaves[1]),
+            &hash_two(&leaves[2], &leaves[3]),
+        );
+        let circuit = TestCircuit::<Fr> {
+            leaves,
+            expected_root,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
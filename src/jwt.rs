@@ -0,0 +1,266 @@
+use crate::rlc::RlcConfig;
+use crate::{AssignedHashResult, Sha256DynamicConfig};
+use alloc::vec;
+use halo2_base::halo2_proofs::{
+    circuit::{AssignedCell, Value},
+    plonk::Error,
+};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+#[derive(Debug, Clone)]
+pub struct AssignedJwtResult<'a, F: PrimeField> {
+    pub hash_result: AssignedHashResult<'a, F>,
+    pub payload_rlc: AssignedCell<F, F>,
+}
+
+/// Hashes the RS256 signing input `base64url(header) || "." || base64url(payload)` of a JWT, made of the first
+/// `signing_input_len` bytes of `signing_input`, and returns the digest with the RLC of the (still base64url-encoded)
+/// payload bytes with the challenge of `rlc`.
+/// `payload_start` is the index of the first payload character: the byte before it is selected by indicator cells and
+/// constrained to be `.`, and the payload ends at `signing_input_len`. Both are assigned values, so one circuit handles
+/// tokens with any header and payload lengths whose signing input fits `signing_input.len()` bytes.
+pub fn digest_jwt<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    rlc: &mut RlcConfig<F>,
+    signing_input: &[AssignedValue<'b, F>],
+    signing_input_len: &AssignedValue<'b, F>,
+    payload_start: &AssignedValue<'b, F>,
+    challenge: Value<F>,
+) -> Result<AssignedJwtResult<'b, F>, Error> {
+    let range = sha256.range().clone();
+    let gate = range.gate();
+    let max_len = signing_input.len();
+    let hash_result =
+        sha256.digest_assigned_dynamic(ctx, signing_input, signing_input_len, None)?;
+
+    // `is_starts[idx]` is 1 if the payload starts at `idx`, for `idx` up to `max_len`.
+    let is_starts = (0..=max_len)
+        .map(|idx| {
+            gate.is_equal(
+                ctx,
+                QuantumCell::Existing(payload_start),
+                QuantumCell::Constant(F::from(idx as u64)),
+            )
+        })
+        .collect_vec();
+    // exactly one byte precedes the payload, so `1 <= payload_start <= max_len`, and it is the separator.
+    let num_separators = gate.sum(ctx, is_starts[1..].iter().map(QuantumCell::Existing));
+    gate.assert_is_const(ctx, &num_separators, F::one());
+    let separator = gate.select_by_indicator(
+        ctx,
+        signing_input.iter().map(QuantumCell::Existing),
+        is_starts[1..].iter(),
+    );
+    gate.assert_is_const(ctx, &separator, F::from(b'.' as u64));
+    // the payload may be empty, but may not start after the end of the signing input.
+    let len_plus_one = gate.add(
+        ctx,
+        QuantumCell::Existing(signing_input_len),
+        QuantumCell::Constant(F::one()),
+    );
+    let num_len_bits = (usize::BITS - (max_len + 1).leading_zeros()) as usize;
+    range.check_less_than(
+        ctx,
+        QuantumCell::Existing(payload_start),
+        QuantumCell::Existing(&len_plus_one),
+        num_len_bits,
+    );
+
+    // the mask is 1 from `payload_start` to `signing_input_len`, excluded.
+    let mut is_after_start = gate.load_zero(ctx);
+    let mut is_after_end = gate.load_zero(ctx);
+    let mut masks = vec![];
+    for (idx, is_start) in is_starts[..max_len].iter().enumerate() {
+        let is_end = gate.is_equal(
+            ctx,
+            QuantumCell::Existing(signing_input_len),
+            QuantumCell::Constant(F::from(idx as u64)),
+        );
+        is_after_start = gate.add(
+            ctx,
+            QuantumCell::Existing(&is_after_start),
+            QuantumCell::Existing(is_start),
+        );
+        is_after_end = gate.add(
+            ctx,
+            QuantumCell::Existing(&is_after_end),
+            QuantumCell::Existing(&is_end),
+        );
+        masks.push(gate.sub(
+            ctx,
+            QuantumCell::Existing(&is_after_start),
+            QuantumCell::Existing(&is_after_end),
+        ));
+    }
+    let payload_rlc = rlc.assign_rlc_masked(ctx, signing_input, &masks, challenge)?;
+    Ok(AssignedJwtResult {
+        hash_result,
+        payload_rlc,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{
+        gates::range::{RangeConfig, RangeStrategy::Vertical},
+        SKIP_FIRST_PASS,
+    };
+    use sha2::{Digest, Sha256};
+
+    /// Header and payload of the RS256 example of RFC 7515, appendix A.2.
+    const HEADER: &[u8] = b"eyJhbGciOiJSUzI1NiJ9";
+    const PAYLOAD: &[u8] = b"eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ";
+    const MAX_SIGNING_INPUT_LEN: usize = 128;
+
+    #[derive(Debug, Clone)]
+    struct TestConfig<F: PrimeField> {
+        sha256: Sha256DynamicConfig<F>,
+        rlc: RlcConfig<F>,
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        signing_input: Vec<u8>,
+        signing_input_len: usize,
+        payload_start: usize,
+        expected_digest: Vec<u8>,
+        // the payload RLC and the challenge, once the second phase is synthesized.
+        rlc_and_challenge: RefCell<Option<(F, F)>>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = TestConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[8], &[1], 1, 16, 0, 17);
+            let sha256 = Sha256DynamicConfig::configure(
+                meta,
+                vec![MAX_SIGNING_INPUT_LEN],
+                range_config,
+                8,
+                2,
+                true,
+            );
+            let rlc = RlcConfig::configure(meta);
+            TestConfig { sha256, rlc }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            let mut rlc = config.rlc.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let challenge = layouter.get_challenge(rlc.challenge);
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "jwt test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let mut buffer = self.signing_input.clone();
+                    buffer.resize(MAX_SIGNING_INPUT_LEN, 0);
+                    let signing_input = buffer
+                        .iter()
+                        .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                        .collect_vec();
+                    let signing_input_len = gate
+                        .load_witness(ctx, Value::known(F::from(self.signing_input_len as u64)));
+                    let payload_start =
+                        gate.load_witness(ctx, Value::known(F::from(self.payload_start as u64)));
+                    let result = digest_jwt(
+                        ctx,
+                        &mut sha256,
+                        &mut rlc,
+                        &signing_input,
+                        &signing_input_len,
+                        &payload_start,
+                        challenge,
+                    )?;
+                    for (assigned, byte) in result
+                        .hash_result
+                        .output_bytes
+                        .iter()
+                        .zip(self.expected_digest.iter())
+                    {
+                        gate.assert_is_const(ctx, assigned, F::from(*byte as u64));
+                    }
+                    result
+                        .payload_rlc
+                        .value()
+                        .zip(challenge)
+                        .map(|(rlc, challenge)| {
+                            *self.rlc_and_challenge.borrow_mut() = Some((*rlc, challenge))
+                        });
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    fn test_circuit(signing_input_len: usize, payload_start: usize) -> TestCircuit<Fr> {
+        let signing_input = [HEADER, b".", PAYLOAD].concat();
+        TestCircuit {
+            expected_digest: Sha256::digest(&signing_input).to_vec(),
+            signing_input,
+            signing_input_len,
+            payload_start,
+            rlc_and_challenge: RefCell::new(None),
+        }
+    }
+
+    #[test]
+    fn test_digest_jwt_rs256() {
+        let circuit = test_circuit(HEADER.len() + 1 + PAYLOAD.len(), HEADER.len() + 1);
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let (rlc, challenge) = circuit.rlc_and_challenge.borrow().unwrap();
+        let expected = PAYLOAD.iter().fold(Fr::zero(), |acc, byte| {
+            acc * challenge + Fr::from(*byte as u64)
+        });
+        assert_eq!(rlc, expected);
+    }
+
+    #[test]
+    fn test_digest_jwt_rejects_wrong_bounds() {
+        // the byte before the payload is then the last header character, not the separator.
+        let circuit = test_circuit(HEADER.len() + 1 + PAYLOAD.len(), HEADER.len());
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+        // a truncated signing input does not hash to the digest of the token.
+        let circuit = test_circuit(HEADER.len() + PAYLOAD.len(), HEADER.len() + 1);
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,199 @@
+//! Caches KZG parameters and proving/verifying keys on disk, keyed by the circuit type and the layout of its chip.
+use crate::Sha256ConfigDescriptor;
+use halo2_base::halo2_proofs::{
+    halo2curves::bn256::{Bn256, Fr, G1Affine},
+    plonk::{keygen_pk, keygen_vk, Circuit, ProvingKey, VerifyingKey},
+    poly::{
+        commitment::{Params, ParamsProver},
+        kzg::commitment::ParamsKZG,
+    },
+    SerdeFormat,
+};
+use itertools::Itertools;
+use rand::rngs::OsRng;
+use sha2::{Digest, Sha256};
+use std::{
+    any::type_name,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+const SERDE_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+/// Returns the file name of a key of the circuit `C` for the degree `k`, e.g. `sha256_k17_b2_2_0123456789abcdef.pk`.
+/// The `max_blocks` of `descriptor` keep the name readable, and the last part is a hash of the type
+/// name of `C` and of the whole `descriptor`, so that circuits of another type or chip layout use other files.
+pub fn key_file_name<C: Circuit<Fr>>(
+    k: u32,
+    descriptor: &Sha256ConfigDescriptor,
+    extension: &str,
+) -> String {
+    let blocks = descriptor.max_blocks.iter().join("_");
+    let hash = Sha256::digest(format!("{}\n{:?}", type_name::<C>(), descriptor));
+    format!(
+        "sha256_k{}_b{}_{}.{}",
+        k,
+        blocks,
+        hex::encode(&hash[..8]),
+        extension
+    )
+}
+
+/// Reads the parameters for `k` from `dir`, or samples and writes them if they do not exist.
+/// Sampled parameters are only suitable for testing; use the parameters of a trusted setup in production.
+pub fn load_or_setup_params(dir: &Path, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+    let path = dir.join(format!("params_k{}.bin", k));
+    if path.exists() {
+        return ParamsKZG::read(&mut BufReader::new(File::open(path)?));
+    }
+    let params = ParamsKZG::<Bn256>::setup(k, OsRng);
+    fs::create_dir_all(dir)?;
+    params.write(&mut BufWriter::new(File::create(path)?))?;
+    Ok(params)
+}
+
+/// Reads the proving key of `circuit` from `dir`, or generates and writes it (and its verifying key) if it does not exist.
+/// `descriptor` is the layout of the SHA-256 chip of `circuit`, see `Sha256DynamicConfig::descriptor`.
+/// The verifying key is generated in both cases: a cached key whose verifying key differs, e.g. one written for a
+/// circuit with the same type and chip layout but other columns or constraints, is an `InvalidData` error.
+pub fn load_or_keygen_pk<C: Circuit<Fr>>(
+    dir: &Path,
+    params: &ParamsKZG<Bn256>,
+    circuit: &C,
+    descriptor: &Sha256ConfigDescriptor,
+) -> io::Result<ProvingKey<G1Affine>> {
+    let vk = keygen_vk(params, circuit).map_err(to_io_error)?;
+    let pk_path = key_path::<C>(dir, params.k(), descriptor, "pk");
+    if pk_path.exists() {
+        let pk =
+            ProvingKey::read::<_, C>(&mut BufReader::new(File::open(&pk_path)?), SERDE_FORMAT)?;
+        if pk.get_vk().transcript_repr() != vk.transcript_repr() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "{} was generated for another circuit; delete it to generate the key again",
+                    pk_path.display()
+                ),
+            ));
+        }
+        return Ok(pk);
+    }
+    let pk = keygen_pk(params, vk, circuit).map_err(to_io_error)?;
+    fs::create_dir_all(dir)?;
+    pk.write(&mut BufWriter::new(File::create(pk_path)?), SERDE_FORMAT)?;
+    let vk_path = key_path::<C>(dir, params.k(), descriptor, "vk");
+    pk.get_vk()
+        .write(&mut BufWriter::new(File::create(vk_path)?), SERDE_FORMAT)?;
+    Ok(pk)
+}
+
+/// Reads the verifying key written by `load_or_keygen_pk`.
+pub fn read_vk<C: Circuit<Fr>>(
+    dir: &Path,
+    k: u32,
+    descriptor: &Sha256ConfigDescriptor,
+) -> io::Result<VerifyingKey<G1Affine>> {
+    let vk_path = key_path::<C>(dir, k, descriptor, "vk");
+    VerifyingKey::read::<_, C>(&mut BufReader::new(File::open(vk_path)?), SERDE_FORMAT)
+}
+
+fn key_path<C: Circuit<Fr>>(
+    dir: &Path,
+    k: u32,
+    descriptor: &Sha256ConfigDescriptor,
+    extension: &str,
+) -> PathBuf {
+    dir.join(key_file_name::<C>(k, descriptor, extension))
+}
+
+fn to_io_error<E: std::fmt::Debug>(e: E) -> io::Error {
+    io::Error::new(ErrorKind::Other, format!("{:?}", e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::preimage::Sha256PreimageCircuit;
+    use halo2_base::halo2_proofs::plonk::ConstraintSystem;
+
+    type SmallCircuit = Sha256PreimageCircuit<Fr, 100>;
+    type LargeCircuit = Sha256PreimageCircuit<Fr, 200>;
+
+    fn descriptor<const MAX_BYTE_SIZE: usize>() -> Sha256ConfigDescriptor {
+        let mut meta = ConstraintSystem::default();
+        Sha256PreimageCircuit::<Fr, MAX_BYTE_SIZE>::configure(&mut meta)
+            .sha256
+            .descriptor()
+    }
+
+    /// A fresh directory under the temporary directory, removed by the caller.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "halo2-dynamic-sha256-keys-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_key_round_trip() {
+        let dir = temp_dir("round-trip");
+        let k = SmallCircuit::K;
+        let params = load_or_setup_params(&dir, k).unwrap();
+        let descriptor = descriptor::<100>();
+        let circuit = SmallCircuit::new(b"abc".to_vec());
+        let pk = load_or_keygen_pk(&dir, &params, &circuit, &descriptor).unwrap();
+        assert!(dir
+            .join(key_file_name::<SmallCircuit>(k, &descriptor, "pk"))
+            .exists());
+
+        // the second call reads the files written by the first one.
+        let params = load_or_setup_params(&dir, k).unwrap();
+        let cached_pk = load_or_keygen_pk(&dir, &params, &circuit, &descriptor).unwrap();
+        let vk = read_vk::<SmallCircuit>(&dir, k, &descriptor).unwrap();
+        assert_eq!(
+            cached_pk.get_vk().transcript_repr(),
+            pk.get_vk().transcript_repr()
+        );
+        assert_eq!(vk.transcript_repr(), pk.get_vk().transcript_repr());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_changed_config_misses_cache() {
+        let k = SmallCircuit::K;
+        let small = descriptor::<100>();
+        let large = descriptor::<200>();
+        assert_ne!(small, large);
+        let small_name = key_file_name::<SmallCircuit>(k, &small, "pk");
+        assert_ne!(small_name, key_file_name::<LargeCircuit>(k, &large, "pk"));
+        assert_ne!(
+            small_name,
+            key_file_name::<SmallCircuit>(k + 1, &small, "pk")
+        );
+        // every field of the descriptor and the circuit type are part of the name.
+        let range_checked = Sha256ConfigDescriptor {
+            is_input_range_check: !small.is_input_range_check,
+            ..small.clone()
+        };
+        assert_ne!(
+            small_name,
+            key_file_name::<SmallCircuit>(k, &range_checked, "pk")
+        );
+        assert_ne!(small_name, key_file_name::<LargeCircuit>(k, &small, "pk"));
+
+        let dir = temp_dir("changed-config");
+        let params = load_or_setup_params(&dir, k).unwrap();
+        let circuit = SmallCircuit::new(b"abc".to_vec());
+        load_or_keygen_pk(&dir, &params, &circuit, &small).unwrap();
+        assert!(read_vk::<LargeCircuit>(&dir, k, &large).is_err());
+        // a circuit with the same type and chip layout but another verifying key is rejected, not loaded.
+        let public_circuit = SmallCircuit::new_public_message(b"abc".to_vec());
+        let err = load_or_keygen_pk(&dir, &params, &public_circuit, &small).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+//! Export of the cells assigned by a circuit, for audits and for diffing the layout across versions.
+//!
+//! `MockProver` keeps its assignment private, so `record_layout` synthesizes the circuit with its own floor planner
+//! into a recorder that keeps the annotation, column and row of every advice and fixed cell, like `MockProver` does.
+//! Only the positions are recorded, not the values.
+use alloc::{format, string::String, vec::Vec};
+use core::marker::PhantomData;
+
+use halo2_base::halo2_proofs::{
+    circuit::{FloorPlanner, Value},
+    plonk::{
+        Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error,
+        Fixed, Instance, Selector,
+    },
+};
+use halo2_base::utils::PrimeField;
+
+/// Kind of the column of a `CellRecord`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Advice,
+    Fixed,
+}
+
+impl ColumnKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ColumnKind::Advice => "advice",
+            ColumnKind::Fixed => "fixed",
+        }
+    }
+}
+
+/// A cell assigned during synthesis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellRecord {
+    /// Name of the region the cell was assigned in.
+    pub region: String,
+    /// Annotation of the assignment, e.g. `"message schedule word 17: dense limb in column 0"` for a spread limb.
+    pub annotation: String,
+    pub column_kind: ColumnKind,
+    /// Index of the column among the columns of its kind.
+    pub column_index: usize,
+    pub row: usize,
+}
+
+struct LayoutRecorder<F: PrimeField> {
+    cells: Vec<CellRecord>,
+    region: String,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField> LayoutRecorder<F> {
+    fn record(
+        &mut self,
+        annotation: String,
+        column_kind: ColumnKind,
+        column_index: usize,
+        row: usize,
+    ) {
+        self.cells.push(CellRecord {
+            region: self.region.clone(),
+            annotation,
+            column_kind,
+            column_index,
+            row,
+        });
+    }
+}
+
+impl<F: PrimeField> Assignment<F> for LayoutRecorder<F> {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.region = name_fn().into();
+    }
+
+    fn exit_region(&mut self) {
+        self.region.clear();
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.record(annotation().into(), ColumnKind::Advice, column.index(), row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.record(annotation().into(), ColumnKind::Fixed, column.index(), row);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// Synthesizes `circuit` and returns its advice and fixed cells in assignment order.
+/// Lookup tables are assigned outside of regions, so their cells have an empty `region`.
+pub fn record_layout<F: PrimeField, C: Circuit<F>>(circuit: &C) -> Result<Vec<CellRecord>, Error> {
+    let mut meta = ConstraintSystem::default();
+    let config = C::configure(&mut meta);
+    let mut recorder = LayoutRecorder {
+        cells: Vec::new(),
+        region: String::new(),
+        _f: PhantomData,
+    };
+    C::FloorPlanner::synthesize(&mut recorder, circuit, config, meta.constants().clone())?;
+    Ok(recorder.cells)
+}
+
+fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        String::from(field)
+    }
+}
+
+fn escape_json(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Returns `cells` as CSV, one cell per line after the `region,annotation,column_kind,column_index,row` header.
+pub fn to_csv(cells: &[CellRecord]) -> String {
+    let mut csv = String::from("region,annotation,column_kind,column_index,row\n");
+    for cell in cells.iter() {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            escape_csv(&cell.region),
+            escape_csv(&cell.annotation),
+            cell.column_kind.as_str(),
+            cell.column_index,
+            cell.row
+        ));
+    }
+    csv
+}
+
+/// Returns `cells` as a JSON array of objects with the fields of `CellRecord`.
+pub fn to_json(cells: &[CellRecord]) -> String {
+    let objects = cells
+        .iter()
+        .map(|cell| {
+            format!(
+                "{{\"region\":\"{}\",\"annotation\":\"{}\",\"column_kind\":\"{}\",\"column_index\":{},\"row\":{}}}",
+                escape_json(&cell.region),
+                escape_json(&cell.annotation),
+                cell.column_kind.as_str(),
+                cell.column_index,
+                cell.row
+            )
+        })
+        .collect::<Vec<_>>();
+    format!("[{}]", objects.join(","))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::preimage::Sha256PreimageCircuit;
+    use halo2_base::halo2_proofs::halo2curves::bn256::Fr;
+
+    #[test]
+    fn test_record_layout() {
+        let circuit = Sha256PreimageCircuit::<Fr, 100>::new(b"abc".to_vec());
+        let cells = record_layout(&circuit).unwrap();
+        let num_rows = 1 << Sha256PreimageCircuit::<Fr, 100>::K;
+        assert!(cells.iter().all(|cell| cell.row < num_rows));
+        // the spread limbs are annotated with the step of the hash that looked them up.
+        assert!(cells.iter().any(|cell| cell.region == "sha256 preimage"
+            && cell
+                .annotation
+                .starts_with("message schedule word 17: dense limb")));
+        // every advice cell is assigned once.
+        let mut positions = cells
+            .iter()
+            .filter(|cell| cell.column_kind == ColumnKind::Advice)
+            .map(|cell| (cell.column_index, cell.row))
+            .collect::<Vec<_>>();
+        let num_advice = positions.len();
+        positions.sort_unstable();
+        positions.dedup();
+        assert_eq!(positions.len(), num_advice);
+
+        let csv = to_csv(&cells);
+        assert_eq!(csv.lines().count(), cells.len() + 1);
+        let json = to_json(&cells);
+        assert!(json.starts_with("[{\"region\":") && json.ends_with("}]"));
+        assert_eq!(json.matches("\"row\":").count(), cells.len());
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape_csv("σ0, word 3"), "\"σ0, word 3\"");
+        assert_eq!(escape_csv("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(escape_json("a \"b\"\\\n"), "a \\\"b\\\"\\\\\\n");
+    }
+}
@@ -1,13 +1,55 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+extern crate alloc;
+
+pub mod base64;
+pub mod batch;
+pub mod bits;
+pub mod bitwise;
+pub mod blake2s;
+pub mod byte_lookup;
+pub mod commitment;
 mod compression;
-pub(crate) mod spread;
-pub(crate) mod utils;
+mod descriptor;
+pub mod endian;
+pub mod hasher;
+pub mod jwt;
+#[cfg(feature = "std")]
+pub mod keys;
+pub mod layout;
+pub mod merkle_sum;
+pub mod mmr;
+pub mod nmt;
+pub mod padding;
+pub mod pkcs1;
+pub mod preimage;
+pub mod prf;
+pub mod reference;
+pub mod ripemd160;
+pub mod rlc;
+#[cfg(feature = "legacy-sha1")]
+pub mod sha1;
+pub mod sha512;
+pub mod smt;
+pub mod spread;
+pub mod ssz;
+mod stats;
+pub mod stream;
+#[cfg(test)]
+mod test_utils;
+pub mod transcript;
+pub mod x509;
 pub use compression::*;
+pub use descriptor::*;
+pub use spread::SpreadConfig;
+pub use stats::*;
 // pub use eth_types::Field;
 // pub use zkevm_circuits::sha256_circuit::{
 //     sha256_compression::{Sha256AssignedRows, Sha256CompressionConfig},
 //     util::H,
 // };
 
+use alloc::{vec, vec::Vec};
+use bits::{assigned_to_u32, assigned_to_u8};
 use generic_array::GenericArray;
 use halo2_base::halo2_proofs::{
     circuit::{AssignedCell, Cell, Layouter, Region, SimpleFloorPlanner, Value},
@@ -28,19 +70,33 @@ use halo2_base::{
 use hex;
 use itertools::Itertools;
 use sha2::{compress256, Digest, Sha256};
-use spread::SpreadConfig;
 
 // const Sha256BitChipRowPerRound: usize = 72;
 // const BLOCK_BYTE: usize = 64;
 // const DIGEST_BYTE: usize = 32;
 
+/// Minimum capacity in bits of the field. The compression gates stay below `2^67`, but `is_digest_less_than` compares
+/// 128-bit halves of the digest with `is_less_than`, which range-checks a 129-bit difference; on a smaller field those
+/// constraints would wrap around the modulus and no longer be sound.
+pub const MIN_FIELD_CAPACITY: u32 = 129;
+
+/// Cells assigned by `digest`. They live in advice columns; to source any of them from public inputs, bind their
+/// cells to an instance column with `Layouter::constrain_instance` once the region is assigned.
 #[derive(Debug, Clone)]
 pub struct AssignedHashResult<'a, F: PrimeField> {
     pub input_len: AssignedValue<'a, F>,
     pub input_bytes: Vec<AssignedValue<'a, F>>,
+    pub output_words: Vec<AssignedValue<'a, F>>,
     pub output_bytes: Vec<AssignedValue<'a, F>>,
 }
 
+/// Chip hashing inputs of dynamic length in a single halo2-base `Context`.
+///
+/// Witnesses are computed while cells are assigned, in the order the gates consume them: every block depends on the
+/// state of the previous one and the `Context` and `SpreadConfig` both keep a single row cursor, so blocks and the
+/// message schedule cannot be assigned from worker threads. To use several cores, split independent inputs across
+/// circuits (e.g. one `Sha256BatchCircuit` per core) and prove them in parallel. The config only holds column
+/// handles and counters, so it is `Send + Sync` and a prover service can share it across worker threads.
 #[derive(Debug, Clone)]
 pub struct Sha256DynamicConfig<F: PrimeField> {
     pub max_variable_byte_sizes: Vec<usize>,
@@ -48,10 +104,27 @@ pub struct Sha256DynamicConfig<F: PrimeField> {
     spread_config: SpreadConfig<F>,
     pub cur_hash_idx: usize,
     is_input_range_check: bool,
+    stats: Sha256Stats,
 }
 
 impl<F: PrimeField> Sha256DynamicConfig<F> {
     const ONE_ROUND_INPUT_BYTES: usize = 64;
+    /// Evaluated when `configure` is instantiated for `F`, so that a too small field fails to compile.
+    const FIELD_CAPACITY_CHECK: () = assert!(
+        F::CAPACITY >= MIN_FIELD_CAPACITY,
+        "the field is too small for Sha256DynamicConfig, see MIN_FIELD_CAPACITY"
+    );
+    /// Columns and arguments are added to `meta` in a fixed order, after those of `range`:
+    /// 1. `num_advice_columns` dense advice columns, then `num_advice_columns` spread advice columns;
+    /// 2. equality on every dense column, then on every spread column, in allocation order;
+    /// 3. the dense table column, then the spread table column;
+    /// 4. one "spread lookup" argument per (dense, spread) pair, in allocation order.
+    ///
+    /// No selector, fixed column or custom gate is added. The order determines the column indexes and the order of the
+    /// permutation and lookup arguments, so changing it changes the verifying key: `test_column_allocation_order` pins
+    /// the indexes and `test_golden_vk` pins the key of the test circuit against `testdata/vk_k17.txt`.
+    /// The chip adds no custom gate and its lookups only query advice columns, so it keeps the constraint system at
+    /// degree 4 as long as `range` is configured with at least one lookup advice column.
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         max_variable_byte_sizes: Vec<usize>,
@@ -60,6 +133,7 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
         num_advice_columns: usize,
         is_input_range_check: bool,
     ) -> Self {
+        let () = Self::FIELD_CAPACITY_CHECK;
         for byte in max_variable_byte_sizes.iter() {
             debug_assert_eq!(byte % Self::ONE_ROUND_INPUT_BYTES, 0);
         }
@@ -71,16 +145,436 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
             spread_config,
             cur_hash_idx: 0,
             is_input_range_check,
+            stats: Sha256Stats::default(),
+        }
+    }
+
+    /// Same as `configure`, but places the spread lookups in the caller's `dense_columns` and `spread_columns`
+    /// so they can be shared with other chips. See `SpreadConfig::configure_with_columns` for `table_columns`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn configure_with_columns(
+        meta: &mut ConstraintSystem<F>,
+        max_variable_byte_sizes: Vec<usize>,
+        range: RangeConfig<F>,
+        num_bits_lookup: usize,
+        dense_columns: Vec<Column<Advice>>,
+        spread_columns: Vec<Column<Advice>>,
+        table_columns: Option<(TableColumn, TableColumn)>,
+        is_input_range_check: bool,
+    ) -> Self {
+        let () = Self::FIELD_CAPACITY_CHECK;
+        for byte in max_variable_byte_sizes.iter() {
+            debug_assert_eq!(byte % Self::ONE_ROUND_INPUT_BYTES, 0);
+        }
+        let spread_config = SpreadConfig::configure_with_columns(
+            meta,
+            num_bits_lookup,
+            dense_columns,
+            spread_columns,
+            table_columns,
+        );
+        Self {
+            max_variable_byte_sizes,
+            range,
+            spread_config,
+            cur_hash_idx: 0,
+            is_input_range_check,
+            stats: Sha256Stats::default(),
         }
     }
 
+    /// Returns the SHA-256 digest of `input`, which may have any byte length up to the next entry of
+    /// `max_variable_byte_sizes` (after padding, and excluding the `precomputed_input_len` bytes, a multiple of 64).
+    /// Every byte position after the input is constrained by the padding, so the input need not be word-aligned.
     pub fn digest<'a, 'b: 'a>(
         &'a mut self,
         ctx: &mut Context<'b, F>,
         input: &'a [u8],
         precomputed_input_len: Option<usize>,
     ) -> Result<AssignedHashResult<'b, F>, Error> {
-        let input_byte_size = input.len();
+        self.digest_with_midstate(ctx, input, None, precomputed_input_len, false, None, None)
+    }
+
+    /// Same as `digest`, but `output_bytes` only holds the first `n_bytes` bytes of the digest, e.g. 20 bytes for an
+    /// address or 31 bytes to fit a field element.
+    /// The bytes come from the decomposition of `output_words`, so each one is range-checked and constrained to the
+    /// digest words; callers should use them rather than re-assigning a truncated digest from witness values.
+    pub fn digest_truncated<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        precomputed_input_len: Option<usize>,
+        n_bytes: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert!(n_bytes > 0 && n_bytes <= 32);
+        let mut result = self.digest(ctx, input, precomputed_input_len)?;
+        result.output_bytes.truncate(n_bytes);
+        Ok(result)
+    }
+
+    /// Returns the SHA-256 digest of the first `bit_len` bits of `input`, which must have `ceil(bit_len / 8)` bytes.
+    /// The bits of the last byte are taken from the most significant one and the unused bits are ignored.
+    /// The number of trailing bits is a private witness, so the circuit is the same for every `bit_len`;
+    /// `AssignedHashResult::input_len` is the number of whole bytes.
+    pub fn digest_bits<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &'a [u8],
+        bit_len: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert_eq!(input.len(), (bit_len + 7) / 8);
+        self.digest_with_midstate(ctx, input, Some(bit_len), None, false, None, None)
+    }
+
+    /// Assigns the number of trailing bits of a bit-granular input and returns the byte that ends the input,
+    /// i.e. the trailing bits followed by a 1 bit and zeros, and the bit length of the input.
+    fn assign_trailing_bits<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        input_len: &AssignedValue<'a, F>,
+        start_byte: u8,
+        num_trailing_bits: usize,
+    ) -> (AssignedValue<'a, F>, AssignedValue<'a, F>) {
+        let range = self.range();
+        let gate = range.gate();
+        let assigned_num_trailing_bits =
+            gate.load_witness(ctx, Value::known(F::from(num_trailing_bits as u64)));
+        range.range_check(ctx, &assigned_num_trailing_bits, 3);
+        let trailing_bits = match num_trailing_bits {
+            0 => 0,
+            _ => start_byte >> (8 - num_trailing_bits),
+        };
+        let assigned_trailing_bits =
+            gate.load_witness(ctx, Value::known(F::from(trailing_bits as u64)));
+        range.range_check(ctx, &assigned_trailing_bits, 8);
+        // the 1 bit that follows the trailing bits.
+        let indicators = (0..8)
+            .map(|idx| {
+                gate.is_equal(
+                    ctx,
+                    QuantumCell::Existing(&assigned_num_trailing_bits),
+                    QuantumCell::Constant(F::from(idx as u64)),
+                )
+            })
+            .collect_vec();
+        let end_bit = gate.inner_product(
+            ctx,
+            indicators.iter().map(QuantumCell::Existing),
+            (0..8).map(|idx| QuantumCell::Constant(F::from(0x80u64 >> idx))),
+        );
+        let shift = gate.mul(
+            ctx,
+            QuantumCell::Existing(&end_bit),
+            QuantumCell::Constant(F::from(2u64)),
+        );
+        // `shift * trailing_bits` is below 256 for a byte-sized start byte, so `trailing_bits` fits the trailing bits.
+        let assigned_start_byte = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&assigned_trailing_bits),
+            QuantumCell::Existing(&shift),
+            QuantumCell::Existing(&end_bit),
+        );
+        let assigned_bit_len = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(input_len),
+            QuantumCell::Constant(F::from(8u64)),
+            QuantumCell::Existing(&assigned_num_trailing_bits),
+        );
+        (assigned_start_byte, assigned_bit_len)
+    }
+
+    /// Constrains the bytes of `padded_input` that follow the first `precomputed_input_len` bytes of the input:
+    /// the byte at `input_len` is `start_byte`, the last 8 bytes before `padded_size` are the big-endian `bit_len` and
+    /// the bytes in between are zero. The positions are selected by indicator cells, so `input_len` stays private
+    /// and only the maximum length, fixed by the circuit, is revealed. The bytes after `padded_size` are unconstrained.
+    #[allow(clippy::too_many_arguments)]
+    fn constrain_padding<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        padded_input: &[AssignedValue<'a, F>],
+        precomputed_input_len: usize,
+        input_len: &AssignedValue<'a, F>,
+        start_byte: &AssignedValue<'a, F>,
+        bit_len: &AssignedValue<'a, F>,
+        padded_size: &AssignedValue<'a, F>,
+    ) {
+        let range = self.range();
+        let gate = range.gate();
+        // the input may not end inside the precomputed bytes.
+        let variable_len = gate.sub(
+            ctx,
+            QuantumCell::Existing(input_len),
+            QuantumCell::Constant(F::from(precomputed_input_len as u64)),
+        );
+        let num_len_bits = (usize::BITS - padded_input.len().leading_zeros()) as usize;
+        range.range_check(ctx, &variable_len, num_len_bits);
+
+        let bit_len_value = bit_len.value().map(|v| v.get_lower_128() as u64);
+        let len_bytes = (0..8)
+            .map(|idx| {
+                let byte = bit_len_value.map(|v| F::from((v >> (8 * (7 - idx))) & 0xff));
+                gate.load_witness(ctx, byte)
+            })
+            .collect_vec();
+        for byte in len_bytes.iter() {
+            range.range_check(ctx, byte, 8);
+        }
+        let composed_bit_len = gate.inner_product(
+            ctx,
+            len_bytes.iter().map(QuantumCell::Existing),
+            (0..8).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (7 - idx))))),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&composed_bit_len),
+            QuantumCell::Existing(bit_len),
+        );
+
+        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
+        let len_offset = one_round_size - 8;
+        // `is_after_start` and `is_after_len_start` are 1 from the start byte and from the first length byte on.
+        let mut is_after_start = gate.load_zero(ctx);
+        let mut is_after_len_start = gate.load_zero(ctx);
+        let mut is_len_start = gate.load_zero(ctx);
+        for (idx, byte) in padded_input.iter().enumerate() {
+            let pos = precomputed_input_len + idx;
+            let is_start = gate.is_equal(
+                ctx,
+                QuantumCell::Existing(input_len),
+                QuantumCell::Constant(F::from(pos as u64)),
+            );
+            is_after_start = gate.add(
+                ctx,
+                QuantumCell::Existing(&is_after_start),
+                QuantumCell::Existing(&is_start),
+            );
+            let offset = pos % one_round_size;
+            if offset == len_offset {
+                is_len_start = gate.is_equal(
+                    ctx,
+                    QuantumCell::Existing(padded_size),
+                    QuantumCell::Constant(F::from((pos + 8) as u64)),
+                );
+                is_after_len_start = gate.add(
+                    ctx,
+                    QuantumCell::Existing(&is_after_len_start),
+                    QuantumCell::Existing(&is_len_start),
+                );
+            }
+            // 1 on the start byte, the zero bytes and the length bytes.
+            let mut is_padding = gate.sub(
+                ctx,
+                QuantumCell::Existing(&is_after_start),
+                QuantumCell::Existing(&is_after_len_start),
+            );
+            let mut expected = gate.mul(
+                ctx,
+                QuantumCell::Existing(&is_start),
+                QuantumCell::Existing(start_byte),
+            );
+            if offset >= len_offset {
+                is_padding = gate.add(
+                    ctx,
+                    QuantumCell::Existing(&is_padding),
+                    QuantumCell::Existing(&is_len_start),
+                );
+                expected = gate.mul_add(
+                    ctx,
+                    QuantumCell::Existing(&is_len_start),
+                    QuantumCell::Existing(&len_bytes[offset - len_offset]),
+                    QuantumCell::Existing(&expected),
+                );
+            }
+            let diff = gate.sub(
+                ctx,
+                QuantumCell::Existing(byte),
+                QuantumCell::Existing(&expected),
+            );
+            let masked_diff = gate.mul(
+                ctx,
+                QuantumCell::Existing(&is_padding),
+                QuantumCell::Existing(&diff),
+            );
+            gate.assert_is_const(ctx, &masked_diff, F::zero());
+        }
+    }
+
+    /// Same as `digest`, but copy-constrains the state after the first `precomputed_input_len` bytes of `input`
+    /// to the eight 32-bit words of `midstate`, e.g. a midstate verified elsewhere in the circuit.
+    /// The witness of the state is read from `midstate`, so the precomputed bytes are not used.
+    pub fn digest_with_assigned_midstate<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        midstate: &[AssignedValue<'b, F>],
+        input: &[u8],
+        precomputed_input_len: usize,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        self.digest_with_midstate(
+            ctx,
+            input,
+            None,
+            Some(precomputed_input_len),
+            false,
+            Some(midstate),
+            None,
+        )
+    }
+
+    /// Returns the digest of a message made of `prefix_len` bytes, a multiple of 64, followed by `input`, when the
+    /// prefix is only known through `midstate`, the state after it as returned by `reference::midstate`.
+    /// The prefix blocks are not proven: `midstate` should be bound to public inputs so that the verifier checks it,
+    /// e.g. against a midstate exported once for a prefix shared by many proofs.
+    pub fn digest_from_midstate<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        midstate: &[AssignedValue<'b, F>],
+        prefix_len: usize,
+        input: &[u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let mut message = vec![0u8; prefix_len];
+        message.extend_from_slice(input);
+        self.digest_with_assigned_midstate(ctx, midstate, &message, prefix_len)
+    }
+
+    /// Same as `digest_from_midstate`, but the length of the prefix is the assigned `prefix_len`, e.g. a public input,
+    /// so one circuit finishes the hash of messages with any prefix. `prefix_len` is constrained to a multiple of 64.
+    /// `AssignedHashResult::input_len` is the length of `input` only.
+    pub fn digest_from_midstate_with_assigned_len<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        midstate: &[AssignedValue<'b, F>],
+        prefix_len: &AssignedValue<'b, F>,
+        input: &[u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = self.range();
+        let gate = range.gate();
+        let num_prefix_blocks = prefix_len
+            .value()
+            .map(|v| F::from(v.get_lower_128() as u64 / Self::ONE_ROUND_INPUT_BYTES as u64));
+        let num_prefix_blocks = gate.load_witness(ctx, num_prefix_blocks);
+        range.range_check(ctx, &num_prefix_blocks, 32);
+        let composed = gate.mul(
+            ctx,
+            QuantumCell::Existing(&num_prefix_blocks),
+            QuantumCell::Constant(F::from(Self::ONE_ROUND_INPUT_BYTES as u64)),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&composed),
+            QuantumCell::Existing(prefix_len),
+        );
+        self.digest_with_midstate(
+            ctx,
+            input,
+            None,
+            None,
+            false,
+            Some(midstate),
+            Some(prefix_len),
+        )
+    }
+
+    /// Computes the BIP-340 tagged hash `SHA256(SHA256(tag) || SHA256(tag) || msg)`.
+    /// The first block only depends on the static `tag`, so its output state is assigned as constants.
+    pub fn tagged_hash<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        tag: &[u8],
+        msg: &[u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let tag_hash = Sha256::digest(tag);
+        let mut input = tag_hash.to_vec();
+        input.extend_from_slice(&tag_hash);
+        input.extend_from_slice(msg);
+        self.digest_with_midstate(
+            ctx,
+            &input,
+            None,
+            Some(Self::ONE_ROUND_INPUT_BYTES),
+            true,
+            None,
+            None,
+        )
+    }
+
+    /// Returns the SHA-256 digest of `prefix || input`, where `prefix` is fixed by the circuit, e.g. a protocol header.
+    /// The whole 64-byte blocks of `prefix` are compressed outside the circuit and their output state is assigned as
+    /// constants, so they take no compression and no entry of `max_variable_byte_sizes`. The rest of `prefix` is hashed
+    /// in the circuit with its bytes constrained to the constants; the length of `input` may vary as in `digest`.
+    pub fn digest_with_constant_prefix<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        prefix: &[u8],
+        input: &[u8],
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        let precomputed_input_len =
+            prefix.len() / Self::ONE_ROUND_INPUT_BYTES * Self::ONE_ROUND_INPUT_BYTES;
+        let mut message = prefix.to_vec();
+        message.extend_from_slice(input);
+        let result = self.digest_with_midstate(
+            ctx,
+            &message,
+            None,
+            Some(precomputed_input_len),
+            true,
+            None,
+            None,
+        )?;
+        // the prefix must not overlap the padding; the padding constraints already cover its whole blocks.
+        if prefix.len() > precomputed_input_len {
+            let is_shorter_than_prefix =
+                range.is_less_than_safe(ctx, &result.input_len, prefix.len() as u64);
+            gate.assert_is_const(ctx, &is_shorter_than_prefix, F::zero());
+        }
+        for (byte, assigned) in prefix[precomputed_input_len..]
+            .iter()
+            .zip(result.input_bytes.iter())
+        {
+            gate.assert_is_const(ctx, assigned, F::from(*byte as u64));
+        }
+        Ok(result)
+    }
+
+    /// If `is_const_midstate` is true, the state after `precomputed_input_len` bytes is assigned as constants instead of witnesses.
+    /// Without precomputed input the state is the standard IV, which is always assigned as constants.
+    /// If `assigned_midstate` is given, the state is copy-constrained to its cells.
+    /// If `bit_len` is given, only the first `bit_len` bits of `input` are hashed.
+    /// If `assigned_prefix_len` is given, `assigned_midstate` is the state after that many bytes, which are not part of
+    /// `input`, and the length encoded in the padding is the sum of both lengths.
+    #[allow(clippy::too_many_arguments)]
+    fn digest_with_midstate<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[u8],
+        bit_len: Option<usize>,
+        precomputed_input_len: Option<usize>,
+        is_const_midstate: bool,
+        assigned_midstate: Option<&[AssignedValue<'b, F>]>,
+        assigned_prefix_len: Option<&AssignedValue<'b, F>>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        assert!(
+            assigned_prefix_len.is_none() || (assigned_midstate.is_some() && bit_len.is_none())
+        );
+        let mut prefix_byte_size = 0;
+        if let Some(prefix_len) = assigned_prefix_len {
+            prefix_len
+                .value()
+                .map(|v| prefix_byte_size = v.get_lower_128() as usize);
+        }
+        // the byte at `input_byte_size` holds the trailing bits, if any, followed by a 1 bit.
+        let (input_byte_size, num_trailing_bits) = match bit_len {
+            Some(bit_len) => (bit_len / 8, bit_len % 8),
+            None => (input.len(), 0),
+        };
+        let start_byte = match num_trailing_bits {
+            0 => 0x80,
+            _ => {
+                (input[input_byte_size] & !(0xff >> num_trailing_bits))
+                    | (0x80 >> num_trailing_bits)
+            }
+        };
         let input_byte_size_with_9 = input_byte_size + 9;
         let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
         let num_round = if input_byte_size_with_9 % one_round_size == 0 {
@@ -94,6 +588,9 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
         let precomputed_input_len = precomputed_input_len.unwrap_or(0);
         assert_eq!(precomputed_input_len % one_round_size, 0);
         assert!(padded_size - precomputed_input_len <= max_variable_byte_size);
+        // a witnessed IV would let the prover start from an arbitrary state.
+        let is_const_midstate =
+            is_const_midstate || (precomputed_input_len == 0 && assigned_prefix_len.is_none());
         let zero_padding_byte_size = padded_size - input_byte_size_with_9;
         let remaining_byte_size = max_variable_byte_size + precomputed_input_len - padded_size;
         let precomputed_round = precomputed_input_len / one_round_size;
@@ -101,13 +598,14 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
             remaining_byte_size,
             one_round_size * (max_variable_round + precomputed_round - num_round)
         );
-        let mut padded_inputs = input.to_vec();
-        padded_inputs.push(0x80);
+        let mut padded_inputs = input[0..input_byte_size].to_vec();
+        padded_inputs.push(start_byte);
         for _ in 0..zero_padding_byte_size {
             padded_inputs.push(0);
         }
         let mut input_len_bytes = [0; 8];
-        let le_size_bytes = (8 * input_byte_size).to_le_bytes();
+        let le_size_bytes =
+            (8 * (prefix_byte_size + input_byte_size) + num_trailing_bits).to_le_bytes();
         input_len_bytes[0..le_size_bytes.len()].copy_from_slice(&le_size_bytes);
         for byte in input_len_bytes.iter().rev() {
             padded_inputs.push(*byte);
@@ -129,6 +627,32 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
         let gate = range.gate();
         let assigned_input_byte_size =
             gate.load_witness(ctx, Value::known(F::from(input_byte_size as u64)));
+        let (assigned_start_byte, assigned_bit_len) = match bit_len {
+            Some(_) => self.assign_trailing_bits(
+                ctx,
+                &assigned_input_byte_size,
+                start_byte,
+                num_trailing_bits,
+            ),
+            None => {
+                let total_len = match assigned_prefix_len {
+                    Some(prefix_len) => gate.add(
+                        ctx,
+                        QuantumCell::Existing(&assigned_input_byte_size),
+                        QuantumCell::Existing(prefix_len),
+                    ),
+                    None => assigned_input_byte_size.clone(),
+                };
+                (
+                    gate.load_constant(ctx, F::from(0x80u64)),
+                    gate.mul(
+                        ctx,
+                        QuantumCell::Existing(&total_len),
+                        QuantumCell::Constant(F::from(8u64)),
+                    ),
+                )
+            }
+        };
         let assigned_num_round = gate.load_witness(ctx, Value::known(F::from(num_round as u64)));
         let assigned_padded_size = gate.mul(
             ctx,
@@ -148,27 +672,50 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
         let padding_is_less_than_round =
             range.is_less_than_safe(ctx, &padding_size, one_round_size as u64);
         gate.assert_is_const(ctx, &padding_is_less_than_round, F::one());
-        let assigned_precomputed_round =
-            gate.load_witness(ctx, Value::known(F::from(precomputed_round as u64)));
+        let assigned_precomputed_round = gate.load_constant(ctx, F::from(precomputed_round as u64));
         let assigned_target_round = gate.sub(
             ctx,
             QuantumCell::Existing(&assigned_num_round),
             QuantumCell::Existing(&assigned_precomputed_round),
         );
 
-        // compute an initial state from the precomputed_input.
-        let precomputed_input = &padded_inputs[0..precomputed_input_len];
+        // compute an initial state from the precomputed_input, unless it is given by the assigned midstate.
         let mut last_state = INIT_STATE;
-        let precomputed_blocks = precomputed_input
-            .chunks(one_round_size)
-            .map(|bytes| GenericArray::clone_from_slice(bytes))
-            .collect_vec();
-        compress256(&mut last_state, &precomputed_blocks[..]);
+        match assigned_midstate {
+            Some(assigned_midstate) => {
+                for (state, assigned) in last_state.iter_mut().zip(assigned_midstate.iter()) {
+                    *state = assigned_to_u32(assigned);
+                }
+            }
+            None => {
+                let precomputed_input = &padded_inputs[0..precomputed_input_len];
+                let precomputed_blocks = precomputed_input
+                    .chunks(one_round_size)
+                    .map(|bytes| GenericArray::clone_from_slice(bytes))
+                    .collect_vec();
+                compress256(&mut last_state, &precomputed_blocks[..]);
+            }
+        }
 
         let mut assigned_last_state_vec = vec![last_state
             .iter()
-            .map(|state| gate.load_witness(ctx, Value::known(F::from(*state as u64))))
+            .map(|state| {
+                if is_const_midstate {
+                    gate.load_constant(ctx, F::from(*state as u64))
+                } else {
+                    gate.load_witness(ctx, Value::known(F::from(*state as u64)))
+                }
+            })
             .collect_vec()];
+        if let Some(assigned_midstate) = assigned_midstate {
+            assert_eq!(assigned_midstate.len(), NUM_STATE_WORD);
+            for (state, assigned) in assigned_last_state_vec[0]
+                .iter()
+                .zip(assigned_midstate.iter())
+            {
+                ctx.region.constrain_equal(state.cell(), assigned.cell())?;
+            }
+        }
         // vec![INIT_STATE
         //     .iter()
         //     .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
@@ -182,14 +729,21 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
                 range.range_check(ctx, assigned_byte, 8);
             }
         }
+        self.constrain_padding(
+            ctx,
+            &assigned_input_bytes,
+            precomputed_input_len,
+            &assigned_input_byte_size,
+            &assigned_start_byte,
+            &assigned_bit_len,
+            &assigned_padded_size,
+        );
         let mut num_processed_input = 0;
         while num_processed_input < max_variable_byte_size {
             let assigned_input_word_at_round =
                 &assigned_input_bytes[num_processed_input..(num_processed_input + one_round_size)];
-            let new_assigned_hs_out = sha256_compression(
+            let new_assigned_hs_out = self.compress(
                 ctx,
-                &range,
-                &mut self.spread_config,
                 assigned_input_word_at_round,
                 &assigned_last_state_vec.last().unwrap(),
             )?;
@@ -314,82 +868,528 @@ impl<F: PrimeField> Sha256DynamicConfig<F> {
                 )
             }
         }
-        let output_digest_bytes = output_h_out
-            .into_iter()
-            .flat_map(|assigned_word| {
-                let be_bytes = assigned_word
-                    .value()
-                    .map(|v| v.get_lower_32().to_be_bytes().to_vec());
-                let assigned_bytes = (0..4)
-                    .map(|idx| {
-                        let assigned = gate
-                            .load_witness(ctx, be_bytes.as_ref().map(|vs| F::from(vs[idx] as u64)));
-                        range.range_check(ctx, &assigned, 8);
-                        assigned
-                    })
-                    .collect::<Vec<AssignedValue<F>>>();
-                let mut sum = gate.load_zero(ctx);
-                for (idx, assigned_byte) in assigned_bytes.iter().enumerate() {
-                    sum = gate.mul_add(
-                        ctx,
-                        QuantumCell::Existing(assigned_byte),
-                        QuantumCell::Constant(F::from(1u64 << (24 - 8 * idx))),
-                        QuantumCell::Existing(&sum),
-                    );
-                }
-                gate.assert_equal(
-                    ctx,
-                    QuantumCell::Existing(&assigned_word),
-                    QuantumCell::Existing(&sum),
-                );
-                assigned_bytes
-            })
-            .collect::<Vec<AssignedValue<F>>>();
+        let output_digest_bytes = self.digest_bytes(ctx, &output_h_out);
         let result = AssignedHashResult {
             input_len: assigned_input_byte_size,
             input_bytes: assigned_input_bytes,
+            output_words: output_h_out,
             output_bytes: output_digest_bytes,
         };
         self.cur_hash_idx += 1;
         Ok(result)
     }
 
-    pub fn new_context<'a, 'b>(&'b self, region: Region<'a, F>) -> Context<'a, F> {
-        Context::new(
-            region,
-            ContextParams {
-                max_rows: self.range.gate.max_rows,
-                num_context_ids: 1,
-                fixed_columns: self.range.gate.constants.clone(),
-            },
-        )
+    /// Hashes bytes that are already assigned in the context.
+    /// The assigned bytes are copy-constrained to the hashed input and the input length is fixed to `input.len()`,
+    /// so the circuit only hashes inputs of that length; use `digest_assigned_dynamic` for inputs of variable length.
+    pub fn digest_assigned<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'b, F>],
+        precomputed_input_len: Option<usize>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let input_bytes = input.iter().map(assigned_to_u8).collect_vec();
+        let result = self.digest(ctx, &input_bytes, precomputed_input_len)?;
+        let precomputed_input_len = precomputed_input_len.unwrap_or(0);
+        for (hashed, byte) in result
+            .input_bytes
+            .iter()
+            .zip(input[precomputed_input_len..].iter())
+        {
+            ctx.region.constrain_equal(hashed.cell(), byte.cell())?;
+        }
+        self.range()
+            .gate()
+            .assert_is_const(ctx, &result.input_len, F::from(input.len() as u64));
+        Ok(result)
     }
 
-    pub fn range(&self) -> &RangeConfig<F> {
-        &self.range
+    /// Hashes the first `input_len` bytes of `input`, where `input_len` is an assigned value, e.g. a private witness, so
+    /// one circuit hashes inputs of any length up to `input.len()`. The bytes after `input_len` are not hashed and may
+    /// hold anything, e.g. the zeros a caller pads its buffer with.
+    /// Each hashed byte is constrained to the byte of `input` at the same position as long as the position is below
+    /// `input_len`, which is selected by indicator cells, and `input_len` is constrained to at most `input.len()`.
+    pub fn digest_assigned_dynamic<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'b, F>],
+        input_len: &AssignedValue<'b, F>,
+        precomputed_input_len: Option<usize>,
+    ) -> Result<AssignedHashResult<'b, F>, Error> {
+        let range = self.range().clone();
+        let gate = range.gate();
+        let precomputed_input_len = precomputed_input_len.unwrap_or(0);
+        assert!(precomputed_input_len <= input.len());
+        // an out-of-range witness fails the constraints below; it is clamped so that the witness can be generated.
+        let mut input_byte_size = precomputed_input_len;
+        input_len
+            .value()
+            .map(|v| input_byte_size = v.get_lower_128() as usize);
+        let input_byte_size = input_byte_size.clamp(precomputed_input_len, input.len());
+        let input_bytes = input[..input_byte_size]
+            .iter()
+            .map(assigned_to_u8)
+            .collect_vec();
+        range.check_less_than_safe(ctx, input_len, input.len() as u64 + 1);
+        let result = self.digest(ctx, &input_bytes, Some(precomputed_input_len))?;
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&result.input_len),
+            QuantumCell::Existing(input_len),
+        );
+        // `is_after_end` is 1 from position `input_len` on.
+        let mut is_after_end = gate.load_zero(ctx);
+        for (idx, (hashed, byte)) in result
+            .input_bytes
+            .iter()
+            .zip(input[precomputed_input_len..].iter())
+            .enumerate()
+        {
+            let is_end = gate.is_equal(
+                ctx,
+                QuantumCell::Existing(input_len),
+                QuantumCell::Constant(F::from((precomputed_input_len + idx) as u64)),
+            );
+            is_after_end = gate.add(
+                ctx,
+                QuantumCell::Existing(&is_after_end),
+                QuantumCell::Existing(&is_end),
+            );
+            let diff = gate.sub(
+                ctx,
+                QuantumCell::Existing(hashed),
+                QuantumCell::Existing(byte),
+            );
+            let masked_diff = gate.mul_not(
+                ctx,
+                QuantumCell::Existing(&is_after_end),
+                QuantumCell::Existing(&diff),
+            );
+            gate.assert_is_const(ctx, &masked_diff, F::zero());
+        }
+        Ok(result)
     }
 
-    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        self.spread_config.load(layouter)
+    /// Applies SHA-256 `n` times starting from the 32-byte `seed` and returns the result of every iteration.
+    /// Only the first iteration consumes an entry of `max_variable_byte_sizes`, which must be at least 64 bytes; the
+    /// next ones hash the 32 bytes of the previous digest as `digest_fixed` does, so the chain can be of any length.
+    pub fn hash_chain<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        seed: &[AssignedValue<'b, F>],
+        n: usize,
+    ) -> Result<Vec<AssignedHashResult<'b, F>>, Error> {
+        assert_eq!(seed.len(), 32);
+        if n == 0 {
+            return Ok(vec![]);
+        }
+        let mut results: Vec<AssignedHashResult<'b, F>> =
+            vec![self.digest_assigned(ctx, seed, None)?];
+        for _ in 1..n {
+            let input_bytes = results[results.len() - 1].output_bytes.clone();
+            let output_words = self.digest_fixed_words(ctx, &input_bytes)?;
+            let output_bytes = self.digest_bytes(ctx, &output_words);
+            let input_len = self.range().gate().load_constant(ctx, F::from(32u64));
+            results.push(AssignedHashResult {
+                input_len,
+                input_bytes,
+                output_words,
+                output_bytes,
+            });
+        }
+        Ok(results)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use std::marker::PhantomData;
-
-    use super::*;
-    use halo2_base::halo2_proofs::{
-        circuit::{Cell, Layouter, Region, SimpleFloorPlanner},
-        dev::MockProver,
-        halo2curves::bn256::Fr,
-        plonk::{Circuit, ConstraintSystem, Instance},
-    };
-    use halo2_base::{gates::range::RangeStrategy::Vertical, ContextParams, SKIP_FIRST_PASS};
+    /// Returns the txid of a legacy-serialized Bitcoin transaction, i.e., `SHA256(SHA256(tx))` in reversed byte order.
+    /// The transaction is the first `tx_len` bytes of `tx_bytes`, where `tx_len` is an assigned value, so one circuit
+    /// handles transactions of any length up to `tx_bytes.len()`; see `digest_assigned_dynamic`.
+    /// Consumes two entries of `max_variable_byte_sizes`: one for the transaction and one of at least 64 bytes.
+    pub fn txid<'a, 'b: 'a>(
+        &'a mut self,
+        ctx: &mut Context<'b, F>,
+        tx_bytes: &[AssignedValue<'b, F>],
+        tx_len: &AssignedValue<'b, F>,
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error> {
+        let first = self.digest_assigned_dynamic(ctx, tx_bytes, tx_len, None)?;
+        let second = self.digest_assigned(ctx, &first.output_bytes, None)?;
+        Ok(second.output_bytes.into_iter().rev().collect_vec())
+    }
 
-    use num_bigint::RandomBits;
-    use rand::rngs::OsRng;
-    use rand::{thread_rng, Rng};
+    /// Compresses a single 64-byte block from the initial state without padding and returns the state as big-endian bytes.
+    /// This is cheaper than `digest` for fixed 64-byte inputs such as Merkle nodes, but it is not the SHA-256 hash of `block`.
+    pub fn compress_init_block<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        block: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let init_state = INIT_STATE
+            .iter()
+            .map(|h| self.range().gate().load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        let output_words = self.compress_with_state(ctx, &init_state, block)?;
+        Ok(self.digest_bytes(ctx, &output_words))
+    }
+
+    /// Compresses a single 64-byte `block` from `state_in` and returns the output state, without IV, padding or
+    /// digest encoding, so that custom Merkle-Damgård or tree modes can drive the compression function directly.
+    /// The block bytes are range-checked if the chip was configured with `is_input_range_check`.
+    pub fn compress_block<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        state_in: &State<'a, F>,
+        block: &[AssignedValue<'a, F>],
+    ) -> Result<State<'a, F>, Error> {
+        let words = self.compress_with_state(ctx, state_in.words(), block)?;
+        Ok(State::new(words))
+    }
+
+    /// Compresses a single 64-byte block from `state_in` without padding and returns the output state words.
+    pub(crate) fn compress_with_state<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        state_in: &[AssignedValue<'a, F>],
+        block: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        assert_eq!(state_in.len(), NUM_STATE_WORD);
+        assert_eq!(block.len(), Self::ONE_ROUND_INPUT_BYTES);
+        if self.is_input_range_check {
+            for assigned_byte in block.iter() {
+                self.range.range_check(ctx, assigned_byte, 8);
+            }
+        }
+        self.compress(ctx, block, state_in)
+    }
+
+    fn compress<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        block: &[AssignedValue<'a, F>],
+        state_in: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let num_advice = ctx.total_advice;
+        let num_range_lookup = ctx.cells_to_lookup.len();
+        let num_spread_lookup = self.spread_config.num_lookups();
+        let num_spread_rows = self.spread_config.num_rows();
+        let state_out =
+            sha256_compression(ctx, &self.range, &mut self.spread_config, block, state_in)?;
+        self.stats.num_compressions += 1;
+        self.stats.advice_cells += ctx.total_advice - num_advice;
+        self.stats.range_lookup_cells += ctx.cells_to_lookup.len() - num_range_lookup;
+        self.stats.spread_lookups += self.spread_config.num_lookups() - num_spread_lookup;
+        self.stats.spread_rows += self.spread_config.num_rows() - num_spread_rows;
+        Ok(state_out)
+    }
+
+    /// Returns the SHA-256 digest bytes of `input`, whose length is fixed by the circuit.
+    /// Unlike `digest`, the padding is assigned as constants and no entry of `max_variable_byte_sizes` is consumed.
+    pub fn digest_fixed<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let state = self.digest_fixed_words(ctx, input)?;
+        Ok(self.digest_bytes(ctx, &state))
+    }
+
+    /// Returns the eight output state words of `digest_fixed`.
+    fn digest_fixed_words<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        if self.is_input_range_check {
+            for assigned_byte in input.iter() {
+                self.range.range_check(ctx, assigned_byte, 8);
+            }
+        }
+        let padded_input = padding::pad_assigned(ctx, self.range.gate(), input);
+        self.digest_blocks_unchecked(ctx, &padded_input)
+    }
+
+    /// Compresses already padded `blocks`, a multiple of 64 bytes, from the IV and returns the eight output state words.
+    /// No entry of `max_variable_byte_sizes` is consumed.
+    pub fn digest_blocks<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        blocks: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        if self.is_input_range_check {
+            let range = self.range();
+            for assigned_byte in blocks.iter() {
+                range.range_check(ctx, assigned_byte, 8);
+            }
+        }
+        self.digest_blocks_unchecked(ctx, blocks)
+    }
+
+    fn digest_blocks_unchecked<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        blocks: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        assert_eq!(blocks.len() % Self::ONE_ROUND_INPUT_BYTES, 0);
+        let mut state = INIT_STATE
+            .iter()
+            .map(|h| self.range().gate().load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        for block in blocks.chunks(Self::ONE_ROUND_INPUT_BYTES) {
+            state = self.compress(ctx, block, &state)?;
+        }
+        Ok(state)
+    }
+
+    /// Decomposes 32-bit words into big-endian bytes.
+    /// Each byte is range-checked with the lookup table and the bytes are constrained to recompose the word.
+    pub fn digest_bytes<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        words: &[AssignedValue<'a, F>],
+    ) -> Vec<AssignedValue<'a, F>> {
+        let range = self.range();
+        let gate = range.gate();
+        words
+            .iter()
+            .flat_map(|assigned_word| {
+                let be_bytes = assigned_word
+                    .value()
+                    .map(|v| v.get_lower_32().to_be_bytes().to_vec());
+                let assigned_bytes = (0..4)
+                    .map(|idx| {
+                        let assigned = gate
+                            .load_witness(ctx, be_bytes.as_ref().map(|vs| F::from(vs[idx] as u64)));
+                        range.range_check(ctx, &assigned, 8);
+                        assigned
+                    })
+                    .collect::<Vec<AssignedValue<F>>>();
+                let mut sum = gate.load_zero(ctx);
+                for (idx, assigned_byte) in assigned_bytes.iter().enumerate() {
+                    sum = gate.mul_add(
+                        ctx,
+                        QuantumCell::Existing(assigned_byte),
+                        QuantumCell::Constant(F::from(1u64 << (24 - 8 * idx))),
+                        QuantumCell::Existing(&sum),
+                    );
+                }
+                gate.assert_equal(
+                    ctx,
+                    QuantumCell::Existing(assigned_word),
+                    QuantumCell::Existing(&sum),
+                );
+                assigned_bytes
+            })
+            .collect()
+    }
+
+    /// Packs the 32 digest bytes into two 128-bit field elements `(hi, lo)`.
+    /// `hi` holds the first 16 bytes and `lo` the last 16 bytes, both read as big-endian integers.
+    pub fn pack_digest_hi_lo<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'a, F>],
+    ) -> (AssignedValue<'a, F>, AssignedValue<'a, F>) {
+        debug_assert_eq!(output_bytes.len(), 32);
+        let gate = self.range().gate();
+        let mut packed = output_bytes
+            .chunks(16)
+            .map(|bytes| {
+                gate.inner_product(
+                    ctx,
+                    bytes.iter().map(QuantumCell::Existing),
+                    (0..16)
+                        .map(|idx| QuantumCell::Constant(F::from_u128(1u128 << (8 * (15 - idx))))),
+                )
+            })
+            .collect_vec();
+        let lo = packed.pop().unwrap();
+        let hi = packed.pop().unwrap();
+        (hi, lo)
+    }
+
+    /// Returns an assigned bit that is 1 iff the digest, read as a 256-bit big-endian integer, is less than the target.
+    /// `target_bytes` are 32 big-endian bytes, e.g. loaded from public inputs, and are range-checked here.
+    pub fn is_digest_less_than<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'a, F>],
+        target_bytes: &[AssignedValue<'a, F>],
+    ) -> AssignedValue<'a, F> {
+        assert_eq!(target_bytes.len(), 32);
+        let range = self.range();
+        let gate = range.gate();
+        for byte in target_bytes.iter() {
+            range.range_check(ctx, byte, 8);
+        }
+        let (digest_hi, digest_lo) = self.pack_digest_hi_lo(ctx, output_bytes);
+        let (target_hi, target_lo) = self.pack_digest_hi_lo(ctx, target_bytes);
+        let hi_lt = range.is_less_than(
+            ctx,
+            QuantumCell::Existing(&digest_hi),
+            QuantumCell::Existing(&target_hi),
+            128,
+        );
+        let hi_eq = gate.is_equal(
+            ctx,
+            QuantumCell::Existing(&digest_hi),
+            QuantumCell::Existing(&target_hi),
+        );
+        let lo_lt = range.is_less_than(
+            ctx,
+            QuantumCell::Existing(&digest_lo),
+            QuantumCell::Existing(&target_lo),
+            128,
+        );
+        // `hi_lt` and `hi_eq` are never both set, so the sum is a bit.
+        gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&hi_eq),
+            QuantumCell::Existing(&lo_lt),
+            QuantumCell::Existing(&hi_lt),
+        )
+    }
+
+    /// Constrains the digest, read as a 256-bit big-endian integer, to be less than the target.
+    pub fn check_digest_less_than<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        output_bytes: &[AssignedValue<'a, F>],
+        target_bytes: &[AssignedValue<'a, F>],
+    ) {
+        let is_less = self.is_digest_less_than(ctx, output_bytes, target_bytes);
+        self.range().gate().assert_is_const(ctx, &is_less, F::one());
+    }
+
+    /// Copy-constrains the digest of `result` to `expected`, given either as the eight state words or as the 32
+    /// big-endian digest bytes, e.g. cells assigned by another chip in the same region.
+    /// `expected` needs no range check, since it is equal to the cells that `digest` already constrained.
+    pub fn constrain_digest_equal<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        result: &AssignedHashResult<'a, F>,
+        expected: &[AssignedValue<'a, F>],
+    ) -> Result<(), Error> {
+        let digest = match expected.len() {
+            NUM_STATE_WORD => &result.output_words,
+            32 => &result.output_bytes,
+            len => panic!(
+                "expected 8 digest words or 32 digest bytes, got {} cells",
+                len
+            ),
+        };
+        assert_eq!(
+            digest.len(),
+            expected.len(),
+            "the digest of `result` is truncated"
+        );
+        for (output, expected) in digest.iter().zip(expected.iter()) {
+            ctx.region.constrain_equal(output.cell(), expected.cell())?;
+        }
+        Ok(())
+    }
+
+    /// Constrains the digest of `result` to the constant `expected`, e.g. to prove knowledge of a preimage of a
+    /// hard-coded hash. The eight state words are compared to constants of the fixed columns of `range`.
+    pub fn expect_digest<'a, 'b: 'a>(
+        &self,
+        ctx: &mut Context<'b, F>,
+        result: &AssignedHashResult<'a, F>,
+        expected: &[u8; 32],
+    ) {
+        let gate = self.range().gate();
+        for (output, expected) in result
+            .output_words
+            .iter()
+            .zip(endian::digest_from_be_bytes(expected).iter())
+        {
+            gate.assert_is_const(ctx, output, F::from(*expected as u64));
+        }
+    }
+
+    /// Returns the spread forms `(lo, hi)` of 32-bit `words`, e.g. the digest words, for gadgets evaluating XOR or
+    /// AND with the spread trick. `lo` and `hi` are the spreads of the 16-bit halves, and the lookups range-check the
+    /// words. The compression never spreads its output words, so this is the only lookup for them; the spreads of the
+    /// message schedule are recorded by `sha256_compression_with_cells`.
+    pub fn spread_words<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        words: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<SpreadU32<'a, F>>, Error> {
+        let range = &self.range;
+        let spread_config = &mut self.spread_config;
+        words
+            .iter()
+            .enumerate()
+            .map(|(idx, word)| {
+                spread_config.set_annotation("external word", idx);
+                state_to_spread_u32(ctx, range, spread_config, word)
+            })
+            .collect()
+    }
+
+    pub fn new_context<'a, 'b>(&'b self, region: Region<'a, F>) -> Context<'a, F> {
+        Context::new(
+            region,
+            ContextParams {
+                max_rows: self.range.gate.max_rows,
+                num_context_ids: 1,
+                fixed_columns: self.range.gate.constants.clone(),
+            },
+        )
+    }
+
+    pub fn range(&self) -> &RangeConfig<F> {
+        &self.range
+    }
+
+    /// Returns the spread lookup of this chip so that other bitwise gadgets can share its table instead of configuring their own.
+    pub fn spread_config_mut(&mut self) -> &mut SpreadConfig<F> {
+        &mut self.spread_config
+    }
+
+    /// Returns the resources used by the compressions assigned so far.
+    pub fn stats(&self) -> &Sha256Stats {
+        &self.stats
+    }
+
+    pub fn descriptor(&self) -> Sha256ConfigDescriptor {
+        let num_bits_lookup = self.spread_config.num_bits_lookup();
+        Sha256ConfigDescriptor {
+            max_variable_byte_sizes: self.max_variable_byte_sizes.clone(),
+            max_blocks: self
+                .max_variable_byte_sizes
+                .iter()
+                .map(|size| size / Self::ONE_ROUND_INPUT_BYTES)
+                .collect_vec(),
+            num_bits_lookup,
+            spread_table_size: 1 << num_bits_lookup,
+            dense_columns: self.spread_config.dense_column_indexes(),
+            spread_columns: self.spread_config.spread_column_indexes(),
+            is_input_range_check: self.is_input_range_check,
+        }
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.spread_config.load(layouter)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, marker::PhantomData};
+
+    use super::*;
+    use crate::test_utils::{prove, Sha256TestCircuit, TestShape, K};
+    use halo2_base::halo2_proofs::{
+        circuit::{Cell, Layouter, Region, SimpleFloorPlanner},
+        dev::{MockProver, VerifyFailure},
+        halo2curves::bn256::{Bn256, Fr},
+        plonk::{keygen_vk, Circuit, ConstraintSystem, Instance},
+        poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, ContextParams, SKIP_FIRST_PASS};
+
+    use num_bigint::RandomBits;
+    use rand::rngs::{OsRng, StdRng};
+    use rand::{thread_rng, Rng, SeedableRng};
 
     #[derive(Debug, Clone)]
     struct TestConfig<F: PrimeField> {
@@ -499,6 +1499,41 @@ mod test {
         const LOOKUP_BITS: usize = 16;
     }
 
+    /// The columns of `TestCircuit`, for the tests that synthesize in a `Sha256TestCircuit`.
+    struct Sha256Shape;
+
+    impl TestShape<Fr> for Sha256Shape {
+        type Extra = ();
+        const NUM_ADVICE: usize = TestCircuit::<Fr>::NUM_ADVICE;
+        const MAX_VARIABLE_BYTE_SIZES: &'static [usize] = &[
+            TestCircuit::<Fr>::MAX_BYTE_SIZE1,
+            TestCircuit::<Fr>::MAX_BYTE_SIZE2,
+        ];
+    }
+
+    /// Compares `actual` with the committed file `testdata/<name>`. With `UPDATE_GOLDEN=1` in the environment, the
+    /// file is written instead, so that an intended change is recorded deliberately and committed with the change.
+    fn assert_golden(name: &str, actual: &str) {
+        let path = format!("{}/testdata/{}", env!("CARGO_MANIFEST_DIR"), name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata")).unwrap();
+            std::fs::write(&path, format!("{}\n", actual)).unwrap();
+            return;
+        }
+        let golden = std::fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "the golden file {} is missing; run the test with UPDATE_GOLDEN=1 to create it",
+                path
+            )
+        });
+        assert_eq!(
+            actual,
+            golden.trim_end(),
+            "{} does not match; run the test with UPDATE_GOLDEN=1 if the change is intended",
+            path
+        );
+    }
+
     #[test]
     fn test_sha256_correct1() {
         let k = 17;
@@ -532,6 +1567,40 @@ mod test {
         assert_eq!(prover.verify(), Ok(()));
     }
 
+    // configs are shared across the worker threads of prover services.
+    static_assertions::assert_impl_all!(Sha256DynamicConfig<Fr>: Send, Sync);
+    static_assertions::assert_impl_all!(SpreadConfig<Fr>: Send, Sync);
+    static_assertions::assert_impl_all!(Sha256Stats: Send, Sync);
+    static_assertions::assert_impl_all!(Sha256ConfigDescriptor: Send, Sync);
+    static_assertions::assert_impl_all!(ripemd160::Ripemd160Config<Fr>: Send, Sync);
+    static_assertions::assert_impl_all!(blake2s::Blake2sConfig<Fr>: Send, Sync);
+    static_assertions::assert_impl_all!(sha512::Sha512Config<Fr>: Send, Sync);
+    static_assertions::assert_impl_all!(rlc::RlcConfig<Fr>: Send, Sync);
+
+    /// Runs the "abc" test vector over the field `F`.
+    fn check_sha256_abc<F: PrimeField>() {
+        let circuit = TestCircuit::<F> {
+            test_inputs: vec![b"abc".to_vec(), vec![]],
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let test_output = [Sha256::digest(b"abc"), Sha256::digest(b"")]
+            .concat()
+            .into_iter()
+            .map(|val| F::from(val as u64))
+            .collect();
+        let prover = MockProver::run(17, &circuit, vec![test_output]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_multi_curve() {
+        use halo2_base::halo2_proofs::halo2curves::{pasta, secp256k1};
+        check_sha256_abc::<Fr>();
+        check_sha256_abc::<pasta::Fp>();
+        check_sha256_abc::<secp256k1::Fq>();
+    }
+
     #[test]
     fn test_sha256_correct2() {
         let k = 17;
@@ -615,4 +1684,876 @@ mod test {
         let prover = MockProver::run(k, &circuit, public_inputs).unwrap();
         assert_eq!(prover.verify(), Ok(()));
     }
+
+    #[test]
+    fn test_sha256_boundary_lengths() {
+        let k = 17;
+        // 55 bytes is the longest input padded into one block, 56 and 64 bytes spill the length into a second block.
+        for lens in [[0, 55], [56, 64], [63, 119]] {
+            let test_inputs = lens
+                .iter()
+                .map(|len| (0..*len).map(|idx| idx as u8).collect_vec())
+                .collect_vec();
+            let test_output = test_inputs
+                .iter()
+                .flat_map(|input| Sha256::digest(input).to_vec())
+                .map(|val| Fr::from_u128(val as u128))
+                .collect();
+            let circuit = TestCircuit::<Fr> {
+                test_inputs,
+                precomputed_input_lens: vec![0, 0],
+                _f: PhantomData,
+            };
+            let prover = MockProver::run(k, &circuit, vec![test_output]).unwrap();
+            assert_eq!(prover.verify(), Ok(()), "input lengths {:?}", lens);
+        }
+    }
+
+    #[test]
+    fn test_sha256_bits() {
+        // NIST SHAVS bit-oriented vector: the 5 bits 01101, and a byte-aligned input.
+        let test_vectors = [
+            (
+                vec![0x68u8],
+                5,
+                "d6d3e02a31a84a8caa9718ed6c2057be09db45e7823eb5079ce7a573a3760f95",
+            ),
+            (
+                b"abc".to_vec(),
+                24,
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            ),
+        ];
+        for (test_input, bit_len, test_output) in test_vectors {
+            let test_output = hex::decode(test_output)
+                .unwrap()
+                .into_iter()
+                .map(|val| Fr::from_u128(val as u128))
+                .collect();
+            let result = prove::<Sha256Shape, _>(test_output, |ctx, chips| {
+                let result = chips.sha256.digest_bits(ctx, &test_input, bit_len)?;
+                Ok(result.output_bytes)
+            });
+            assert_eq!(result, Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_sha256_constant_prefix() {
+        for (prefix_len, input_len) in [(0, 10), (20, 30), (64, 0), (70, 20), (130, 40)] {
+            let prefix = (0..prefix_len).map(|idx| idx as u8).collect_vec();
+            let test_input = vec![0xab; input_len];
+            let mut message = prefix.clone();
+            message.extend_from_slice(&test_input);
+            let test_output = Sha256::digest(&message)
+                .into_iter()
+                .map(|val| Fr::from_u128(val as u128))
+                .collect();
+            let result = prove::<Sha256Shape, _>(test_output, |ctx, chips| {
+                let result = chips
+                    .sha256
+                    .digest_with_constant_prefix(ctx, &prefix, &test_input)?;
+                Ok(result.output_bytes)
+            });
+            assert_eq!(result, Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_sha256_truncated() {
+        for n_bytes in [1, 20, 31] {
+            let test_input = vec![0x5a; 50];
+            let test_output = Sha256::digest(&test_input)
+                .into_iter()
+                .take(n_bytes)
+                .map(|val| Fr::from_u128(val as u128))
+                .collect();
+            let result = prove::<Sha256Shape, _>(test_output, |ctx, chips| {
+                let result = chips
+                    .sha256
+                    .digest_truncated(ctx, &test_input, None, n_bytes)?;
+                assert_eq!(result.output_bytes.len(), n_bytes);
+                Ok(result.output_bytes)
+            });
+            assert_eq!(result, Ok(()));
+        }
+    }
+
+    /// Hashes `test_input` and constrains its digest to `expected`, given as a constant when `is_constant`, else as
+    /// witnessed words or bytes.
+    fn prove_digest_equal(
+        test_input: &[u8],
+        expected: [u8; 32],
+        as_words: bool,
+        is_constant: bool,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        prove::<Sha256Shape, _>(vec![], |ctx, chips| {
+            let result = chips.sha256.digest(ctx, test_input, None)?;
+            if is_constant {
+                chips.sha256.expect_digest(ctx, &result, &expected);
+                return Ok(vec![]);
+            }
+            let expected_vals = if as_words {
+                endian::digest_from_be_bytes(&expected)
+                    .iter()
+                    .map(|word| Fr::from(*word as u64))
+                    .collect_vec()
+            } else {
+                expected
+                    .iter()
+                    .map(|byte| Fr::from(*byte as u64))
+                    .collect_vec()
+            };
+            let expected = expected_vals
+                .into_iter()
+                .map(|val| chips.range.gate().load_witness(ctx, Value::known(val)))
+                .collect_vec();
+            chips
+                .sha256
+                .constrain_digest_equal(ctx, &result, &expected)?;
+            Ok(vec![])
+        })
+    }
+
+    #[test]
+    fn test_sha256_constrain_digest_equal() {
+        let test_input = b"hash-locked".to_vec();
+        let digest: [u8; 32] = Sha256::digest(&test_input).into();
+        let mut wrong_digest = digest;
+        wrong_digest[31] ^= 1;
+        for &as_words in [true, false].iter() {
+            for (expected, is_ok) in [(digest, true), (wrong_digest, false)] {
+                let result = prove_digest_equal(&test_input, expected, as_words, false);
+                assert_eq!(result.is_ok(), is_ok);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sha256_expect_digest() {
+        let test_input = b"hash-locked".to_vec();
+        let digest: [u8; 32] = Sha256::digest(&test_input).into();
+        let mut wrong_digest = digest;
+        wrong_digest[0] ^= 0x80;
+        for (expected, is_ok) in [(digest, true), (wrong_digest, false)] {
+            let result = prove_digest_equal(&test_input, expected, true, true);
+            assert_eq!(result.is_ok(), is_ok);
+        }
+    }
+
+    #[test]
+    fn test_sha256_from_midstate() {
+        for (prefix_len, input_len) in [(64, 10), (128, 50)] {
+            let prefix = (0..prefix_len).map(|idx| idx as u8).collect_vec();
+            let test_input = vec![0xcd; input_len];
+            let midstate = reference::midstate(&prefix);
+            let mut message = prefix.clone();
+            message.extend_from_slice(&test_input);
+            let public_inputs = Sha256::digest(&message)
+                .into_iter()
+                .map(|val| Fr::from_u128(val as u128))
+                .chain(midstate.iter().map(|word| Fr::from(*word as u64)))
+                .collect();
+            // the prover only knows the midstate, not the prefix.
+            let result = prove::<Sha256Shape, _>(public_inputs, |ctx, chips| {
+                let assigned_midstate = midstate
+                    .iter()
+                    .map(|word| {
+                        chips
+                            .range
+                            .gate()
+                            .load_witness(ctx, Value::known(Fr::from(*word as u64)))
+                    })
+                    .collect_vec();
+                let result = chips.sha256.digest_from_midstate(
+                    ctx,
+                    &assigned_midstate,
+                    prefix_len,
+                    &test_input,
+                )?;
+                Ok(result
+                    .output_bytes
+                    .into_iter()
+                    .chain(assigned_midstate)
+                    .collect())
+            });
+            assert_eq!(result, Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_compress_block() {
+        let trace = reference::sha256_trace(&[0x5a; 70]);
+        let test_output = trace
+            .digest
+            .iter()
+            .map(|byte| Fr::from(*byte as u64))
+            .collect();
+        let result = prove::<Sha256Shape, _>(test_output, |ctx, chips| {
+            let mut state = State::iv(ctx, chips.range.gate());
+            for block in trace.padded_input.chunks(64) {
+                let block = block
+                    .iter()
+                    .map(|byte| {
+                        chips
+                            .range
+                            .gate()
+                            .load_witness(ctx, Value::known(Fr::from(*byte as u64)))
+                    })
+                    .collect_vec();
+                state = chips.sha256.compress_block(ctx, &state, &block)?;
+            }
+            Ok(chips.sha256.digest_bytes(ctx, state.words()))
+        });
+        assert_eq!(result, Ok(()));
+    }
+
+    /// Hashes the first `input_len` bytes of the assigned `buffer` and returns the digest.
+    fn prove_assigned_dynamic(
+        buffer: &[u8],
+        input_len: u64,
+        expected: Vec<Fr>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        prove::<Sha256Shape, _>(expected, |ctx, chips| {
+            let gate = chips.range.gate();
+            let buffer = buffer
+                .iter()
+                .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                .collect_vec();
+            let input_len = gate.load_witness(ctx, Value::known(Fr::from(input_len)));
+            let result = chips
+                .sha256
+                .digest_assigned_dynamic(ctx, &buffer, &input_len, None)?;
+            Ok(result.output_bytes)
+        })
+    }
+
+    #[test]
+    fn test_sha256_digest_assigned_dynamic() {
+        // the bytes after the input are not hashed, whatever their values.
+        let mut buffer = b"dynamic length input".to_vec();
+        let input_len = buffer.len();
+        buffer.extend_from_slice(&[0xff; 80]);
+        let expected = Sha256::digest(&buffer[..input_len])
+            .into_iter()
+            .map(|byte| Fr::from(byte as u64))
+            .collect_vec();
+        assert_eq!(
+            prove_assigned_dynamic(&buffer, input_len as u64, expected),
+            Ok(())
+        );
+
+        // a length past the end of the buffer would hash bytes the caller never assigned.
+        buffer.truncate(input_len);
+        let expected = Sha256::digest(&buffer)
+            .into_iter()
+            .map(|byte| Fr::from(byte as u64))
+            .collect_vec();
+        assert!(prove_assigned_dynamic(&buffer, input_len as u64 + 1, expected).is_err());
+    }
+
+    /// The columns of the txid tests: the genesis coinbase takes 204 bytes and 4 blocks once padded.
+    struct TxidShape;
+
+    impl TxidShape {
+        const MAX_TX_SIZE: usize = 256;
+    }
+
+    impl TestShape<Fr> for TxidShape {
+        type Extra = ();
+        const NUM_ADVICE: usize = 8;
+        const MAX_VARIABLE_BYTE_SIZES: &'static [usize] = &[Self::MAX_TX_SIZE, 64];
+    }
+
+    /// Computes the txid of `tx`, assigned in a buffer of `TxidShape::MAX_TX_SIZE` bytes.
+    fn prove_txid(tx: &[u8], txid: Vec<Fr>) -> Result<(), Vec<VerifyFailure>> {
+        prove::<TxidShape, _>(txid, |ctx, chips| {
+            let gate = chips.range.gate();
+            let mut buffer = tx.to_vec();
+            buffer.resize(TxidShape::MAX_TX_SIZE, 0);
+            let tx_bytes = buffer
+                .iter()
+                .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                .collect_vec();
+            let tx_len = gate.load_witness(ctx, Value::known(Fr::from(tx.len() as u64)));
+            chips.sha256.txid(ctx, &tx_bytes, &tx_len)
+        })
+    }
+
+    #[test]
+    fn test_txid_genesis_coinbase() {
+        let tx = hex::decode(
+            "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04\
+             ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e\
+             206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f205\
+             2a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6\
+             bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000",
+        )
+        .unwrap();
+        // the txid as displayed by block explorers, i.e. already byte-reversed.
+        let txid = hex::decode("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b")
+            .unwrap()
+            .into_iter()
+            .map(|byte| Fr::from(byte as u64))
+            .collect_vec();
+        assert_eq!(prove_txid(&tx, txid.clone()), Ok(()));
+
+        // dropping the lock time gives another transaction, whose txid differs.
+        assert!(prove_txid(&tx[..tx.len() - 4], txid).is_err());
+    }
+
+    #[test]
+    fn test_column_allocation_order() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let config = TestCircuit::<Fr>::configure(&mut meta);
+        let descriptor = config.sha256.descriptor();
+        // the range chip allocates 3 advice columns and 1 lookup advice column first.
+        assert_eq!(descriptor.dense_columns, vec![4, 5]);
+        assert_eq!(descriptor.spread_columns, vec![6, 7]);
+    }
+
+    #[test]
+    fn test_max_degree() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        TestCircuit::<Fr>::configure(&mut meta);
+        assert_eq!(meta.degree(), 4);
+    }
+
+    /// The range checks added by the `paranoid` feature are implied by the spread lookups:
+    /// without them, a dense value of 17 bits is still rejected.
+    #[test]
+    fn test_spread_implies_range_check() {
+        for (dense, is_valid) in [(0xffff, true), (0x1_0000, false), (0x1_2345, false)] {
+            let result = prove::<Sha256Shape, _>(vec![], |ctx, chips| {
+                let dense = chips
+                    .range
+                    .gate()
+                    .load_witness(ctx, Value::known(Fr::from(dense)));
+                chips
+                    .sha256
+                    .spread_config_mut()
+                    .spread(ctx, &chips.range, &dense)?;
+                Ok(vec![])
+            });
+            assert_eq!(result.is_ok(), is_valid);
+        }
+    }
+
+    #[test]
+    fn test_configure_with_columns() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let range_config = RangeConfig::configure(
+            &mut meta,
+            Vertical,
+            &[TestCircuit::<Fr>::NUM_ADVICE],
+            &[TestCircuit::<Fr>::NUM_LOOKUP_ADVICE],
+            TestCircuit::<Fr>::NUM_FIXED,
+            TestCircuit::<Fr>::LOOKUP_BITS,
+            0,
+            17,
+        );
+        let denses = vec![meta.advice_column(), meta.advice_column()];
+        let spreads = vec![meta.advice_column(), meta.advice_column()];
+        let num_advice_columns = meta.num_advice_columns();
+        let sha256 = Sha256DynamicConfig::configure_with_columns(
+            &mut meta,
+            vec![64],
+            range_config,
+            8,
+            denses.clone(),
+            spreads.clone(),
+            None,
+            true,
+        );
+        assert_eq!(meta.num_advice_columns(), num_advice_columns);
+        let descriptor = sha256.descriptor();
+        assert_eq!(
+            descriptor.dense_columns,
+            denses.iter().map(|column| column.index()).collect_vec()
+        );
+        assert_eq!(
+            descriptor.spread_columns,
+            spreads.iter().map(|column| column.index()).collect_vec()
+        );
+    }
+
+    #[test]
+    fn test_block_footprint() {
+        let result = prove::<Sha256Shape, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let sha256 = &mut chips.sha256;
+            let block = (0..64)
+                .map(|_| gate.load_witness(ctx, Value::known(Fr::zero())))
+                .collect_vec();
+            let num_advice = ctx.total_advice;
+            let num_lookup = ctx.cells_to_lookup.len();
+            sha256.compress_init_block(ctx, &block)?;
+            assert_eq!(sha256.stats().num_compressions, 1);
+            let stats = *sha256.stats();
+            assert_eq!(
+                stats.spread_lookups,
+                sha256.spread_config_mut().num_lookups()
+            );
+            let cost = (
+                ctx.total_advice - num_advice,
+                ctx.cells_to_lookup.len() - num_lookup,
+            );
+            // every block has the same footprint, whatever its values and position.
+            let block = (0..64)
+                .map(|idx| gate.load_witness(ctx, Value::known(Fr::from(idx))))
+                .collect_vec();
+            let num_advice = ctx.total_advice;
+            let num_lookup = ctx.cells_to_lookup.len();
+            sha256.compress_init_block(ctx, &block)?;
+            assert_eq!(
+                cost,
+                (
+                    ctx.total_advice - num_advice,
+                    ctx.cells_to_lookup.len() - num_lookup
+                )
+            );
+            Ok(vec![])
+        });
+        assert_eq!(result, Ok(()));
+    }
+
+    /// Advice cells, range lookups and spread lookups of one part of a compression.
+    type PartCost = (usize, usize, usize);
+
+    #[test]
+    fn test_layout_rows() {
+        // the message schedule and the whole compression of one block.
+        let cost = RefCell::new(((0, 0, 0), (0, 0, 0)));
+        let circuit = Sha256TestCircuit::<Fr, Sha256Shape, _>::new(|ctx, chips| {
+            let range = &chips.range;
+            let gate = range.gate();
+            let block = (0..64)
+                .map(|_| gate.load_witness(ctx, Value::known(Fr::zero())))
+                .collect_vec();
+            let state = INIT_STATE
+                .iter()
+                .map(|h| gate.load_constant(ctx, Fr::from(*h as u64)))
+                .collect_vec();
+            let spread_config = chips.sha256.spread_config_mut();
+            let measure = |ctx: &mut Context<Fr>,
+                           spread_config: &mut SpreadConfig<Fr>,
+                           schedule_only: bool|
+             -> Result<PartCost, Error> {
+                let num_advice = ctx.total_advice;
+                let num_lookup = ctx.cells_to_lookup.len();
+                let num_spread = spread_config.num_lookups();
+                if schedule_only {
+                    message_schedule(ctx, range, spread_config, &block)?;
+                } else {
+                    sha256_compression(ctx, range, spread_config, &block, &state)?;
+                }
+                Ok((
+                    ctx.total_advice - num_advice,
+                    ctx.cells_to_lookup.len() - num_lookup,
+                    spread_config.num_lookups() - num_spread,
+                ))
+            };
+            let schedule = measure(ctx, spread_config, true)?;
+            let compression = measure(ctx, spread_config, false)?;
+            *cost.borrow_mut() = (schedule, compression);
+            Ok(vec![])
+        });
+        let prover = MockProver::run(K, &circuit, vec![vec![]]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+        let (schedule, compression) = *cost.borrow();
+        // the tables are assigned outside of regions: the spread table in its `table_dense` and `table_spread`
+        // columns and the range table in the remaining table column.
+        let cells = layout::record_layout(&circuit).unwrap();
+        let table_rows = |is_spread_table: bool| {
+            cells
+                .iter()
+                .filter(|cell| {
+                    cell.region.is_empty()
+                        && cell.column_kind == layout::ColumnKind::Fixed
+                        && cell.annotation.starts_with("table_") == is_spread_table
+                })
+                .map(|cell| cell.row)
+                .collect::<std::collections::BTreeSet<_>>()
+                .len()
+        };
+        let rounds = (
+            compression.0 - schedule.0,
+            compression.1 - schedule.1,
+            compression.2 - schedule.2,
+        );
+        // every column of a kind is filled evenly, so a part takes its cells divided by the number of columns.
+        let div_ceil = |cells: usize, columns: usize| (cells + columns - 1) / columns;
+        let rows = |(advice, lookup, spread): PartCost| {
+            format!(
+                "{} advice rows, {} range lookup rows, {} spread rows",
+                div_ceil(advice, TestCircuit::<Fr>::NUM_ADVICE),
+                div_ceil(lookup, TestCircuit::<Fr>::NUM_LOOKUP_ADVICE),
+                div_ceil(spread, 2)
+            )
+        };
+        let layout = format!(
+            "message schedule: {}\nrounds: {}\ntables: {} range rows, {} spread rows",
+            rows(schedule),
+            rows(rounds),
+            table_rows(false),
+            table_rows(true)
+        );
+        // a part that takes more rows than before may push users to a larger k, so the rows of each part are pinned.
+        assert_golden("layout_rows.txt", &layout);
+    }
+
+    /// Describes the first intermediate cell of `cells` that differs from `trace`, in assignment order.
+    fn first_divergence<F: PrimeField>(
+        cells: &CompressionCells<F>,
+        trace: &reference::BlockTrace,
+    ) -> Option<String> {
+        assert_eq!(cells.schedule.len(), NUM_ROUND);
+        assert_eq!(cells.rounds.len(), NUM_ROUND);
+        for (idx, (cell, word)) in cells.schedule.iter().zip(trace.schedule.iter()).enumerate() {
+            if bits::assigned_to_u32(cell) != *word {
+                return Some(format!(
+                    "W_{}: circuit {:#010x}, reference {:#010x}",
+                    idx,
+                    bits::assigned_to_u32(cell),
+                    word
+                ));
+            }
+        }
+        for (round, (round_cells, words)) in
+            cells.rounds.iter().zip(trace.rounds.iter()).enumerate()
+        {
+            for (name, (cell, word)) in "abcdefgh".chars().zip(round_cells.iter().zip(words.iter()))
+            {
+                if bits::assigned_to_u32(cell) != *word {
+                    return Some(format!(
+                        "{} after round {}: circuit {:#010x}, reference {:#010x}",
+                        name,
+                        round,
+                        bits::assigned_to_u32(cell),
+                        word
+                    ));
+                }
+            }
+        }
+        None
+    }
+
+    #[test]
+    fn test_reference_trace_differential() {
+        let mut rng = thread_rng();
+        let blocks = (0..2)
+            .map(|_| {
+                let mut state = [0u32; NUM_STATE_WORD];
+                rng.fill(&mut state[..]);
+                let mut block = [0u8; 64];
+                rng.fill(&mut block[..]);
+                (state, block)
+            })
+            .collect_vec();
+        let result = prove::<Sha256Shape, _>(vec![], |ctx, chips| {
+            let range = &chips.range;
+            let sha256 = &mut chips.sha256;
+            for (state, block) in blocks.iter() {
+                let trace = reference::compression_trace(state, block);
+                let assigned_block = block
+                    .iter()
+                    .map(|byte| {
+                        range
+                            .gate()
+                            .load_witness(ctx, Value::known(Fr::from(*byte as u64)))
+                    })
+                    .collect_vec();
+                let assigned_state = state
+                    .iter()
+                    .map(|word| {
+                        range
+                            .gate()
+                            .load_witness(ctx, Value::known(Fr::from(*word as u64)))
+                    })
+                    .collect_vec();
+                let mut cells = CompressionCells {
+                    schedule: vec![],
+                    schedule_spreads: vec![],
+                    rounds: vec![],
+                };
+                let state_out = sha256_compression_with_cells(
+                    ctx,
+                    range,
+                    sha256.spread_config_mut(),
+                    &assigned_block,
+                    &assigned_state,
+                    Some(&mut cells),
+                )?;
+                assert_eq!(first_divergence(&cells, &trace), None);
+                for (spread, word) in cells.schedule_spreads.iter().zip(trace.schedule.iter()) {
+                    assert_eq!(
+                        bits::assigned_to_u32(&spread.0),
+                        bits::spread_u16(*word as u16)
+                    );
+                    assert_eq!(
+                        bits::assigned_to_u32(&spread.1),
+                        bits::spread_u16((*word >> 16) as u16)
+                    );
+                }
+                assert_eq!(
+                    state_out.iter().map(bits::assigned_to_u32).collect_vec(),
+                    trace.state_out.to_vec()
+                );
+                let spreads = sha256.spread_words(ctx, &state_out)?;
+                for (spread, word) in spreads.iter().zip(trace.state_out.iter()) {
+                    assert_eq!(
+                        bits::assigned_to_u32(&spread.1),
+                        bits::spread_u16((*word >> 16) as u16)
+                    );
+                }
+            }
+            Ok(vec![])
+        });
+        assert_eq!(result, Ok(()));
+    }
+
+    #[test]
+    fn test_golden_vk() {
+        let k = 17;
+        let params = ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(0));
+        let circuit = TestCircuit::<Fr> {
+            test_inputs: vec![vec![], vec![]],
+            precomputed_input_lens: vec![0, 0],
+            _f: PhantomData,
+        };
+        let vk = keygen_vk(&params, &circuit).unwrap();
+        let vk_repr = format!("{:?}", vk.transcript_repr());
+        assert_golden("vk_k17.txt", &vk_repr);
+    }
+
+    /// Computes `n` iterations of SHA-256 from `seed` and returns their digests.
+    fn prove_hash_chain(
+        seed: [u8; 32],
+        n: usize,
+        expected: Vec<Fr>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        prove::<Sha256Shape, _>(expected, |ctx, chips| {
+            let gate = chips.range.gate();
+            let seed = seed
+                .iter()
+                .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                .collect_vec();
+            let results = chips.sha256.hash_chain(ctx, &seed, n)?;
+            // only the first iteration takes an entry of `max_variable_byte_sizes`.
+            assert_eq!(chips.sha256.cur_hash_idx, 1);
+            Ok(results
+                .into_iter()
+                .flat_map(|result| result.output_bytes)
+                .collect())
+        })
+    }
+
+    /// Returns the digests of the first `n` iterations of SHA-256 from `seed`, concatenated.
+    fn native_hash_chain(seed: &[u8], n: usize) -> Vec<Fr> {
+        let mut digest = seed.to_vec();
+        let mut digests = vec![];
+        for _ in 0..n {
+            digest = Sha256::digest(&digest).to_vec();
+            digests.extend(digest.iter().map(|byte| Fr::from(*byte as u64)));
+        }
+        digests
+    }
+
+    #[test]
+    fn test_hash_chain() {
+        let seed = [0x42; 32];
+        // longer than the two entries of `max_variable_byte_sizes`.
+        assert_eq!(
+            prove_hash_chain(seed, 3, native_hash_chain(&seed, 3)),
+            Ok(())
+        );
+
+        // the chain of another seed.
+        let mut other_seed = seed;
+        other_seed[0] ^= 1;
+        assert!(prove_hash_chain(seed, 3, native_hash_chain(&other_seed, 3)).is_err());
+    }
+
+    /// Constrains `is_digest_less_than` of the digest of "hello world" and `target` to `is_less`.
+    fn prove_digest_less_than(target: [u8; 32], is_less: bool) -> Result<(), Vec<VerifyFailure>> {
+        prove::<Sha256Shape, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let result = chips.sha256.digest(ctx, b"hello world", None)?;
+            let target = target
+                .iter()
+                .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                .collect_vec();
+            let is_less_cell = chips
+                .sha256
+                .is_digest_less_than(ctx, &result.output_bytes, &target);
+            gate.assert_is_const(ctx, &is_less_cell, Fr::from(is_less as u64));
+            Ok(vec![])
+        })
+    }
+
+    #[test]
+    fn test_is_digest_less_than() {
+        let digest: [u8; 32] = Sha256::digest(b"hello world").into();
+        // the same upper half and a greater lower half.
+        let mut above_lo = digest;
+        above_lo[31] += 1;
+        // a smaller upper half and a greater lower half.
+        let mut below_hi = [0xff; 32];
+        below_hi[0] = digest[0] - 1;
+        for (target, is_less) in [(above_lo, true), (below_hi, false), (digest, false)] {
+            // big-endian byte arrays compare as the integers they encode.
+            assert_eq!(digest < target, is_less);
+            assert_eq!(prove_digest_less_than(target, is_less), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_is_digest_less_than_rejects_equal_bound() {
+        let digest: [u8; 32] = Sha256::digest(b"hello world").into();
+        assert!(prove_digest_less_than(digest, true).is_err());
+    }
+
+    /// Computes the BIP-340 tagged hash of `msg` and returns it.
+    fn prove_tagged_hash(
+        tag: &[u8],
+        msg: &[u8],
+        expected: Vec<Fr>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        prove::<Sha256Shape, _>(expected, |ctx, chips| {
+            Ok(chips.sha256.tagged_hash(ctx, tag, msg)?.output_bytes)
+        })
+    }
+
+    /// Returns the BIP-340 tagged hash of `msg`, hashing the whole message natively.
+    fn native_tagged_hash(tag: &[u8], msg: &[u8]) -> Vec<Fr> {
+        let tag_hash = Sha256::digest(tag);
+        let mut hasher = Sha256::new();
+        hasher.update(tag_hash);
+        hasher.update(tag_hash);
+        hasher.update(msg);
+        hasher
+            .finalize()
+            .into_iter()
+            .map(|byte| Fr::from(byte as u64))
+            .collect()
+    }
+
+    #[test]
+    fn test_tagged_hash() {
+        let tag = b"BIP0340/challenge";
+        assert_eq!(
+            hex::encode(Sha256::digest(tag)),
+            "7bb52d7a9fef58323eb1bf7a407db382d2f3f2d81bb1224f49fe518f6d48d37c"
+        );
+        // `r || P || m` of a BIP-340 challenge.
+        let msg = (0..96).map(|idx| idx as u8).collect_vec();
+        assert_eq!(
+            prove_tagged_hash(tag, &msg, native_tagged_hash(tag, &msg)),
+            Ok(())
+        );
+
+        // the same message under another tag.
+        assert!(prove_tagged_hash(tag, &msg, native_tagged_hash(b"BIP0340/aux", &msg)).is_err());
+    }
+
+    #[derive(Debug, Clone)]
+    struct PaddingCase {
+        // the bytes after the first `precomputed_input_len` bytes of the padded input.
+        padded_input: Vec<u8>,
+        precomputed_input_len: usize,
+        input_len: u64,
+        padded_size: u64,
+    }
+
+    impl PaddingCase {
+        fn run(&self) -> Result<(), Vec<VerifyFailure>> {
+            prove::<Sha256Shape, _>(vec![], |ctx, chips| {
+                let gate = chips.range.gate();
+                let padded_input = self
+                    .padded_input
+                    .iter()
+                    .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                    .collect_vec();
+                let input_len = gate.load_witness(ctx, Value::known(Fr::from(self.input_len)));
+                let start_byte = gate.load_constant(ctx, Fr::from(0x80u64));
+                let bit_len = gate.mul(
+                    ctx,
+                    QuantumCell::Existing(&input_len),
+                    QuantumCell::Constant(Fr::from(8u64)),
+                );
+                let padded_size = gate.load_witness(ctx, Value::known(Fr::from(self.padded_size)));
+                chips.sha256.constrain_padding(
+                    ctx,
+                    &padded_input,
+                    self.precomputed_input_len,
+                    &input_len,
+                    &start_byte,
+                    &bit_len,
+                    &padded_size,
+                );
+                Ok(vec![])
+            })
+        }
+    }
+
+    /// Returns the case padding an `input_len`-byte input in a 128-byte buffer, as `digest` does.
+    fn padding_case(input_len: usize, precomputed_input_len: usize) -> PaddingCase {
+        let padded_size = (input_len + 9 + 63) / 64 * 64;
+        let mut padded_input = vec![b'a'; input_len];
+        padded_input.push(0x80);
+        padded_input.resize(padded_size - 8, 0);
+        padded_input.extend_from_slice(&(8 * input_len as u64).to_be_bytes());
+        padded_input.resize(precomputed_input_len + 128, 0);
+        PaddingCase {
+            padded_input: padded_input[precomputed_input_len..].to_vec(),
+            precomputed_input_len,
+            input_len: input_len as u64,
+            padded_size: padded_size as u64,
+        }
+    }
+
+    #[test]
+    fn test_constrain_padding() {
+        for (input_len, precomputed_input_len) in [(3, 0), (55, 0), (56, 0), (64, 64), (70, 64)] {
+            assert_eq!(padding_case(input_len, precomputed_input_len).run(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_constrain_padding_rejects_misplaced_start_byte() {
+        let mut case = padding_case(3, 0);
+        case.padded_input.swap(3, 4);
+        assert!(case.run().is_err());
+    }
+
+    #[test]
+    fn test_constrain_padding_rejects_nonzero_byte() {
+        let mut case = padding_case(3, 0);
+        case.padded_input[30] = 1;
+        assert!(case.run().is_err());
+    }
+
+    #[test]
+    fn test_constrain_padding_rejects_wrong_length() {
+        // the bit length in little-endian order.
+        let mut case = padding_case(3, 0);
+        case.padded_input[56..64].reverse();
+        assert!(case.run().is_err());
+        // the bit length of another input.
+        let mut case = padding_case(3, 0);
+        case.padded_input[63] = 32;
+        assert!(case.run().is_err());
+    }
+
+    #[test]
+    fn test_constrain_padding_rejects_input_in_precomputed_bytes() {
+        // only the range check of `input_len - precomputed_input_len` fails: no byte is selected as the start byte
+        // and the length is at the end of the second block, as the bytes after the precomputed ones are zero.
+        let mut case = padding_case(3, 0);
+        case.padded_input = vec![0; 128];
+        case.padded_input[56..64].copy_from_slice(&24u64.to_be_bytes());
+        case.precomputed_input_len = 64;
+        case.padded_size = 128;
+        assert!(case.run().is_err());
+    }
 }
@@ -0,0 +1,256 @@
+//! Merkle sum tree, as used in proofs of solvency: every node commits to the sum of the balances below it.
+//! A node is `SHA-256(left_hash || left_sum || right_hash || right_sum)` where sums are 8 big-endian bytes.
+use crate::mmr::conditional_swap;
+use crate::Sha256DynamicConfig;
+use alloc::vec::Vec;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+/// Number of bytes of a serialized sum. Sums, including the root one, are less than `2^64`.
+pub const SUM_BYTES: usize = 8;
+
+/// A node of the tree: a 32-byte hash and the sum of the leaves below it.
+#[derive(Debug, Clone)]
+pub struct SumNode<'a, F: PrimeField> {
+    pub hash: Vec<AssignedValue<'a, F>>,
+    pub sum: AssignedValue<'a, F>,
+}
+
+/// Returns the hash of the parent of `(left_hash, left_sum)` and `(right_hash, right_sum)` out of circuit.
+/// Panics if the sum overflows, as the circuit would not be satisfied.
+pub fn native_hash_node(
+    left_hash: &[u8; 32],
+    left_sum: u64,
+    right_hash: &[u8; 32],
+    right_sum: u64,
+) -> ([u8; 32], u64) {
+    let sum = left_sum
+        .checked_add(right_sum)
+        .expect("the sum of a merkle sum tree node overflows");
+    let mut hasher = Sha256::new();
+    hasher.update(left_hash);
+    hasher.update(left_sum.to_be_bytes());
+    hasher.update(right_hash);
+    hasher.update(right_sum.to_be_bytes());
+    (hasher.finalize().into(), sum)
+}
+
+/// Serializes `sum` into `SUM_BYTES` big-endian bytes.
+/// The bytes are range-checked, so `sum` is constrained to be less than `2^64`.
+pub fn sum_to_bytes<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    sum: &AssignedValue<'a, F>,
+) -> Vec<AssignedValue<'a, F>> {
+    let gate = range.gate();
+    let sum_u64 = sum.value().map(|v| v.get_lower_128() as u64);
+    let bytes = (0..SUM_BYTES)
+        .map(|idx| {
+            let shift = 8 * (SUM_BYTES - 1 - idx);
+            let byte = gate.load_witness(ctx, sum_u64.map(|v| F::from((v >> shift) & 0xff)));
+            range.range_check(ctx, &byte, 8);
+            byte
+        })
+        .collect_vec();
+    let composed = gate.inner_product(
+        ctx,
+        bytes.iter().map(QuantumCell::Existing).collect_vec(),
+        (0..SUM_BYTES)
+            .map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (SUM_BYTES - 1 - idx)))))
+            .collect_vec(),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(&composed),
+        QuantumCell::Existing(sum),
+    );
+    bytes
+}
+
+/// Hashes two children into their parent, whose sum is constrained to be `left.sum + right.sum` without overflow.
+pub fn hash_node<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    left: &SumNode<'a, F>,
+    right: &SumNode<'a, F>,
+) -> Result<SumNode<'a, F>, Error> {
+    assert_eq!(left.hash.len(), 32);
+    assert_eq!(right.hash.len(), 32);
+    let range = sha256.range();
+    let left_sum_bytes = sum_to_bytes(ctx, range, &left.sum);
+    let right_sum_bytes = sum_to_bytes(ctx, range, &right.sum);
+    // Both children are less than `2^64`, so their sum does not wrap around the field and a 64-bit range check
+    // rules out any overflow.
+    let sum = range.gate().add(
+        ctx,
+        QuantumCell::Existing(&left.sum),
+        QuantumCell::Existing(&right.sum),
+    );
+    range.range_check(ctx, &sum, 8 * SUM_BYTES);
+    let input = left
+        .hash
+        .iter()
+        .chain(left_sum_bytes.iter())
+        .chain(right.hash.iter())
+        .chain(right_sum_bytes.iter())
+        .cloned()
+        .collect_vec();
+    let hash = sha256.digest_fixed(ctx, &input)?;
+    Ok(SumNode { hash, sum })
+}
+
+/// Computes the root of the tree from `leaf` and returns it.
+/// `path` lists the siblings from the leaf up to the root and `is_left_siblings` are bits that are 1 when the
+/// sibling is the left child.
+pub fn compute_root<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    leaf: &SumNode<'a, F>,
+    path: &[SumNode<'a, F>],
+    is_left_siblings: &[AssignedValue<'a, F>],
+) -> Result<SumNode<'a, F>, Error> {
+    assert_eq!(path.len(), is_left_siblings.len());
+    let gate = sha256.range().gate().clone();
+    let mut node = leaf.clone();
+    for (sibling, is_left_sibling) in path.iter().zip(is_left_siblings.iter()) {
+        gate.assert_bit(ctx, is_left_sibling);
+        // The sum and the hash are swapped together, so they are packed into the same vector.
+        let node_cells = node.hash.iter().chain([&node.sum]).cloned().collect_vec();
+        let sibling_cells = sibling
+            .hash
+            .iter()
+            .chain([&sibling.sum])
+            .cloned()
+            .collect_vec();
+        let (mut left, mut right) =
+            conditional_swap(ctx, &gate, &node_cells, &sibling_cells, is_left_sibling);
+        let left = SumNode {
+            sum: left.pop().unwrap(),
+            hash: left,
+        };
+        let right = SumNode {
+            sum: right.pop().unwrap(),
+            hash: right,
+        };
+        node = hash_node(ctx, sha256, &left, &right)?;
+    }
+    Ok(node)
+}
+
+/// Constrains that `leaf` is included in the tree with `root`, whose hash and sum are both checked.
+pub fn verify_inclusion<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    root: &SumNode<'a, F>,
+    leaf: &SumNode<'a, F>,
+    path: &[SumNode<'a, F>],
+    is_left_siblings: &[AssignedValue<'a, F>],
+) -> Result<(), Error> {
+    let computed = compute_root(ctx, sha256, leaf, path, is_left_siblings)?;
+    for (computed, expected) in computed.hash.iter().zip(root.hash.iter()) {
+        ctx.region
+            .constrain_equal(computed.cell(), expected.cell())?;
+    }
+    ctx.region
+        .constrain_equal(computed.sum.cell(), root.sum.cell())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{prove, PlainShape};
+    use halo2_base::halo2_proofs::{circuit::Value, dev::VerifyFailure, halo2curves::bn256::Fr};
+
+    struct TestCase {
+        leaf: ([u8; 32], u64),
+        path: Vec<([u8; 32], u64)>,
+        is_left_siblings: Vec<bool>,
+        /// The root sum is wider than a `u64` to test overflowing sums.
+        root: ([u8; 32], u128),
+    }
+
+    fn run(case: &TestCase) -> Result<(), Vec<VerifyFailure>> {
+        prove::<PlainShape<8>, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let load_node = |ctx: &mut Context<_>, (hash, sum): &([u8; 32], u64)| SumNode {
+                hash: hash
+                    .iter()
+                    .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                    .collect_vec(),
+                sum: gate.load_witness(ctx, Value::known(Fr::from(*sum))),
+            };
+            let leaf = load_node(ctx, &case.leaf);
+            let path = case
+                .path
+                .iter()
+                .map(|node| load_node(ctx, node))
+                .collect_vec();
+            let root = SumNode {
+                hash: load_node(ctx, &(case.root.0, 0)).hash,
+                sum: gate.load_witness(ctx, Value::known(Fr::from_u128(case.root.1))),
+            };
+            let is_left_siblings = case
+                .is_left_siblings
+                .iter()
+                .map(|bit| gate.load_witness(ctx, Value::known(Fr::from(*bit as u64))))
+                .collect_vec();
+            verify_inclusion(
+                ctx,
+                &mut chips.sha256,
+                &root,
+                &leaf,
+                &path,
+                &is_left_siblings,
+            )?;
+            Ok(vec![])
+        })
+    }
+
+    #[test]
+    fn test_merkle_sum_tree_inclusion() {
+        let leaves = (0..4u8)
+            .map(|idx| ([idx; 32], 1000 * (idx as u64 + 1)))
+            .collect_vec();
+        let node = |left: &([u8; 32], u64), right: &([u8; 32], u64)| {
+            native_hash_node(&left.0, left.1, &right.0, right.1)
+        };
+        let node01 = node(&leaves[0], &leaves[1]);
+        let node23 = node(&leaves[2], &leaves[3]);
+        let root = node(&node01, &node23);
+        assert_eq!(root.1, 10000);
+        let case = TestCase {
+            leaf: leaves[2],
+            path: vec![leaves[3], node01],
+            is_left_siblings: vec![false, true],
+            root: (root.0, root.1 as u128),
+        };
+        assert_eq!(run(&case), Ok(()));
+    }
+
+    #[test]
+    fn test_merkle_sum_tree_rejects_overflow() {
+        let left = ([1u8; 32], 1u64 << 63);
+        let right = ([2u8; 32], 1u64 << 63);
+        // `native_hash_node` panics on this pair, so the root is hashed by hand with the sum `2^64`.
+        let mut hasher = Sha256::new();
+        hasher.update(left.0);
+        hasher.update(left.1.to_be_bytes());
+        hasher.update(right.0);
+        hasher.update(right.1.to_be_bytes());
+        let case = TestCase {
+            leaf: left,
+            path: vec![right],
+            is_left_siblings: vec![false],
+            root: (hasher.finalize().into(), 1u128 << 64),
+        };
+        assert!(run(&case).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+use crate::Sha256DynamicConfig;
+use alloc::vec::Vec;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+/// Hashes two 32-byte children into their parent with a single compression of `left || right`.
+pub fn hash_node<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    left: &[AssignedValue<'a, F>],
+    right: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    assert_eq!(left.len(), 32);
+    assert_eq!(right.len(), 32);
+    let block = left.iter().chain(right.iter()).cloned().collect_vec();
+    sha256.compress_init_block(ctx, &block)
+}
+
+/// Returns `(sibling, node)` if `is_left_sibling` is 1 and `(node, sibling)` otherwise.
+pub(crate) fn conditional_swap<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    node: &[AssignedValue<'a, F>],
+    sibling: &[AssignedValue<'a, F>],
+    is_left_sibling: &AssignedValue<'a, F>,
+) -> (Vec<AssignedValue<'a, F>>, Vec<AssignedValue<'a, F>>) {
+    node.iter()
+        .zip(sibling.iter())
+        .map(|(node_byte, sibling_byte)| {
+            let left = gate.select(
+                ctx,
+                QuantumCell::Existing(sibling_byte),
+                QuantumCell::Existing(node_byte),
+                QuantumCell::Existing(is_left_sibling),
+            );
+            let right = gate.select(
+                ctx,
+                QuantumCell::Existing(node_byte),
+                QuantumCell::Existing(sibling_byte),
+                QuantumCell::Existing(is_left_sibling),
+            );
+            (left, right)
+        })
+        .unzip()
+}
+
+/// Appends `leaf` to a Merkle Mountain Range with `num_leaves` leaves and returns the new peaks.
+/// `peaks` are ordered from the highest to the lowest; their heights are given by the set bits of `num_leaves`.
+pub fn append<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    peaks: &[Vec<AssignedValue<'a, F>>],
+    num_leaves: usize,
+    leaf: &[AssignedValue<'a, F>],
+) -> Result<Vec<Vec<AssignedValue<'a, F>>>, Error> {
+    assert_eq!(peaks.len(), num_leaves.count_ones() as usize);
+    let mut new_peaks = peaks.to_vec();
+    let mut node = leaf.to_vec();
+    let mut height = 0;
+    while (num_leaves >> height) & 1 == 1 {
+        let left = new_peaks.pop().unwrap();
+        node = hash_node(ctx, sha256, &left, &node)?;
+        height += 1;
+    }
+    new_peaks.push(node);
+    Ok(new_peaks)
+}
+
+/// Bags the peaks from right to left into a single root, i.e., `H(p_0, H(p_1, ... H(p_{n-2}, p_{n-1})))`.
+pub fn bag_peaks<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    peaks: &[Vec<AssignedValue<'a, F>>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let mut peaks_iter = peaks.iter().rev();
+    let mut root = peaks_iter
+        .next()
+        .expect("an MMR must have at least one peak")
+        .clone();
+    for peak in peaks_iter {
+        root = hash_node(ctx, sha256, peak, &root)?;
+    }
+    Ok(root)
+}
+
+/// Verifies that `leaf` is included under `peaks[peak_idx]` and returns the bagged root.
+/// `path` lists the siblings from the leaf up to the peak and `is_left_siblings` are bits that are 1 when the sibling is the left child.
+pub fn verify_inclusion<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    leaf: &[AssignedValue<'a, F>],
+    path: &[Vec<AssignedValue<'a, F>>],
+    is_left_siblings: &[AssignedValue<'a, F>],
+    peaks: &[Vec<AssignedValue<'a, F>>],
+    peak_idx: usize,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    assert_eq!(path.len(), is_left_siblings.len());
+    let gate = sha256.range().gate().clone();
+    let mut node = leaf.to_vec();
+    for (sibling, is_left_sibling) in path.iter().zip(is_left_siblings.iter()) {
+        gate.assert_bit(ctx, is_left_sibling);
+        let (left, right) = conditional_swap(ctx, &gate, &node, sibling, is_left_sibling);
+        node = hash_node(ctx, sha256, &left, &right)?;
+    }
+    for (computed, peak) in node.iter().zip(peaks[peak_idx].iter()) {
+        ctx.region.constrain_equal(computed.cell(), peak.cell())?;
+    }
+    bag_peaks(ctx, sha256, peaks)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::endian::digest_to_be_bytes;
+    use crate::test_utils::{prove, PlainShape};
+    use crate::INIT_STATE;
+    use generic_array::GenericArray;
+    use halo2_base::halo2_proofs::{circuit::Value, dev::VerifyFailure, halo2curves::bn256::Fr};
+    use sha2::compress256;
+
+    struct TestCase {
+        leaves: Vec<[u8; 32]>,
+        leaf_idx: usize,
+        path: Vec<[u8; 32]>,
+        is_left_siblings: Vec<bool>,
+        peak_idx: usize,
+        root: [u8; 32],
+        // the leaf appended after the inclusion proof and the peaks after it.
+        new_leaf: [u8; 32],
+        new_peaks: Vec<[u8; 32]>,
+    }
+
+    fn run(case: &TestCase) -> Result<(), Vec<VerifyFailure>> {
+        prove::<PlainShape<8>, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let load_bytes = |ctx: &mut Context<_>, bytes: &[u8]| {
+                bytes
+                    .iter()
+                    .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                    .collect_vec()
+            };
+            let leaves = case
+                .leaves
+                .iter()
+                .map(|leaf| load_bytes(ctx, leaf))
+                .collect_vec();
+            let mut peaks = vec![];
+            for (num_leaves, leaf) in leaves.iter().enumerate() {
+                peaks = append(ctx, &mut chips.sha256, &peaks, num_leaves, leaf)?;
+            }
+            let path = case
+                .path
+                .iter()
+                .map(|node| load_bytes(ctx, node))
+                .collect_vec();
+            let is_left_siblings = case
+                .is_left_siblings
+                .iter()
+                .map(|bit| gate.load_witness(ctx, Value::known(Fr::from(*bit as u64))))
+                .collect_vec();
+            let root = verify_inclusion(
+                ctx,
+                &mut chips.sha256,
+                &leaves[case.leaf_idx],
+                &path,
+                &is_left_siblings,
+                &peaks,
+                case.peak_idx,
+            )?;
+            for (computed, expected) in root.iter().zip(case.root.iter()) {
+                gate.assert_is_const(ctx, computed, Fr::from(*expected as u64));
+            }
+
+            let new_leaf = load_bytes(ctx, &case.new_leaf);
+            let new_peaks = append(ctx, &mut chips.sha256, &peaks, leaves.len(), &new_leaf)?;
+            assert_eq!(new_peaks.len(), case.new_peaks.len());
+            for (computed, expected) in new_peaks.iter().zip(case.new_peaks.iter()) {
+                for (computed, expected) in computed.iter().zip(expected.iter()) {
+                    gate.assert_is_const(ctx, computed, Fr::from(*expected as u64));
+                }
+            }
+            Ok(vec![])
+        })
+    }
+
+    /// Compresses `left || right` from the initial state, as `hash_node` does.
+    fn native_hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+        let block = left.iter().chain(right.iter()).cloned().collect_vec();
+        let mut state = INIT_STATE;
+        compress256(&mut state, &[GenericArray::clone_from_slice(&block)]);
+        digest_to_be_bytes(&state)
+    }
+
+    fn test_case(path: Vec<[u8; 32]>) -> TestCase {
+        let leaves = (0..4u8).map(|idx| [idx; 32]).collect_vec();
+        // three leaves have the peaks `H(l0, l1)` and `l2`, bagged into `H(H(l0, l1), l2)`.
+        let node01 = native_hash_node(&leaves[0], &leaves[1]);
+        let root = native_hash_node(&node01, &leaves[2]);
+        // the fourth leaf merges them into a single peak.
+        let node23 = native_hash_node(&leaves[2], &leaves[3]);
+        let new_peak = native_hash_node(&node01, &node23);
+        TestCase {
+            leaves: leaves[..3].to_vec(),
+            leaf_idx: 1,
+            path,
+            is_left_siblings: vec![true],
+            peak_idx: 0,
+            root,
+            new_leaf: leaves[3],
+            new_peaks: vec![new_peak],
+        }
+    }
+
+    #[test]
+    fn test_mmr_append_and_inclusion() {
+        assert_eq!(run(&test_case(vec![[0; 32]])), Ok(()));
+    }
+
+    #[test]
+    fn test_mmr_rejects_wrong_sibling() {
+        let mut sibling = [0; 32];
+        sibling[0] = 1;
+        assert!(run(&test_case(vec![sibling])).is_err());
+    }
+}
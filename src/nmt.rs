@@ -0,0 +1,335 @@
+//! Namespaced Merkle tree, as used by Celestia for data-availability roots.
+//! A node is `min_namespace || max_namespace || digest`, where leaves hash to `SHA-256(0x00 || namespace || data)` and
+//! inner nodes to `SHA-256(0x01 || left || right)`. Following Celestia, the parity namespace (all `0xff` bytes) is
+//! ignored when computing the maximum namespace of a node.
+use crate::mmr::conditional_swap;
+use crate::Sha256DynamicConfig;
+use alloc::{vec, vec::Vec};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+pub const NAMESPACE_LEN: usize = 29;
+/// Number of bytes of a serialized node.
+pub const NODE_LEN: usize = 2 * NAMESPACE_LEN + 32;
+pub const LEAF_PREFIX: u8 = 0;
+pub const NODE_PREFIX: u8 = 1;
+pub const PARITY_NAMESPACE: [u8; NAMESPACE_LEN] = [0xff; NAMESPACE_LEN];
+
+/// A node of the tree, whose cells are bytes.
+#[derive(Debug, Clone)]
+pub struct NmtNode<'a, F: PrimeField> {
+    pub min_namespace: Vec<AssignedValue<'a, F>>,
+    pub max_namespace: Vec<AssignedValue<'a, F>>,
+    pub digest: Vec<AssignedValue<'a, F>>,
+}
+
+impl<'a, F: PrimeField> NmtNode<'a, F> {
+    /// Splits the `NODE_LEN` bytes of a serialized node.
+    pub fn from_bytes(bytes: &[AssignedValue<'a, F>]) -> Self {
+        assert_eq!(bytes.len(), NODE_LEN);
+        Self {
+            min_namespace: bytes[..NAMESPACE_LEN].to_vec(),
+            max_namespace: bytes[NAMESPACE_LEN..2 * NAMESPACE_LEN].to_vec(),
+            digest: bytes[2 * NAMESPACE_LEN..].to_vec(),
+        }
+    }
+
+    /// Returns the `NODE_LEN` bytes of the node.
+    pub fn to_bytes(&self) -> Vec<AssignedValue<'a, F>> {
+        self.min_namespace
+            .iter()
+            .chain(self.max_namespace.iter())
+            .chain(self.digest.iter())
+            .cloned()
+            .collect_vec()
+    }
+}
+
+/// Returns the serialized leaf node of `data` in `namespace` out of circuit.
+pub fn native_hash_leaf(namespace: &[u8; NAMESPACE_LEN], data: &[u8]) -> [u8; NODE_LEN] {
+    let mut hasher = Sha256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(namespace);
+    hasher.update(data);
+    let mut node = [0u8; NODE_LEN];
+    node[..NAMESPACE_LEN].copy_from_slice(namespace);
+    node[NAMESPACE_LEN..2 * NAMESPACE_LEN].copy_from_slice(namespace);
+    node[2 * NAMESPACE_LEN..].copy_from_slice(&hasher.finalize());
+    node
+}
+
+/// Returns the serialized parent of two serialized nodes out of circuit.
+/// Panics if the namespaces of the children are out of order, as the circuit would not be satisfied.
+pub fn native_hash_node(left: &[u8; NODE_LEN], right: &[u8; NODE_LEN]) -> [u8; NODE_LEN] {
+    let left_max = &left[NAMESPACE_LEN..2 * NAMESPACE_LEN];
+    let right_min = &right[..NAMESPACE_LEN];
+    assert!(
+        left_max <= right_min,
+        "the namespaces of the children are out of order"
+    );
+    let mut hasher = Sha256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    let mut node = [0u8; NODE_LEN];
+    node[..NAMESPACE_LEN].copy_from_slice(&left[..NAMESPACE_LEN]);
+    let max = if right_min == PARITY_NAMESPACE {
+        left_max
+    } else {
+        &right[NAMESPACE_LEN..2 * NAMESPACE_LEN]
+    };
+    node[NAMESPACE_LEN..2 * NAMESPACE_LEN].copy_from_slice(max);
+    node[2 * NAMESPACE_LEN..].copy_from_slice(&hasher.finalize());
+    node
+}
+
+/// Packs namespace bytes into a big-endian integer of `8 * NAMESPACE_LEN` bits.
+/// The bytes are range-checked, so namespaces supplied by the prover can be compared.
+fn pack_namespace<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    namespace: &[AssignedValue<'a, F>],
+) -> AssignedValue<'a, F> {
+    assert_eq!(namespace.len(), NAMESPACE_LEN);
+    for byte in namespace.iter() {
+        range.range_check(ctx, byte, 8);
+    }
+    let mut coeff = F::one();
+    let mut coeffs = Vec::with_capacity(NAMESPACE_LEN);
+    for _ in 0..NAMESPACE_LEN {
+        coeffs.push(QuantumCell::Constant(coeff));
+        coeff *= F::from(256);
+    }
+    coeffs.reverse();
+    range.gate().inner_product(
+        ctx,
+        namespace.iter().map(QuantumCell::Existing).collect_vec(),
+        coeffs,
+    )
+}
+
+/// Hashes `data` in `namespace` into a leaf node.
+pub fn hash_leaf<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    namespace: &[AssignedValue<'a, F>],
+    data: &[AssignedValue<'a, F>],
+) -> Result<NmtNode<'a, F>, Error> {
+    assert_eq!(namespace.len(), NAMESPACE_LEN);
+    let prefix = sha256
+        .range()
+        .gate()
+        .load_constant(ctx, F::from(LEAF_PREFIX as u64));
+    let input = [prefix]
+        .iter()
+        .chain(namespace.iter())
+        .chain(data.iter())
+        .cloned()
+        .collect_vec();
+    let digest = sha256.digest_fixed(ctx, &input)?;
+    Ok(NmtNode {
+        min_namespace: namespace.to_vec(),
+        max_namespace: namespace.to_vec(),
+        digest,
+    })
+}
+
+/// Hashes two children into their parent.
+/// Constrains the maximum namespace of `left` to be at most the minimum namespace of `right`.
+pub fn hash_node<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    left: &NmtNode<'a, F>,
+    right: &NmtNode<'a, F>,
+) -> Result<NmtNode<'a, F>, Error> {
+    let range = sha256.range();
+    let gate = range.gate();
+    let left_max = pack_namespace(ctx, range, &left.max_namespace);
+    let right_min = pack_namespace(ctx, range, &right.min_namespace);
+    let is_out_of_order = range.is_less_than(
+        ctx,
+        QuantumCell::Existing(&right_min),
+        QuantumCell::Existing(&left_max),
+        8 * NAMESPACE_LEN,
+    );
+    gate.assert_is_const(ctx, &is_out_of_order, F::zero());
+
+    let parity_namespace = (0..8 * NAMESPACE_LEN).fold(F::zero(), |acc, _| acc.double() + F::one());
+    let is_parity = gate.is_equal(
+        ctx,
+        QuantumCell::Existing(&right_min),
+        QuantumCell::Constant(parity_namespace),
+    );
+    let max_namespace = left
+        .max_namespace
+        .iter()
+        .zip(right.max_namespace.iter())
+        .map(|(left_byte, right_byte)| {
+            gate.select(
+                ctx,
+                QuantumCell::Existing(left_byte),
+                QuantumCell::Existing(right_byte),
+                QuantumCell::Existing(&is_parity),
+            )
+        })
+        .collect_vec();
+
+    let prefix = gate.load_constant(ctx, F::from(NODE_PREFIX as u64));
+    let mut input = vec![prefix];
+    input.extend(left.to_bytes());
+    input.extend(right.to_bytes());
+    let digest = sha256.digest_fixed(ctx, &input)?;
+    Ok(NmtNode {
+        min_namespace: left.min_namespace.clone(),
+        max_namespace,
+        digest,
+    })
+}
+
+/// Constrains that `leaf` is included in the tree with `root`.
+/// `path` lists the siblings from the leaf up to the root and `is_left_siblings` are bits that are 1 when the
+/// sibling is the left child.
+/// This only proves inclusion: it does not prove that the path covers every leaf of the namespace.
+pub fn verify_inclusion<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    root: &NmtNode<'a, F>,
+    leaf: &NmtNode<'a, F>,
+    path: &[NmtNode<'a, F>],
+    is_left_siblings: &[AssignedValue<'a, F>],
+) -> Result<(), Error> {
+    assert_eq!(path.len(), is_left_siblings.len());
+    let gate = sha256.range().gate().clone();
+    let mut node = leaf.clone();
+    for (sibling, is_left_sibling) in path.iter().zip(is_left_siblings.iter()) {
+        gate.assert_bit(ctx, is_left_sibling);
+        let (left, right) = conditional_swap(
+            ctx,
+            &gate,
+            &node.to_bytes(),
+            &sibling.to_bytes(),
+            is_left_sibling,
+        );
+        node = hash_node(
+            ctx,
+            sha256,
+            &NmtNode::from_bytes(&left),
+            &NmtNode::from_bytes(&right),
+        )?;
+    }
+    for (computed, expected) in node.to_bytes().iter().zip(root.to_bytes().iter()) {
+        ctx.region
+            .constrain_equal(computed.cell(), expected.cell())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{prove, PlainShape};
+    use halo2_base::halo2_proofs::{circuit::Value, dev::VerifyFailure, halo2curves::bn256::Fr};
+
+    struct TestCase {
+        namespace: [u8; NAMESPACE_LEN],
+        data: Vec<u8>,
+        path: Vec<[u8; NODE_LEN]>,
+        is_left_siblings: Vec<bool>,
+        root: [u8; NODE_LEN],
+    }
+
+    fn run(case: &TestCase) -> Result<(), Vec<VerifyFailure>> {
+        prove::<PlainShape<8>, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let load_bytes = |ctx: &mut Context<_>, bytes: &[u8]| {
+                bytes
+                    .iter()
+                    .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                    .collect_vec()
+            };
+            let namespace = load_bytes(ctx, &case.namespace);
+            let data = load_bytes(ctx, &case.data);
+            let leaf = hash_leaf(ctx, &mut chips.sha256, &namespace, &data)?;
+            let path = case
+                .path
+                .iter()
+                .map(|node| NmtNode::from_bytes(&load_bytes(ctx, node)))
+                .collect_vec();
+            let root = NmtNode::from_bytes(&load_bytes(ctx, &case.root));
+            let is_left_siblings = case
+                .is_left_siblings
+                .iter()
+                .map(|bit| gate.load_witness(ctx, Value::known(Fr::from(*bit as u64))))
+                .collect_vec();
+            verify_inclusion(
+                ctx,
+                &mut chips.sha256,
+                &root,
+                &leaf,
+                &path,
+                &is_left_siblings,
+            )?;
+            Ok(vec![])
+        })
+    }
+
+    fn namespace(byte: u8) -> [u8; NAMESPACE_LEN] {
+        let mut namespace = [0u8; NAMESPACE_LEN];
+        namespace[NAMESPACE_LEN - 1] = byte;
+        namespace
+    }
+
+    #[test]
+    fn test_nmt_inclusion() {
+        // The last leaf is in the parity namespace, so the root spans namespaces 1 to 2.
+        let namespaces = [namespace(1), namespace(1), namespace(2), PARITY_NAMESPACE];
+        let leaves = namespaces
+            .iter()
+            .enumerate()
+            .map(|(idx, namespace)| native_hash_leaf(namespace, &[idx as u8; 32]))
+            .collect_vec();
+        let node01 = native_hash_node(&leaves[0], &leaves[1]);
+        let node23 = native_hash_node(&leaves[2], &leaves[3]);
+        let root = native_hash_node(&node01, &node23);
+        assert_eq!(root[..NAMESPACE_LEN], namespace(1));
+        assert_eq!(root[NAMESPACE_LEN..2 * NAMESPACE_LEN], namespace(2));
+        let case = TestCase {
+            namespace: namespaces[2],
+            data: vec![2; 32],
+            path: vec![leaves[3], node01],
+            is_left_siblings: vec![false, true],
+            root,
+        };
+        assert_eq!(run(&case), Ok(()));
+    }
+
+    #[test]
+    fn test_nmt_rejects_unordered_namespaces() {
+        let sibling = native_hash_leaf(&namespace(1), &[0; 32]);
+        let leaf = native_hash_leaf(&namespace(2), &[1; 32]);
+        // The root is hashed by hand as `native_hash_node` panics on children out of order.
+        let mut hasher = Sha256::new();
+        hasher.update([NODE_PREFIX]);
+        hasher.update(leaf);
+        hasher.update(sibling);
+        let mut root = [0u8; NODE_LEN];
+        root[..NAMESPACE_LEN].copy_from_slice(&namespace(2));
+        root[NAMESPACE_LEN..2 * NAMESPACE_LEN].copy_from_slice(&namespace(1));
+        root[2 * NAMESPACE_LEN..].copy_from_slice(&hasher.finalize());
+        let case = TestCase {
+            namespace: namespace(2),
+            data: vec![1; 32],
+            path: vec![sibling],
+            is_left_siblings: vec![false],
+            root,
+        };
+        assert!(run(&case).is_err());
+    }
+}
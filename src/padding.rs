@@ -0,0 +1,86 @@
+//! SHA-256 message padding (FIPS 180-4, Section 5.1.1).
+//!
+//! The message is followed by a `0x80` byte, zero bytes up to 56 modulo 64, and its bit length as a big-endian
+//! 64-bit integer. `pad_message` is for callers that pre-pad messages off-circuit, e.g. for
+//! `Sha256DynamicConfig::digest_blocks_unchecked`; `pad_assigned` pads assigned bytes whose length is fixed by the
+//! circuit.
+use crate::endian::words_from_be_bytes;
+use alloc::{vec, vec::Vec};
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+
+/// Number of bytes of a block.
+pub const BLOCK_BYTES: usize = 64;
+/// Number of 32-bit words of a block.
+pub const BLOCK_SIZE: usize = 16;
+
+/// Returns the number of blocks of the padded `msg_len`-byte message.
+pub fn num_blocks(msg_len: usize) -> usize {
+    (msg_len + 9 + BLOCK_BYTES - 1) / BLOCK_BYTES
+}
+
+/// Returns the padding appended to a `msg_len`-byte message.
+pub fn padding_bytes(msg_len: usize) -> Vec<u8> {
+    let mut padding = vec![0x80u8];
+    padding.resize(num_blocks(msg_len) * BLOCK_BYTES - msg_len - 8, 0);
+    padding.extend_from_slice(&(8 * msg_len as u64).to_be_bytes());
+    padding
+}
+
+/// Returns `msg` followed by its padding, a multiple of 64 bytes.
+pub fn pad_bytes(msg: &[u8]) -> Vec<u8> {
+    let mut padded = msg.to_vec();
+    padded.extend(padding_bytes(msg.len()));
+    padded
+}
+
+/// Returns the padded `msg` as blocks of big-endian words.
+pub fn pad_message(msg: &[u8]) -> Vec<[u32; BLOCK_SIZE]> {
+    pad_bytes(msg)
+        .chunks(BLOCK_BYTES)
+        .map(|block| {
+            let mut words = [0u32; BLOCK_SIZE];
+            words.copy_from_slice(&words_from_be_bytes(block));
+            words
+        })
+        .collect()
+}
+
+/// Returns the assigned `input` followed by its padding, assigned as constants.
+/// The length of `input` is fixed by the circuit, so the padding does not depend on any witness.
+pub fn pad_assigned<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    input: &[AssignedValue<'a, F>],
+) -> Vec<AssignedValue<'a, F>> {
+    let mut padded = input.to_vec();
+    for byte in padding_bytes(input.len()).into_iter() {
+        padded.push(gate.load_constant(ctx, F::from(byte as u64)));
+    }
+    padded
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pad_message() {
+        let blocks = pad_message(b"abc");
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0][0], 0x61626380);
+        assert!(blocks[0][1..15].iter().all(|word| *word == 0));
+        assert_eq!(blocks[0][15], 24);
+
+        // 55 bytes are the most that fit in one block with the padding.
+        for &(msg_len, expected) in [(55, 1), (56, 2), (64, 2), (119, 2), (120, 3)].iter() {
+            let padded = pad_bytes(&vec![0xff; msg_len]);
+            assert_eq!(padded.len(), expected * BLOCK_BYTES);
+            assert_eq!(padded[msg_len], 0x80);
+            assert_eq!(pad_message(&vec![0xff; msg_len]).len(), expected);
+        }
+    }
+}
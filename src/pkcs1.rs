@@ -0,0 +1,189 @@
+use alloc::{vec, vec::Vec};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+
+/// DER encoding of the SHA-256 `AlgorithmIdentifier` and the `OCTET STRING` header of `DigestInfo` (RFC 8017, Section 9.2).
+pub const SHA256_DIGEST_INFO_PREFIX: [u8; 19] = [
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+
+/// Encodes a SHA-256 digest as the EMSA-PKCS1-v1_5 message `0x00 || 0x01 || 0xff..ff || 0x00 || DigestInfo prefix || digest`.
+/// The message is returned as little-endian limbs of `limb_bits` bits, the layout used by big-integer RSA chips.
+/// `digest_bytes` must be range-checked bytes, e.g. `AssignedHashResult::output_bytes`.
+pub fn emsa_pkcs1_v15_encode<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    digest_bytes: &[AssignedValue<'a, F>],
+    modulus_bits: usize,
+    limb_bits: usize,
+) -> Vec<AssignedValue<'a, F>> {
+    assert_eq!(digest_bytes.len(), 32);
+    assert_eq!(limb_bits % 8, 0);
+    assert!(limb_bits <= 128);
+    assert_eq!(modulus_bits % limb_bits, 0);
+    let em_len = modulus_bits / 8;
+    let t_len = SHA256_DIGEST_INFO_PREFIX.len() + digest_bytes.len();
+    assert!(em_len >= t_len + 11);
+    let ps_len = em_len - t_len - 3;
+
+    let mut const_bytes = vec![0x00, 0x01];
+    const_bytes.extend(vec![0xff; ps_len]);
+    const_bytes.push(0x00);
+    const_bytes.extend_from_slice(&SHA256_DIGEST_INFO_PREFIX);
+    let em_byte = |idx: usize| {
+        if idx < const_bytes.len() {
+            QuantumCell::Constant(F::from(const_bytes[idx] as u64))
+        } else {
+            QuantumCell::Existing(&digest_bytes[idx - const_bytes.len()])
+        }
+    };
+
+    let limb_bytes = limb_bits / 8;
+    (0..modulus_bits / limb_bits)
+        .map(|limb_idx| {
+            gate.inner_product(
+                ctx,
+                (0..limb_bytes).map(|idx| em_byte(em_len - 1 - (limb_idx * limb_bytes + idx))),
+                (0..limb_bytes).map(|idx| QuantumCell::Constant(F::from_u128(1u128 << (8 * idx)))),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Sha256DynamicConfig;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem, Error},
+    };
+    use halo2_base::{
+        gates::{
+            range::{RangeConfig, RangeStrategy::Vertical},
+            RangeInstructions,
+        },
+        SKIP_FIRST_PASS,
+    };
+    use sha2::{Digest, Sha256};
+
+    const MODULUS_BITS: usize = 2048;
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        msg: Vec<u8>,
+        limb_bits: usize,
+        expected_limbs: Vec<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[3], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![128], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "pkcs1 test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let result = sha256.digest(ctx, &self.msg, None)?;
+                    let limbs = emsa_pkcs1_v15_encode(
+                        ctx,
+                        gate,
+                        &result.output_bytes,
+                        MODULUS_BITS,
+                        self.limb_bits,
+                    );
+                    assert_eq!(limbs.len(), self.expected_limbs.len());
+                    for (limb, expected) in limbs.iter().zip(self.expected_limbs.iter()) {
+                        gate.assert_is_const(ctx, limb, *expected);
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    /// Returns the EMSA-PKCS1-v1_5 encoding of `msg` for a 2048-bit modulus, following RFC 8017, Section 9.2.
+    fn native_encoding(msg: &[u8]) -> Vec<u8> {
+        // the SHA-256 `DigestInfo` prefix given in note 1 of Section 9.2.
+        let digest_info_prefix = hex::decode("3031300d060960864801650304020105000420").unwrap();
+        let t = [digest_info_prefix, Sha256::digest(msg).to_vec()].concat();
+        let em_len = MODULUS_BITS / 8;
+        let ps = vec![0xff; em_len - t.len() - 3];
+        [vec![0x00, 0x01], ps, vec![0x00], t].concat()
+    }
+
+    /// Splits the big-endian `em` into little-endian limbs of `limb_bits` bits.
+    fn native_limbs(em: &[u8], limb_bits: usize) -> Vec<Fr> {
+        em.rchunks(limb_bits / 8)
+            .map(|chunk| {
+                let limb = chunk
+                    .iter()
+                    .fold(0u128, |acc, byte| (acc << 8) | *byte as u128);
+                Fr::from_u128(limb)
+            })
+            .collect()
+    }
+
+    fn test_circuit(msg: &[u8], limb_bits: usize, expected_msg: &[u8]) -> TestCircuit<Fr> {
+        let em = native_encoding(expected_msg);
+        assert_eq!(em.len(), 256);
+        assert_eq!(&em[..3], &[0x00, 0x01, 0xff]);
+        TestCircuit {
+            msg: msg.to_vec(),
+            limb_bits,
+            expected_limbs: native_limbs(&em, limb_bits),
+        }
+    }
+
+    #[test]
+    fn test_emsa_pkcs1_v15_encode() {
+        for limb_bits in [64, 128] {
+            let circuit = test_circuit(b"hello world", limb_bits, b"hello world");
+            assert_eq!(circuit.expected_limbs.len(), MODULUS_BITS / limb_bits);
+            let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_emsa_pkcs1_v15_encode_wrong_message() {
+        for limb_bits in [64, 128] {
+            let circuit = test_circuit(b"hello world", limb_bits, b"hello world!");
+            let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
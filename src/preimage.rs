@@ -0,0 +1,243 @@
+use alloc::{vec, vec::Vec};
+use core::any::TypeId;
+use core::marker::PhantomData;
+
+use crate::padding::pad_bytes;
+use crate::Sha256DynamicConfig;
+use halo2_base::halo2_proofs::{
+    circuit::{Cell, FloorPlanner, Layouter, SimpleFloorPlanner},
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2_base::{
+    gates::{
+        range::{RangeConfig, RangeStrategy::Vertical},
+        RangeInstructions,
+    },
+    utils::PrimeField,
+    SKIP_FIRST_PASS,
+};
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Clone)]
+pub struct Sha256PreimageConfig<F: PrimeField> {
+    pub sha256: Sha256DynamicConfig<F>,
+    pub digest_column: Column<Instance>,
+}
+
+/// Returns whether the region closures of a circuit laid out by `P` skip their first call, see `SKIP_FIRST_PASS`.
+/// `SimpleFloorPlanner` calls them twice in one synthesis, first only to measure the region. `V1` synthesizes the
+/// circuit once to measure and once to assign, calling each closure once per synthesis, so no call can be skipped.
+pub(crate) fn skip_first_pass<P: FloorPlanner + 'static>() -> bool {
+    SKIP_FIRST_PASS && TypeId::of::<P>() == TypeId::of::<SimpleFloorPlanner>()
+}
+
+/// Proves knowledge of a private preimage of at most `MAX_BYTE_SIZE` bytes whose SHA-256 digest is the public input.
+/// The 32 digest bytes are the first 32 rows of the instance column, as returned by `instances`.
+/// A circuit built with `new_public_message` also exposes the message: the digest is followed by the message length
+/// and the padded message bytes, so a verifier can check properties of a public message bound to its digest.
+///
+/// `P` is the floor planner. All the cells are in one region, so `floor_planner::V1` places them where
+/// `SimpleFloorPlanner` does, with the same area and verifying key, but it assigns the circuit once more to measure
+/// it; `SimpleFloorPlanner` is therefore the default.
+#[derive(Debug)]
+pub struct Sha256PreimageCircuit<
+    F: PrimeField,
+    const MAX_BYTE_SIZE: usize,
+    P: FloorPlanner + 'static = SimpleFloorPlanner,
+> {
+    preimage: Vec<u8>,
+    is_message_public: bool,
+    _f: PhantomData<(F, P)>,
+}
+
+impl<F: PrimeField, const MAX_BYTE_SIZE: usize, P: FloorPlanner + 'static> Clone
+    for Sha256PreimageCircuit<F, MAX_BYTE_SIZE, P>
+{
+    fn clone(&self) -> Self {
+        Self {
+            preimage: self.preimage.clone(),
+            is_message_public: self.is_message_public,
+            _f: PhantomData,
+        }
+    }
+}
+
+impl<F: PrimeField, const MAX_BYTE_SIZE: usize, P: FloorPlanner + 'static>
+    Sha256PreimageCircuit<F, MAX_BYTE_SIZE, P>
+{
+    pub const K: u32 = 17;
+    const NUM_FIXED: usize = 1;
+    const NUM_LOOKUP_ADVICE: usize = 1;
+    const LOOKUP_BITS: usize = 16;
+
+    pub fn new(preimage: Vec<u8>) -> Self {
+        assert!(preimage.len() + 9 <= Self::max_padded_byte_size());
+        Self {
+            preimage,
+            is_message_public: false,
+            _f: PhantomData,
+        }
+    }
+
+    pub fn new_public_message(message: Vec<u8>) -> Self {
+        Self {
+            is_message_public: true,
+            ..Self::new(message)
+        }
+    }
+
+    /// Returns the public inputs, i.e., the digest bytes of the preimage, followed by the message length and the
+    /// `max_padded_byte_size` bytes of the padded message if the message is public.
+    pub fn instances(&self) -> Vec<Vec<F>> {
+        let digest = Sha256::digest(&self.preimage);
+        let mut instances = digest
+            .iter()
+            .map(|byte| F::from(*byte as u64))
+            .collect::<Vec<F>>();
+        if self.is_message_public {
+            instances.push(F::from(self.preimage.len() as u64));
+            let mut padded = pad_bytes(&self.preimage);
+            padded.resize(Self::max_padded_byte_size(), 0);
+            instances.extend(padded.into_iter().map(|byte| F::from(byte as u64)));
+        }
+        vec![instances]
+    }
+
+    fn max_padded_byte_size() -> usize {
+        (MAX_BYTE_SIZE + 9 + 63) / 64 * 64
+    }
+
+    fn num_advice() -> usize {
+        (Self::max_padded_byte_size() / 64 + 1) / 2 + 1
+    }
+}
+
+impl<F: PrimeField, const MAX_BYTE_SIZE: usize, P: FloorPlanner + 'static> Circuit<F>
+    for Sha256PreimageCircuit<F, MAX_BYTE_SIZE, P>
+{
+    type Config = Sha256PreimageConfig<F>;
+    type FloorPlanner = P;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            preimage: vec![],
+            is_message_public: self.is_message_public,
+            _f: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let range_config = RangeConfig::configure(
+            meta,
+            Vertical,
+            &[Self::num_advice()],
+            &[Self::NUM_LOOKUP_ADVICE],
+            Self::NUM_FIXED,
+            Self::LOOKUP_BITS,
+            0,
+            Self::K as usize,
+        );
+        let digest_column = meta.instance_column();
+        meta.enable_equality(digest_column);
+        let sha256 = Sha256DynamicConfig::configure(
+            meta,
+            vec![Self::max_padded_byte_size()],
+            range_config,
+            8,
+            2,
+            true,
+        );
+        Self::Config {
+            sha256,
+            digest_column,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let mut sha256 = config.sha256.clone();
+        let range = sha256.range().clone();
+        range.load_lookup_table(&mut layouter)?;
+        sha256.load(&mut layouter)?;
+        let mut first_pass = skip_first_pass::<P>();
+        let mut public_cells: Vec<Cell> = vec![];
+        layouter.assign_region(
+            || "sha256 preimage",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let ctx = &mut sha256.new_context(region);
+                let result = sha256.digest(ctx, &self.preimage, None)?;
+                public_cells = result.output_bytes.iter().map(|byte| byte.cell()).collect();
+                if self.is_message_public {
+                    public_cells.push(result.input_len.cell());
+                    public_cells.extend(result.input_bytes.iter().map(|byte| byte.cell()));
+                }
+                range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        for (idx, cell) in public_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.digest_column, idx)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::floor_planner::V1,
+        dev::MockProver,
+        halo2curves::bn256::{Bn256, Fr},
+        plonk::keygen_vk,
+        poly::{commitment::ParamsProver, kzg::commitment::ParamsKZG},
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_sha256_preimage_circuit() {
+        let circuit = Sha256PreimageCircuit::<Fr, 100>::new(b"abc".to_vec());
+        let instances = circuit.instances();
+        let prover =
+            MockProver::run(Sha256PreimageCircuit::<Fr, 100>::K, &circuit, instances).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_public_message_circuit() {
+        let circuit = Sha256PreimageCircuit::<Fr, 100>::new_public_message(vec![0x61; 56]);
+        let instances = circuit.instances();
+        assert_eq!(instances[0].len(), 32 + 1 + 128);
+        let prover =
+            MockProver::run(Sha256PreimageCircuit::<Fr, 100>::K, &circuit, instances).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha256_preimage_circuit_v1() {
+        let circuit = Sha256PreimageCircuit::<Fr, 100, V1>::new(b"abc".to_vec());
+        let instances = circuit.instances();
+        let prover =
+            MockProver::run(Sha256PreimageCircuit::<Fr, 100>::K, &circuit, instances).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // both planners place the single region at the same rows, so the fixed columns and the area are the same.
+        let params = ParamsKZG::<Bn256>::setup(
+            Sha256PreimageCircuit::<Fr, 100>::K,
+            StdRng::seed_from_u64(0),
+        );
+        let simple_vk = keygen_vk(&params, &Sha256PreimageCircuit::<Fr, 100>::new(vec![])).unwrap();
+        let v1_vk = keygen_vk(&params, &circuit).unwrap();
+        assert_eq!(
+            format!("{:?}", simple_vk.transcript_repr()),
+            format!("{:?}", v1_vk.transcript_repr())
+        );
+    }
+}
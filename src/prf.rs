@@ -0,0 +1,152 @@
+use crate::Sha256DynamicConfig;
+use alloc::vec::Vec;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use sha2::{Digest, Sha256};
+
+pub const PRF_KEY_BYTES: usize = 32;
+
+/// Computes `SHA256(key || domain || input)` out of the circuit.
+pub fn compute_prf(domain: u8, key: &[u8; PRF_KEY_BYTES], input: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(key);
+    hasher.update([domain]);
+    hasher.update(input);
+    hasher.finalize().into()
+}
+
+/// Returns the digest bytes of `SHA256(key || domain || input)` for a 32-byte `key` and a constant domain-separation byte.
+/// The length of `input` is fixed by the circuit, so the padding is assigned as constants.
+pub fn prf<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    domain: u8,
+    key: &[AssignedValue<'a, F>],
+    input: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    assert_eq!(key.len(), PRF_KEY_BYTES);
+    let domain = sha256
+        .range()
+        .gate()
+        .load_constant(ctx, F::from(domain as u64));
+    let mut prf_input = key.to_vec();
+    prf_input.push(domain);
+    prf_input.extend_from_slice(input);
+    sha256.digest_fixed(ctx, &prf_input)
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{
+        gates::range::{RangeConfig, RangeStrategy::Vertical},
+        SKIP_FIRST_PASS,
+    };
+    use itertools::Itertools;
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        domain: u8,
+        key: [u8; PRF_KEY_BYTES],
+        input: Vec<u8>,
+        expected: [u8; 32],
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[3], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "prf test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let load_bytes = |ctx: &mut Context<F>, bytes: &[u8]| {
+                        bytes
+                            .iter()
+                            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                            .collect_vec()
+                    };
+                    let key = load_bytes(ctx, &self.key);
+                    let input = load_bytes(ctx, &self.input);
+                    let output = prf(ctx, &mut sha256, self.domain, &key, &input)?;
+                    for (assigned, expected) in output.iter().zip(self.expected.iter()) {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_prf() {
+        let key = [0x0b; PRF_KEY_BYTES];
+        let input = b"nullifier".to_vec();
+        let expected = compute_prf(1, &key, &input);
+        let message = [key.as_slice(), &[1], &input].concat();
+        assert_eq!(expected.to_vec(), Sha256::digest(message).to_vec());
+        let circuit = TestCircuit::<Fr> {
+            domain: 1,
+            key,
+            input,
+            expected,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_prf_rejects_other_domain() {
+        let key = [0x0b; PRF_KEY_BYTES];
+        let input = b"nullifier".to_vec();
+        let circuit = TestCircuit::<Fr> {
+            domain: 2,
+            key,
+            expected: compute_prf(1, &key, &input),
+            input,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
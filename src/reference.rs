@@ -0,0 +1,141 @@
+//! Native SHA-256 that records the intermediate values assigned by the circuit.
+//!
+//! The trace records the values that `sha256_compression` assigns at the end of each step: the message schedule,
+//! then the working variables `a..h` after each round, then the output state. It is an independent model computed
+//! with plain `u32` arithmetic from FIPS 180-4, not from the spread decomposition of the circuit, so that a mistake in
+//! the decomposition shows up as a divergence instead of being reproduced. It needs no circuit, so it is cheap enough
+//! to check the circuit witness in unit tests.
+use crate::endian::{digest_to_be_bytes, words_from_be_bytes};
+use crate::padding::pad_bytes;
+use crate::{INIT_STATE, NUM_ROUND, NUM_STATE_WORD, ROUND_CONSTANTS};
+use alloc::vec::Vec;
+
+/// Intermediate values of the compression of one 64-byte block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockTrace {
+    /// State words before the block.
+    pub state_in: [u32; NUM_STATE_WORD],
+    /// Words `W_0..W_63` of the message schedule.
+    pub schedule: [u32; NUM_ROUND],
+    /// Working variables `[a, b, c, d, e, f, g, h]` after each round.
+    pub rounds: [[u32; NUM_STATE_WORD]; NUM_ROUND],
+    /// State words after the block, i.e., `state_in` plus the working variables of the last round.
+    pub state_out: [u32; NUM_STATE_WORD],
+}
+
+/// Intermediate values of hashing a whole message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sha256Trace {
+    /// The message followed by its padding, a multiple of 64 bytes.
+    pub padded_input: Vec<u8>,
+    /// One trace per block of `padded_input`.
+    pub blocks: Vec<BlockTrace>,
+    pub digest: [u8; 32],
+}
+
+/// Returns the trace of hashing `msg` from the IV.
+pub fn sha256_trace(msg: &[u8]) -> Sha256Trace {
+    let padded_input = pad_bytes(msg);
+
+    let mut state = INIT_STATE;
+    let mut blocks = Vec::with_capacity(padded_input.len() / 64);
+    for block in padded_input.chunks(64) {
+        let trace = compression_trace(&state, block);
+        state = trace.state_out;
+        blocks.push(trace);
+    }
+    Sha256Trace {
+        padded_input,
+        blocks,
+        digest: digest_to_be_bytes(&state),
+    }
+}
+
+/// Returns the state after compressing `prefix`, a multiple of 64 bytes, from the IV.
+/// It can be published once and passed to `Sha256DynamicConfig::digest_from_midstate` by every proof over a
+/// message starting with `prefix`.
+pub fn midstate(prefix: &[u8]) -> [u32; NUM_STATE_WORD] {
+    assert_eq!(prefix.len() % 64, 0);
+    let mut state = INIT_STATE;
+    for block in prefix.chunks(64) {
+        state = compression_trace(&state, block).state_out;
+    }
+    state
+}
+
+/// Returns the trace of compressing a 64-byte `block` from `state_in`.
+/// The values are computed with `u32` rotations and wrapping additions, independently of the circuit; only the values
+/// at the step boundaries, not the spread limbs or the unreduced sums of the circuit, are comparable.
+pub fn compression_trace(state_in: &[u32; NUM_STATE_WORD], block: &[u8]) -> BlockTrace {
+    assert_eq!(block.len(), 64);
+    let mut schedule = [0u32; NUM_ROUND];
+    schedule[..16].copy_from_slice(&words_from_be_bytes(block));
+    for idx in 16..NUM_ROUND {
+        let w15 = schedule[idx - 15];
+        let w2 = schedule[idx - 2];
+        let sigma0 = w15.rotate_right(7) ^ w15.rotate_right(18) ^ (w15 >> 3);
+        let sigma1 = w2.rotate_right(17) ^ w2.rotate_right(19) ^ (w2 >> 10);
+        schedule[idx] = schedule[idx - 16]
+            .wrapping_add(sigma0)
+            .wrapping_add(schedule[idx - 7])
+            .wrapping_add(sigma1);
+    }
+
+    let mut rounds = [[0u32; NUM_STATE_WORD]; NUM_ROUND];
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state_in;
+    for idx in 0..NUM_ROUND {
+        let sigma_upper1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(sigma_upper1)
+            .wrapping_add(ch)
+            .wrapping_add(ROUND_CONSTANTS[idx])
+            .wrapping_add(schedule[idx]);
+        let sigma_upper0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = sigma_upper0.wrapping_add(maj);
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        rounds[idx] = [a, b, c, d, e, f, g, h];
+    }
+
+    let mut state_out = *state_in;
+    for (out, word) in state_out.iter_mut().zip(rounds[NUM_ROUND - 1].iter()) {
+        *out = out.wrapping_add(*word);
+    }
+    BlockTrace {
+        state_in: *state_in,
+        schedule,
+        rounds,
+        state_out,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use generic_array::GenericArray;
+    use sha2::{compress256, Digest, Sha256};
+
+    #[test]
+    fn test_sha256_trace() {
+        for len in [0usize, 3, 55, 56, 64, 119, 200] {
+            let msg = (0..len).map(|idx| (idx * 7 + 1) as u8).collect::<Vec<u8>>();
+            let trace = sha256_trace(&msg);
+            assert_eq!(trace.digest[..], Sha256::digest(&msg)[..]);
+            assert_eq!(trace.blocks.len(), trace.padded_input.len() / 64);
+            let mut state = INIT_STATE;
+            for (block, block_trace) in trace.padded_input.chunks(64).zip(trace.blocks.iter()) {
+                assert_eq!(block_trace.state_in, state);
+                compress256(&mut state, &[GenericArray::clone_from_slice(block)]);
+                assert_eq!(block_trace.state_out, state);
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use crate::bitwise::{even_and_odd, rotl};
+use crate::compression::{mod_u32, state_to_spread_u32, three_add};
+use crate::endian::word_from_le_bytes;
+use crate::spread::SpreadConfig;
+use crate::{AssignedHashResult, Sha256DynamicConfig, SpreadU32};
+use alloc::{vec, vec::Vec};
+use halo2_base::halo2_proofs::{
+    circuit::Layouter,
+    plonk::{ConstraintSystem, Error},
+};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+const NUM_RIPEMD_ROUND: usize = 80;
+const NUM_RIPEMD_STATE_WORD: usize = 5;
+const MASK_EVEN_32: u64 = 0x55555555;
+
+pub const RIPEMD160_INIT_STATE: [u32; NUM_RIPEMD_STATE_WORD] =
+    [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+const LEFT_CONSTANTS: [u32; 5] = [0x00000000, 0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xa953fd4e];
+const RIGHT_CONSTANTS: [u32; 5] = [0x50a28be6, 0x5c4dd124, 0x6d703ef3, 0x7a6d76e9, 0x00000000];
+const LEFT_WORD_INDEXES: [usize; NUM_RIPEMD_ROUND] = [
+    0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 7, 4, 13, 1, 10, 6, 15, 3, 12, 0, 9, 5,
+    2, 14, 11, 8, 3, 10, 14, 4, 9, 15, 8, 1, 2, 7, 0, 6, 13, 11, 5, 12, 1, 9, 11, 10, 0, 8, 12, 4,
+    13, 3, 7, 15, 14, 5, 6, 2, 4, 0, 5, 9, 7, 12, 2, 10, 14, 1, 3, 8, 11, 6, 15, 13,
+];
+const RIGHT_WORD_INDEXES: [usize; NUM_RIPEMD_ROUND] = [
+    5, 14, 7, 0, 9, 2, 11, 4, 13, 6, 15, 8, 1, 10, 3, 12, 6, 11, 3, 7, 0, 13, 5, 10, 14, 15, 8, 12,
+    4, 9, 1, 2, 15, 5, 1, 3, 7, 14, 6, 9, 11, 8, 12, 2, 10, 0, 4, 13, 8, 6, 4, 1, 3, 11, 15, 0, 5,
+    12, 2, 13, 9, 7, 10, 14, 12, 15, 10, 4, 1, 5, 8, 7, 6, 2, 13, 14, 0, 3, 9, 11,
+];
+const LEFT_ROTATIONS: [usize; NUM_RIPEMD_ROUND] = [
+    11, 14, 15, 12, 5, 8, 7, 9, 11, 13, 14, 15, 6, 7, 9, 8, 7, 6, 8, 13, 11, 9, 7, 15, 7, 12, 15,
+    9, 11, 7, 13, 12, 11, 13, 6, 7, 14, 9, 13, 15, 14, 8, 13, 6, 5, 12, 7, 5, 11, 12, 14, 15, 14,
+    15, 9, 8, 9, 14, 5, 6, 8, 6, 5, 12, 9, 15, 5, 11, 6, 8, 13, 12, 5, 12, 13, 14, 11, 8, 5, 6,
+];
+const RIGHT_ROTATIONS: [usize; NUM_RIPEMD_ROUND] = [
+    8, 9, 9, 11, 13, 15, 15, 5, 7, 7, 8, 11, 14, 14, 12, 6, 9, 13, 15, 7, 12, 8, 9, 11, 7, 7, 12,
+    7, 6, 15, 13, 11, 9, 7, 15, 11, 8, 6, 6, 14, 12, 13, 5, 14, 13, 13, 7, 5, 15, 5, 8, 11, 14, 14,
+    6, 14, 6, 9, 12, 9, 12, 5, 15, 8, 8, 5, 12, 9, 12, 5, 14, 6, 8, 13, 6, 5, 15, 13, 11, 11,
+];
+
+/// RIPEMD-160 chip for inputs of a static length.
+/// The boolean functions are evaluated on the spread representation, in the same way as `ch` and `maj` of SHA-256.
+#[derive(Debug, Clone)]
+pub struct Ripemd160Config<F: PrimeField> {
+    range: RangeConfig<F>,
+    spread_config: SpreadConfig<F>,
+}
+
+impl<F: PrimeField> Ripemd160Config<F> {
+    const ONE_ROUND_INPUT_BYTES: usize = 64;
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        range: RangeConfig<F>,
+        num_bits_lookup: usize,
+        num_advice_columns: usize,
+    ) -> Self {
+        let spread_config = SpreadConfig::configure(meta, num_bits_lookup, num_advice_columns);
+        Self {
+            range,
+            spread_config,
+        }
+    }
+
+    /// Returns the 20 bytes of the RIPEMD-160 digest of `input`.
+    /// `input` must be range-checked bytes; the padding depends only on `input.len()` and is assigned as constants.
+    pub fn digest<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
+        let input_byte_size = input.len();
+        let num_round = (input_byte_size + 9 + one_round_size - 1) / one_round_size;
+        let zero_padding_byte_size = num_round * one_round_size - input_byte_size - 9;
+        let range = &self.range;
+        let gate = range.gate();
+
+        let mut padding = vec![0x80u8];
+        padding.extend(vec![0; zero_padding_byte_size]);
+        padding.extend_from_slice(&(8 * input_byte_size as u64).to_le_bytes());
+        let mut padded_input = input.to_vec();
+        for byte in padding.into_iter() {
+            padded_input.push(gate.load_constant(ctx, F::from(byte as u64)));
+        }
+        assert_eq!(padded_input.len(), num_round * one_round_size);
+
+        let mut state = RIPEMD160_INIT_STATE
+            .iter()
+            .map(|word| gate.load_constant(ctx, F::from(*word as u64)))
+            .collect_vec();
+        for block in padded_input.chunks(one_round_size) {
+            state = ripemd160_compression(ctx, range, &mut self.spread_config, block, &state)?;
+        }
+
+        let mut output_bytes = vec![];
+        for word in state.iter() {
+            let word_val = word.value().map(|v| v.get_lower_32());
+            let bytes = (0..4)
+                .map(|idx| {
+                    let byte = gate.load_witness(
+                        ctx,
+                        word_val.map(|v| F::from(((v >> (8 * idx)) & 0xff) as u64)),
+                    );
+                    range.range_check(ctx, &byte, 8);
+                    byte
+                })
+                .collect_vec();
+            let recomposed = gate.inner_product(
+                ctx,
+                bytes.iter().map(QuantumCell::Existing),
+                (0..4).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * idx)))),
+            );
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(word),
+                QuantumCell::Existing(&recomposed),
+            );
+            output_bytes.extend(bytes);
+        }
+        Ok(output_bytes)
+    }
+
+    /// Computes `RIPEMD160(SHA256(input))`, the hash used for Bitcoin addresses.
+    /// Returns the SHA-256 result, which binds the input bytes, together with the 20 output bytes.
+    pub fn hash160<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        sha256: &mut Sha256DynamicConfig<F>,
+        input: &[u8],
+    ) -> Result<(AssignedHashResult<'b, F>, Vec<AssignedValue<'b, F>>), Error> {
+        let sha256_result = sha256.digest(ctx, input, None)?;
+        let output_bytes = self.digest(ctx, &sha256_result.output_bytes)?;
+        Ok((sha256_result, output_bytes))
+    }
+
+    pub fn range(&self) -> &RangeConfig<F> {
+        &self.range
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.spread_config.load(layouter)
+    }
+}
+
+pub fn ripemd160_compression<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    debug_assert_eq!(assigned_input_bytes.len(), 64);
+    debug_assert_eq!(pre_state_words.len(), NUM_RIPEMD_STATE_WORD);
+    let gate = range.gate();
+    // message words are little-endian.
+    let message_u32s = assigned_input_bytes
+        .chunks(4)
+        .map(|bytes| word_from_le_bytes(ctx, gate, bytes))
+        .collect_vec();
+
+    let mut left = pre_state_words.to_vec();
+    let mut right = pre_state_words.to_vec();
+    for idx in 0..NUM_RIPEMD_ROUND {
+        let func_idx = idx / 16;
+        spread_config.set_annotation("left line of RIPEMD-160 round", idx);
+        left = ripemd160_round(
+            ctx,
+            range,
+            spread_config,
+            &left,
+            func_idx,
+            &message_u32s[LEFT_WORD_INDEXES[idx]],
+            LEFT_CONSTANTS[func_idx],
+            LEFT_ROTATIONS[idx],
+        )?;
+        spread_config.set_annotation("right line of RIPEMD-160 round", idx);
+        right = ripemd160_round(
+            ctx,
+            range,
+            spread_config,
+            &right,
+            4 - func_idx,
+            &message_u32s[RIGHT_WORD_INDEXES[idx]],
+            RIGHT_CONSTANTS[func_idx],
+            RIGHT_ROTATIONS[idx],
+        )?;
+    }
+
+    let next_state_words = (0..NUM_RIPEMD_STATE_WORD)
+        .map(|idx| {
+            let add1 = gate.add(
+                ctx,
+                QuantumCell::Existing(&pre_state_words[(idx + 1) % 5]),
+                QuantumCell::Existing(&left[(idx + 2) % 5]),
+            );
+            let add2 = gate.add(
+                ctx,
+                QuantumCell::Existing(&add1),
+                QuantumCell::Existing(&right[(idx + 3) % 5]),
+            );
+            mod_u32(ctx, range, &add2)
+        })
+        .collect_vec();
+    Ok(next_state_words)
+}
+
+fn ripemd160_round<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    state: &[AssignedValue<'a, F>],
+    func_idx: usize,
+    message_word: &AssignedValue<'a, F>,
+    round_constant: u32,
+    rotation: usize,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let gate = range.gate();
+    let (a, b, c, d, e) = (&state[0], &state[1], &state[2], &state[3], &state[4]);
+    let b_spread = state_to_spread_u32(ctx, range, spread_config, b)?;
+    let c_spread = state_to_spread_u32(ctx, range, spread_config, c)?;
+    let d_spread = state_to_spread_u32(ctx, range, spread_config, d)?;
+    let f_term = boolean_func(
+        ctx,
+        range,
+        spread_config,
+        func_idx,
+        &b_spread,
+        &c_spread,
+        &d_spread,
+    )?;
+    let add1 = gate.add(
+        ctx,
+        QuantumCell::Existing(a),
+        QuantumCell::Existing(&f_term),
+    );
+    let add2 = gate.add(
+        ctx,
+        QuantumCell::Existing(&add1),
+        QuantumCell::Existing(message_word),
+    );
+    let add3 = gate.add(
+        ctx,
+        QuantumCell::Existing(&add2),
+        QuantumCell::Constant(F::from(round_constant as u64)),
+    );
+    let sum = mod_u32(ctx, range, &add3);
+    let rotated = rotl(ctx, range, &sum, rotation);
+    let t = {
+        let add = gate.add(
+            ctx,
+            QuantumCell::Existing(&rotated),
+            QuantumCell::Existing(e),
+        );
+        mod_u32(ctx, range, &add)
+    };
+    let c_rotated = rotl(ctx, range, c, 10);
+    Ok(vec![e.clone(), t, b.clone(), c_rotated, d.clone()])
+}
+
+/// Evaluates the `func_idx`-th boolean function of RIPEMD-160 limb by limb.
+/// `f0 = x ^ y ^ z`, `f1 = (x & y) | (!x & z)`, `f2 = (x | !y) ^ z`, `f3 = (x & z) | (y & !z)` and `f4 = x ^ (y | !z)`.
+pub(crate) fn boolean_func<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    func_idx: usize,
+    x: &SpreadU32<'a, F>,
+    y: &SpreadU32<'a, F>,
+    z: &SpreadU32<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let gate = range.gate();
+    let (x_lo, x_hi) = x;
+    let (y_lo, y_hi) = y;
+    let (z_lo, z_hi) = z;
+    let mut out_limbs = vec![];
+    for (x, y, z) in [(x_lo, y_lo, z_lo), (x_hi, y_hi, z_hi)] {
+        let out = match func_idx {
+            0 => {
+                let sum = three_add(
+                    ctx,
+                    gate,
+                    QuantumCell::Existing(x),
+                    QuantumCell::Existing(y),
+                    QuantumCell::Existing(z),
+                );
+                let ((even, _), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+                even
+            }
+            1 | 3 => {
+                // f3 is f1 with the arguments (z, x, y).
+                let (x, y, z) = if func_idx == 1 { (x, y, z) } else { (z, x, y) };
+                let p = gate.add(ctx, QuantumCell::Existing(x), QuantumCell::Existing(y));
+                let x_neg = gate.neg(ctx, QuantumCell::Existing(x));
+                let q = three_add(
+                    ctx,
+                    gate,
+                    QuantumCell::Constant(F::from(MASK_EVEN_32)),
+                    QuantumCell::Existing(&x_neg),
+                    QuantumCell::Existing(z),
+                );
+                let ((_, p_odd), _) = even_and_odd(ctx, range, spread_config, &p)?;
+                let ((_, q_odd), _) = even_and_odd(ctx, range, spread_config, &q)?;
+                gate.add(
+                    ctx,
+                    QuantumCell::Existing(&p_odd),
+                    QuantumCell::Existing(&q_odd),
+                )
+            }
+            2 | 4 => {
+                // f2 = !(!x & y) ^ z and f4 = x ^ !(!y & z).
+                let (x, y, z) = if func_idx == 2 { (x, y, z) } else { (y, z, x) };
+                let x_neg = gate.neg(ctx, QuantumCell::Existing(x));
+                let t = three_add(
+                    ctx,
+                    gate,
+                    QuantumCell::Constant(F::from(MASK_EVEN_32)),
+                    QuantumCell::Existing(&x_neg),
+                    QuantumCell::Existing(y),
+                );
+                let (_, (_, t_odd_spread)) = even_and_odd(ctx, range, spread_config, &t)?;
+                let t_neg = gate.neg(ctx, QuantumCell::Existing(&t_odd_spread));
+                let u = three_add(
+                    ctx,
+                    gate,
+                    QuantumCell::Constant(F::from(MASK_EVEN_32)),
+                    QuantumCell::Existing(&t_neg),
+                    QuantumCell::Existing(z),
+                );
+                let ((u_even, _), _) = even_and_odd(ctx, range, spread_config, &u)?;
+                u_even
+            }
+            _ => panic!("invalid RIPEMD-160 function index {}", func_idx),
+        };
+        out_limbs.push(out);
+    }
+    Ok(gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&out_limbs[1]),
+        QuantumCell::Constant(F::from(1u64 << 16)),
+        QuantumCell::Existing(&out_limbs[0]),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::SimpleFloorPlanner, dev::MockProver, halo2curves::bn256::Fr, plonk::Circuit,
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct TestConfig<F: PrimeField> {
+        sha256: Sha256DynamicConfig<F>,
+        ripemd160: Ripemd160Config<F>,
+    }
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        test_input: Vec<u8>,
+        expected_output: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = TestConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[6], &[1], 1, 16, 0, 17);
+            let sha256 =
+                Sha256DynamicConfig::configure(meta, vec![64], range_config.clone(), 8, 2, true);
+            let ripemd160 = Ripemd160Config::configure(meta, range_config, 8, 2);
+            Self::Config { sha256, ripemd160 }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.sha256.clone();
+            let mut ripemd160 = config.ripemd160.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            ripemd160.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "hash160 test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let (_, output_bytes) =
+                        ripemd160.hash160(ctx, &mut sha256, &self.test_input)?;
+                    for (assigned, expected) in output_bytes.iter().zip(self.expected_output.iter())
+                    {
+                        range
+                            .gate()
+                            .assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_hash160_of_empty_input() {
+        let circuit = TestCircuit::<Fr> {
+            test_input: vec![],
+            expected_output: hex::decode("b472a266d0bd89c13706a4132ccfb16f7c3b9fcb").unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
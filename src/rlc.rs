@@ -0,0 +1,250 @@
+use alloc::{format, vec};
+use core::marker::PhantomData;
+
+use halo2_base::halo2_proofs::{
+    circuit::{AssignedCell, Value},
+    plonk::{
+        Advice, Challenge, Column, ConstraintSystem, Error, FirstPhase, SecondPhase, Selector,
+    },
+    poly::Rotation,
+};
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+
+/// Computes a random linear combination (RLC) of assigned bytes with a second-phase challenge.
+/// The accumulator is `rlc_0 = b_0` and `rlc_i = rlc_{i-1} * challenge + b_i`.
+/// `assign_rlc_masked` only accumulates the bytes whose mask is 1, e.g. a substring whose bounds are assigned values.
+#[derive(Debug, Clone)]
+pub struct RlcConfig<F: PrimeField> {
+    q_first: Selector,
+    q_rlc: Selector,
+    q_masked: Selector,
+    bytes: Column<Advice>,
+    masks: Column<Advice>,
+    rlcs: Column<Advice>,
+    pub challenge: Challenge,
+    row_offset: usize,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField> RlcConfig<F> {
+    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+        let q_first = meta.selector();
+        let q_rlc = meta.selector();
+        let q_masked = meta.selector();
+        let bytes = meta.advice_column();
+        meta.enable_equality(bytes);
+        let masks = meta.advice_column();
+        meta.enable_equality(masks);
+        let rlcs = meta.advice_column_in(SecondPhase);
+        meta.enable_equality(rlcs);
+        let challenge = meta.challenge_usable_after(FirstPhase);
+
+        meta.create_gate("rlc first byte", |meta| {
+            let q = meta.query_selector(q_first);
+            let byte = meta.query_advice(bytes, Rotation::cur());
+            let rlc = meta.query_advice(rlcs, Rotation::cur());
+            vec![q * (rlc - byte)]
+        });
+        meta.create_gate("rlc accumulation", |meta| {
+            let q = meta.query_selector(q_rlc);
+            let byte = meta.query_advice(bytes, Rotation::cur());
+            let prev = meta.query_advice(rlcs, Rotation::prev());
+            let rlc = meta.query_advice(rlcs, Rotation::cur());
+            let challenge = meta.query_challenge(challenge);
+            vec![q * (rlc - (prev * challenge + byte))]
+        });
+        meta.create_gate("masked rlc accumulation", |meta| {
+            let q = meta.query_selector(q_masked);
+            let byte = meta.query_advice(bytes, Rotation::cur());
+            let mask = meta.query_advice(masks, Rotation::cur());
+            let prev = meta.query_advice(rlcs, Rotation::prev());
+            let rlc = meta.query_advice(rlcs, Rotation::cur());
+            let challenge = meta.query_challenge(challenge);
+            vec![q * (rlc - (prev.clone() + mask * (prev.clone() * challenge + byte - prev)))]
+        });
+        Self {
+            q_first,
+            q_rlc,
+            q_masked,
+            bytes,
+            masks,
+            rlcs,
+            challenge,
+            row_offset: 0,
+            _f: PhantomData,
+        }
+    }
+
+    /// Returns the RLC of `bytes` as a cell of the second-phase column.
+    /// `challenge` must be obtained by `layouter.get_challenge(self.challenge)` before the region is assigned.
+    pub fn assign_rlc<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        bytes: &[AssignedValue<'a, F>],
+        challenge: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        if bytes.is_empty() {
+            let zero = ctx.region.assign_advice(
+                || format!("rlc at offset {}", self.row_offset),
+                self.rlcs,
+                self.row_offset,
+                || Value::known(F::zero()),
+            )?;
+            ctx.region.constrain_constant(zero.cell(), F::zero())?;
+            self.row_offset += 1;
+            return Ok(zero);
+        }
+        let mut acc = Value::known(F::zero());
+        let mut last_rlc = None;
+        for (idx, byte) in bytes.iter().enumerate() {
+            if idx == 0 {
+                self.q_first.enable(&mut ctx.region, self.row_offset)?;
+                acc = byte.value;
+            } else {
+                self.q_rlc.enable(&mut ctx.region, self.row_offset)?;
+                acc = acc
+                    .zip(challenge)
+                    .zip(byte.value)
+                    .map(|((acc, challenge), byte)| acc * challenge + byte);
+            }
+            let assigned_byte = ctx.region.assign_advice(
+                || format!("rlc byte at offset {}", self.row_offset),
+                self.bytes,
+                self.row_offset,
+                || byte.value,
+            )?;
+            ctx.region
+                .constrain_equal(assigned_byte.cell(), byte.cell())?;
+            let assigned_rlc = ctx.region.assign_advice(
+                || format!("rlc at offset {}", self.row_offset),
+                self.rlcs,
+                self.row_offset,
+                || acc,
+            )?;
+            last_rlc = Some(assigned_rlc);
+            self.row_offset += 1;
+        }
+        Ok(last_rlc.unwrap())
+    }
+
+    /// Returns the RLC of the bytes of `bytes` whose mask is 1, in order, as a cell of the second-phase column.
+    /// The accumulator is kept on the rows whose mask is 0, so the masked bytes need not be contiguous.
+    /// The callers must constrain `masks` to bits.
+    pub fn assign_rlc_masked<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        bytes: &[AssignedValue<'a, F>],
+        masks: &[AssignedValue<'a, F>],
+        challenge: Value<F>,
+    ) -> Result<AssignedCell<F, F>, Error> {
+        assert_eq!(bytes.len(), masks.len());
+        // the accumulation starts from a row constrained to zero.
+        let mut last_rlc = ctx.region.assign_advice(
+            || format!("rlc at offset {}", self.row_offset),
+            self.rlcs,
+            self.row_offset,
+            || Value::known(F::zero()),
+        )?;
+        ctx.region.constrain_constant(last_rlc.cell(), F::zero())?;
+        self.row_offset += 1;
+        let mut acc = Value::known(F::zero());
+        for (byte, mask) in bytes.iter().zip(masks.iter()) {
+            self.q_masked.enable(&mut ctx.region, self.row_offset)?;
+            acc = acc.zip(challenge).zip(byte.value).zip(mask.value).map(
+                |(((acc, challenge), byte), mask)| acc + mask * (acc * challenge + byte - acc),
+            );
+            let assigned_byte = ctx.region.assign_advice(
+                || format!("rlc byte at offset {}", self.row_offset),
+                self.bytes,
+                self.row_offset,
+                || byte.value,
+            )?;
+            ctx.region
+                .constrain_equal(assigned_byte.cell(), byte.cell())?;
+            let assigned_mask = ctx.region.assign_advice(
+                || format!("rlc mask at offset {}", self.row_offset),
+                self.masks,
+                self.row_offset,
+                || mask.value,
+            )?;
+            ctx.region
+                .constrain_equal(assigned_mask.cell(), mask.cell())?;
+            last_rlc = ctx.region.assign_advice(
+                || format!("rlc at offset {}", self.row_offset),
+                self.rlcs,
+                self.row_offset,
+                || acc,
+            )?;
+            self.row_offset += 1;
+        }
+        Ok(last_rlc)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::cell::RefCell;
+
+    use super::*;
+    use crate::test_utils::{prove, RlcTestChip, TestShape};
+    use halo2_base::gates::{GateInstructions, RangeInstructions};
+    use halo2_base::halo2_proofs::{dev::VerifyFailure, halo2curves::bn256::Fr};
+    use itertools::Itertools;
+
+    struct RlcShape;
+
+    impl TestShape<Fr> for RlcShape {
+        type Extra = RlcTestChip<Fr>;
+        const NUM_ADVICE: usize = 3;
+        const MAX_VARIABLE_BYTE_SIZES: &'static [usize] = &[128];
+    }
+
+    /// Hashes `test_input` and constrains the RLC of its bytes to that of `claimed_input`, computed from separately
+    /// loaded cells. The RLC of the hashed bytes and the challenge are written to `rlc_and_challenge`.
+    fn run(
+        test_input: &[u8],
+        claimed_input: &[u8],
+        rlc_and_challenge: &RefCell<Option<(Fr, Fr)>>,
+    ) -> Result<(), Vec<VerifyFailure>> {
+        prove::<RlcShape, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let RlcTestChip { rlc, challenge } = &mut chips.extra;
+            let result = chips.sha256.digest(ctx, test_input, None)?;
+            let assigned_rlc =
+                rlc.assign_rlc(ctx, &result.input_bytes[..test_input.len()], *challenge)?;
+            let claimed_bytes = claimed_input
+                .iter()
+                .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                .collect_vec();
+            let claimed_rlc = rlc.assign_rlc(ctx, &claimed_bytes, *challenge)?;
+            ctx.region
+                .constrain_equal(assigned_rlc.cell(), claimed_rlc.cell())?;
+            assigned_rlc
+                .value()
+                .zip(*challenge)
+                .map(|(rlc, challenge)| *rlc_and_challenge.borrow_mut() = Some((*rlc, challenge)));
+            Ok(vec![])
+        })
+    }
+
+    #[test]
+    fn test_rlc_of_input_bytes() {
+        let input = b"hello world".to_vec();
+        let rlc_and_challenge = RefCell::new(None);
+        assert_eq!(run(&input, &input, &rlc_and_challenge), Ok(()));
+        let (rlc, challenge) = rlc_and_challenge.borrow().unwrap();
+        // `sum_i b_i * challenge^(n - 1 - i)`, i.e. the first byte has the highest power.
+        let expected = input.iter().fold(Fr::zero(), |acc, byte| {
+            acc * challenge + Fr::from(*byte as u64)
+        });
+        assert_eq!(rlc, expected);
+    }
+
+    #[test]
+    fn test_rlc_rejects_tampered_byte() {
+        let input = b"hello world".to_vec();
+        let mut claimed_input = input.clone();
+        claimed_input[4] ^= 1;
+        assert!(run(&input, &claimed_input, &RefCell::new(None)).is_err());
+    }
+}
@@ -0,0 +1,397 @@
+//! SHA-1, only for verifying legacy signatures such as `rsa-sha1` DKIM signatures.
+//!
+//! **WARNING: SHA-1 is broken.** Chosen-prefix collisions are practical, so a SHA-1 digest does not bind its input:
+//! anyone able to choose part of a signed message may have a second message with the same digest.
+//! Do not use this chip for commitments, Merkle trees or any new protocol, and prefer rejecting SHA-1 signatures
+//! whenever the signer supports SHA-256. It is only compiled with the `legacy-sha1` feature.
+use crate::bitwise::{even_and_odd, rotl};
+use crate::compression::{mod_u32, state_to_spread_u32, three_add};
+use crate::endian::word_from_be_bytes;
+use crate::ripemd160::boolean_func;
+use crate::spread::SpreadConfig;
+use crate::SpreadU32;
+use alloc::{vec, vec::Vec};
+use halo2_base::halo2_proofs::{
+    circuit::Layouter,
+    plonk::{ConstraintSystem, Error},
+};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+const NUM_SHA1_ROUND: usize = 80;
+const NUM_SHA1_STATE_WORD: usize = 5;
+
+pub const SHA1_INIT_STATE: [u32; NUM_SHA1_STATE_WORD] =
+    [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+const SHA1_CONSTANTS: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
+
+/// SHA-1 chip for inputs of a static length. See the module documentation before using it.
+/// The words have the same structure as in SHA-256, so the boolean functions are evaluated on the spread
+/// representation with the helpers of the RIPEMD-160 chip.
+#[derive(Debug, Clone)]
+pub struct Sha1Config<F: PrimeField> {
+    range: RangeConfig<F>,
+    spread_config: SpreadConfig<F>,
+}
+
+impl<F: PrimeField> Sha1Config<F> {
+    const ONE_ROUND_INPUT_BYTES: usize = 64;
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        range: RangeConfig<F>,
+        num_bits_lookup: usize,
+        num_advice_columns: usize,
+    ) -> Self {
+        let spread_config = SpreadConfig::configure(meta, num_bits_lookup, num_advice_columns);
+        Self {
+            range,
+            spread_config,
+        }
+    }
+
+    /// Returns the 20 bytes of the SHA-1 digest of `input`.
+    /// `input` must be range-checked bytes; the padding depends only on `input.len()` and is assigned as constants.
+    pub fn digest<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
+        let input_byte_size = input.len();
+        let num_round = (input_byte_size + 9 + one_round_size - 1) / one_round_size;
+        let zero_padding_byte_size = num_round * one_round_size - input_byte_size - 9;
+        let range = &self.range;
+        let gate = range.gate();
+
+        let mut padding = vec![0x80u8];
+        padding.extend(vec![0; zero_padding_byte_size]);
+        padding.extend_from_slice(&(8 * input_byte_size as u64).to_be_bytes());
+        let mut padded_input = input.to_vec();
+        for byte in padding.into_iter() {
+            padded_input.push(gate.load_constant(ctx, F::from(byte as u64)));
+        }
+        assert_eq!(padded_input.len(), num_round * one_round_size);
+
+        let mut state = SHA1_INIT_STATE
+            .iter()
+            .map(|word| gate.load_constant(ctx, F::from(*word as u64)))
+            .collect_vec();
+        for block in padded_input.chunks(one_round_size) {
+            state = sha1_compression(ctx, range, &mut self.spread_config, block, &state)?;
+        }
+
+        let mut output_bytes = vec![];
+        for word in state.iter() {
+            let word_val = word.value().map(|v| v.get_lower_32());
+            let bytes = (0..4)
+                .map(|idx| {
+                    let byte = gate.load_witness(
+                        ctx,
+                        word_val.map(|v| F::from(((v >> (8 * (3 - idx))) & 0xff) as u64)),
+                    );
+                    range.range_check(ctx, &byte, 8);
+                    byte
+                })
+                .collect_vec();
+            let recomposed = gate.inner_product(
+                ctx,
+                bytes.iter().map(QuantumCell::Existing),
+                (0..4).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (3 - idx))))),
+            );
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(word),
+                QuantumCell::Existing(&recomposed),
+            );
+            output_bytes.extend(bytes);
+        }
+        Ok(output_bytes)
+    }
+
+    pub fn range(&self) -> &RangeConfig<F> {
+        &self.range
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.spread_config.load(layouter)
+    }
+}
+
+pub fn sha1_compression<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    debug_assert_eq!(assigned_input_bytes.len(), 64);
+    debug_assert_eq!(pre_state_words.len(), NUM_SHA1_STATE_WORD);
+    let gate = range.gate();
+    // message words are big-endian.
+    let mut message_u32s = assigned_input_bytes
+        .chunks(4)
+        .map(|bytes| word_from_be_bytes(ctx, gate, bytes))
+        .collect_vec();
+    let mut message_spreads = Vec::with_capacity(NUM_SHA1_ROUND);
+    for (idx, word) in message_u32s.iter().enumerate() {
+        spread_config.set_annotation("SHA-1 schedule word", idx);
+        message_spreads.push(state_to_spread_u32(ctx, range, spread_config, word)?);
+    }
+    for idx in 16..NUM_SHA1_ROUND {
+        spread_config.set_annotation("SHA-1 schedule word", idx);
+        let xor = xor4(
+            ctx,
+            range,
+            spread_config,
+            [
+                &message_spreads[idx - 3],
+                &message_spreads[idx - 8],
+                &message_spreads[idx - 14],
+                &message_spreads[idx - 16],
+            ],
+        )?;
+        let word = rotl(ctx, range, &xor, 1);
+        message_spreads.push(state_to_spread_u32(ctx, range, spread_config, &word)?);
+        message_u32s.push(word);
+    }
+
+    let mut state = pre_state_words.to_vec();
+    for (idx, message_word) in message_u32s.iter().enumerate() {
+        spread_config.set_annotation("SHA-1 round", idx);
+        state = sha1_round(ctx, range, spread_config, &state, idx, message_word)?;
+    }
+
+    let next_state_words = pre_state_words
+        .iter()
+        .zip(state.iter())
+        .map(|(pre, word)| {
+            let add = gate.add(ctx, QuantumCell::Existing(pre), QuantumCell::Existing(word));
+            mod_u32(ctx, range, &add)
+        })
+        .collect_vec();
+    Ok(next_state_words)
+}
+
+fn sha1_round<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    state: &[AssignedValue<'a, F>],
+    round_idx: usize,
+    message_word: &AssignedValue<'a, F>,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let gate = range.gate();
+    let (a, b, c, d, e) = (&state[0], &state[1], &state[2], &state[3], &state[4]);
+    let b_spread = state_to_spread_u32(ctx, range, spread_config, b)?;
+    let c_spread = state_to_spread_u32(ctx, range, spread_config, c)?;
+    let d_spread = state_to_spread_u32(ctx, range, spread_config, d)?;
+    let f_term = match round_idx / 20 {
+        // ch, which is `f1` of RIPEMD-160.
+        0 => boolean_func(
+            ctx,
+            range,
+            spread_config,
+            1,
+            &b_spread,
+            &c_spread,
+            &d_spread,
+        )?,
+        2 => maj(ctx, range, spread_config, &b_spread, &c_spread, &d_spread)?,
+        // parity, which is `f0` of RIPEMD-160.
+        _ => boolean_func(
+            ctx,
+            range,
+            spread_config,
+            0,
+            &b_spread,
+            &c_spread,
+            &d_spread,
+        )?,
+    };
+    let a_rotated = rotl(ctx, range, a, 5);
+    let add1 = gate.add(
+        ctx,
+        QuantumCell::Existing(&a_rotated),
+        QuantumCell::Existing(&f_term),
+    );
+    let add2 = gate.add(ctx, QuantumCell::Existing(&add1), QuantumCell::Existing(e));
+    let add3 = gate.add(
+        ctx,
+        QuantumCell::Existing(&add2),
+        QuantumCell::Existing(message_word),
+    );
+    let add4 = gate.add(
+        ctx,
+        QuantumCell::Existing(&add3),
+        QuantumCell::Constant(F::from(SHA1_CONSTANTS[round_idx / 20] as u64)),
+    );
+    let temp = mod_u32(ctx, range, &add4);
+    let b_rotated = rotl(ctx, range, b, 30);
+    Ok(vec![temp, a.clone(), b_rotated, c.clone(), d.clone()])
+}
+
+/// Returns `x & y | x & z | y & z`: the odd bits of the sum of the spread limbs.
+fn maj<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x: &SpreadU32<'a, F>,
+    y: &SpreadU32<'a, F>,
+    z: &SpreadU32<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let gate = range.gate();
+    let mut out_limbs = vec![];
+    for (x, y, z) in [(&x.0, &y.0, &z.0), (&x.1, &y.1, &z.1)] {
+        let sum = three_add(
+            ctx,
+            gate,
+            QuantumCell::Existing(x),
+            QuantumCell::Existing(y),
+            QuantumCell::Existing(z),
+        );
+        let ((_, odd), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+        out_limbs.push(odd);
+    }
+    Ok(gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&out_limbs[1]),
+        QuantumCell::Constant(F::from(1u64 << 16)),
+        QuantumCell::Existing(&out_limbs[0]),
+    ))
+}
+
+/// Returns the XOR of four words as a dense value.
+/// Summing four spread limbs would carry into the next bit, so the XOR of the first three is spread again first.
+fn xor4<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    words: [&SpreadU32<'a, F>; 4],
+) -> Result<AssignedValue<'a, F>, Error> {
+    let gate = range.gate();
+    let limbs = |word: &SpreadU32<'a, F>| [word.0.clone(), word.1.clone()];
+    let [w0, w1, w2, w3] = words.map(limbs);
+    let mut out_limbs = vec![];
+    for limb_idx in 0..2 {
+        let sum = three_add(
+            ctx,
+            gate,
+            QuantumCell::Existing(&w0[limb_idx]),
+            QuantumCell::Existing(&w1[limb_idx]),
+            QuantumCell::Existing(&w2[limb_idx]),
+        );
+        let (_, (xor3_spread, _)) = even_and_odd(ctx, range, spread_config, &sum)?;
+        let sum = gate.add(
+            ctx,
+            QuantumCell::Existing(&xor3_spread),
+            QuantumCell::Existing(&w3[limb_idx]),
+        );
+        let ((xor4, _), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+        out_limbs.push(xor4);
+    }
+    Ok(gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&out_limbs[1]),
+        QuantumCell::Constant(F::from(1u64 << 16)),
+        QuantumCell::Existing(&out_limbs[0]),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::Circuit,
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, ContextParams, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        test_input: Vec<u8>,
+        expected_output: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha1Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[4], &[1], 1, 16, 0, 17);
+            Sha1Config::configure(meta, range_config, 8, 2)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha1 = config.clone();
+            let range = sha1.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha1.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "sha1 test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut Context::new(
+                        region,
+                        ContextParams {
+                            max_rows: range.gate.max_rows,
+                            num_context_ids: 1,
+                            fixed_columns: range.gate.constants.clone(),
+                        },
+                    );
+                    let gate = range.gate();
+                    let input = self
+                        .test_input
+                        .iter()
+                        .map(|byte| {
+                            let byte = gate.load_witness(ctx, Value::known(F::from(*byte as u64)));
+                            range.range_check(ctx, &byte, 8);
+                            byte
+                        })
+                        .collect_vec();
+                    let output_bytes = sha1.digest(ctx, &input)?;
+                    for (assigned, expected) in output_bytes.iter().zip(self.expected_output.iter())
+                    {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sha1_abc() {
+        let circuit = TestCircuit::<Fr> {
+            test_input: b"abc".to_vec(),
+            expected_output: hex::decode("a9993e364706816aba3e25717850c26c9cd0d89d").unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
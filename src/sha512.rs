@@ -0,0 +1,725 @@
+//! SHA-512 and SHA-512/256, its truncation to a 32-byte digest with a distinct IV.
+//! Words are 64 bits, so each one is spread as four 16-bit limbs that are looked up in the same spread table as
+//! SHA-256. Rotations and shifts are computed on the dense word by decomposing it at the rotation offset, and the
+//! three terms of each `σ`/`Σ` are then XORed limb by limb.
+use crate::bitwise::even_and_odd;
+use crate::compression::three_add;
+use crate::spread::SpreadConfig;
+use alloc::{vec, vec::Vec};
+use core::convert::TryInto;
+use halo2_base::halo2_proofs::{
+    circuit::{Layouter, Value},
+    plonk::{ConstraintSystem, Error},
+};
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+
+const NUM_SHA512_ROUND: usize = 80;
+const NUM_SHA512_STATE_WORD: usize = 8;
+const NUM_LIMB: usize = 4;
+const MASK_EVEN_16: u64 = 0x55555555;
+/// Number of bits of the carry of `mod_u64`, as in `mod_u32`.
+const MAX_CARRY_BITS: usize = 8;
+
+pub const SHA512_INIT_STATE: [u64; NUM_SHA512_STATE_WORD] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+pub const SHA512_256_INIT_STATE: [u64; NUM_SHA512_STATE_WORD] = [
+    0x22312194fc2bf72c,
+    0x9f555fa3c84c64c2,
+    0x2393b86b6f53b151,
+    0x963877195940eabd,
+    0x96283ee2a88effe3,
+    0xbe5e1e2553863992,
+    0x2b0199fc2c85b8aa,
+    0x0eb72ddc81c52ca2,
+];
+const ROUND_CONSTANTS_512: [u64; NUM_SHA512_ROUND] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+/// Spreads of the four 16-bit limbs of a 64-bit word, from the least significant one.
+pub type SpreadU64<'a, F> = [AssignedValue<'a, F>; NUM_LIMB];
+
+/// SHA-512 chip for inputs of a static length.
+#[derive(Debug, Clone)]
+pub struct Sha512Config<F: PrimeField> {
+    range: RangeConfig<F>,
+    spread_config: SpreadConfig<F>,
+}
+
+impl<F: PrimeField> Sha512Config<F> {
+    const ONE_ROUND_INPUT_BYTES: usize = 128;
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        range: RangeConfig<F>,
+        num_bits_lookup: usize,
+        num_advice_columns: usize,
+    ) -> Self {
+        let spread_config = SpreadConfig::configure(meta, num_bits_lookup, num_advice_columns);
+        Self {
+            range,
+            spread_config,
+        }
+    }
+
+    /// Returns the 64 bytes of the SHA-512 digest of `input`.
+    /// `input` must be range-checked bytes; the padding depends only on `input.len()` and is assigned as constants.
+    pub fn digest<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        self.digest_with_init_state(ctx, input, &SHA512_INIT_STATE, 64)
+    }
+
+    /// Returns the 32 bytes of the SHA-512/256 digest of `input`.
+    /// Only the first four state words are decomposed into output bytes.
+    pub fn digest_512_256<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        self.digest_with_init_state(ctx, input, &SHA512_256_INIT_STATE, 32)
+    }
+
+    fn digest_with_init_state<'a, 'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        input: &[AssignedValue<'a, F>],
+        init_state: &[u64; NUM_SHA512_STATE_WORD],
+        output_byte_size: usize,
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        debug_assert_eq!(output_byte_size % 8, 0);
+        let one_round_size = Self::ONE_ROUND_INPUT_BYTES;
+        let input_byte_size = input.len();
+        let num_round = (input_byte_size + 17 + one_round_size - 1) / one_round_size;
+        let zero_padding_byte_size = num_round * one_round_size - input_byte_size - 17;
+        let range = &self.range;
+        let gate = range.gate();
+
+        let mut padding = vec![0x80u8];
+        padding.extend(vec![0; zero_padding_byte_size]);
+        padding.extend_from_slice(&(8 * input_byte_size as u128).to_be_bytes());
+        let mut padded_input = input.to_vec();
+        for byte in padding.into_iter() {
+            padded_input.push(gate.load_constant(ctx, F::from(byte as u64)));
+        }
+        assert_eq!(padded_input.len(), num_round * one_round_size);
+
+        let mut state = init_state
+            .iter()
+            .map(|word| gate.load_constant(ctx, F::from(*word)))
+            .collect_vec();
+        for block in padded_input.chunks(one_round_size) {
+            state = sha512_compression(ctx, range, &mut self.spread_config, block, &state)?;
+        }
+
+        let mut output_bytes = vec![];
+        for word in state.iter().take(output_byte_size / 8) {
+            let word_val = word.value().map(|v| v.get_lower_128() as u64);
+            let bytes = (0..8)
+                .map(|idx| {
+                    let byte = gate.load_witness(
+                        ctx,
+                        word_val.map(|v| F::from((v >> (8 * (7 - idx))) & 0xff)),
+                    );
+                    range.range_check(ctx, &byte, 8);
+                    byte
+                })
+                .collect_vec();
+            let recomposed = gate.inner_product(
+                ctx,
+                bytes.iter().map(QuantumCell::Existing),
+                (0..8).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (7 - idx))))),
+            );
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(word),
+                QuantumCell::Existing(&recomposed),
+            );
+            output_bytes.extend(bytes);
+        }
+        Ok(output_bytes)
+    }
+
+    pub fn range(&self) -> &RangeConfig<F> {
+        &self.range
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.spread_config.load(layouter)
+    }
+}
+
+pub fn sha512_compression<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    assigned_input_bytes: &[AssignedValue<'a, F>],
+    pre_state_words: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    debug_assert_eq!(assigned_input_bytes.len(), 128);
+    debug_assert_eq!(pre_state_words.len(), NUM_SHA512_STATE_WORD);
+    let gate = range.gate();
+    // message words are big-endian.
+    let mut message_u64s = assigned_input_bytes
+        .chunks(8)
+        .map(|bytes| {
+            gate.inner_product(
+                ctx,
+                bytes.iter().map(QuantumCell::Existing),
+                (0..8).map(|idx| QuantumCell::Constant(F::from(1u64 << (8 * (7 - idx))))),
+            )
+        })
+        .collect_vec();
+    for idx in 16..NUM_SHA512_ROUND {
+        spread_config.set_annotation("SHA-512 schedule word", idx);
+        let w15 = &message_u64s[idx - 15];
+        let w2 = &message_u64s[idx - 2];
+        let sigma0 = {
+            let terms = [
+                rotr_u64(ctx, range, w15, 1),
+                rotr_u64(ctx, range, w15, 8),
+                shr_u64(ctx, range, w15, 7),
+            ];
+            xor3(ctx, range, spread_config, &terms)?
+        };
+        let sigma1 = {
+            let terms = [
+                rotr_u64(ctx, range, w2, 19),
+                rotr_u64(ctx, range, w2, 61),
+                shr_u64(ctx, range, w2, 6),
+            ];
+            xor3(ctx, range, spread_config, &terms)?
+        };
+        let sum = gate.sum(
+            ctx,
+            [
+                &message_u64s[idx - 16],
+                &sigma0,
+                &message_u64s[idx - 7],
+                &sigma1,
+            ]
+            .iter()
+            .map(|word| QuantumCell::Existing(*word)),
+        );
+        message_u64s.push(mod_u64(ctx, range, &sum));
+    }
+
+    // the spreads of `a, b, c` and `e, f, g` are kept for `maj` and `ch` as the words move down the state.
+    let mut state = pre_state_words.to_vec();
+    let mut spreads = Vec::with_capacity(NUM_SHA512_STATE_WORD);
+    for (idx, word) in state.iter().enumerate() {
+        spread_config.set_annotation("SHA-512 input state word", idx);
+        spreads.push(word_to_spread_u64(ctx, range, spread_config, word)?);
+    }
+    for (idx, message_word) in message_u64s.iter().enumerate() {
+        spread_config.set_annotation("SHA-512 round", idx);
+        let (a, d, e, h) = (&state[0], &state[3], &state[4], &state[7]);
+        let sigma_upper1 = {
+            let terms = [
+                rotr_u64(ctx, range, e, 14),
+                rotr_u64(ctx, range, e, 18),
+                rotr_u64(ctx, range, e, 41),
+            ];
+            xor3(ctx, range, spread_config, &terms)?
+        };
+        let ch = ch(
+            ctx,
+            range,
+            spread_config,
+            &spreads[4],
+            &spreads[5],
+            &spreads[6],
+        )?;
+        let t1 = gate.sum(
+            ctx,
+            [
+                QuantumCell::Existing(h),
+                QuantumCell::Existing(&sigma_upper1),
+                QuantumCell::Existing(&ch),
+                QuantumCell::Constant(F::from(ROUND_CONSTANTS_512[idx])),
+                QuantumCell::Existing(message_word),
+            ],
+        );
+        let sigma_upper0 = {
+            let terms = [
+                rotr_u64(ctx, range, a, 28),
+                rotr_u64(ctx, range, a, 34),
+                rotr_u64(ctx, range, a, 39),
+            ];
+            xor3(ctx, range, spread_config, &terms)?
+        };
+        let maj = maj(
+            ctx,
+            range,
+            spread_config,
+            &spreads[0],
+            &spreads[1],
+            &spreads[2],
+        )?;
+        let new_e = {
+            let sum = gate.add(ctx, QuantumCell::Existing(d), QuantumCell::Existing(&t1));
+            mod_u64(ctx, range, &sum)
+        };
+        let new_a = {
+            let sum = gate.sum(
+                ctx,
+                [
+                    QuantumCell::Existing(&t1),
+                    QuantumCell::Existing(&sigma_upper0),
+                    QuantumCell::Existing(&maj),
+                ],
+            );
+            mod_u64(ctx, range, &sum)
+        };
+        let new_e_spread = word_to_spread_u64(ctx, range, spread_config, &new_e)?;
+        let new_a_spread = word_to_spread_u64(ctx, range, spread_config, &new_a)?;
+        state = vec![
+            new_a,
+            state[0].clone(),
+            state[1].clone(),
+            state[2].clone(),
+            new_e,
+            state[4].clone(),
+            state[5].clone(),
+            state[6].clone(),
+        ];
+        spreads = vec![
+            new_a_spread,
+            spreads[0].clone(),
+            spreads[1].clone(),
+            spreads[2].clone(),
+            new_e_spread,
+            spreads[4].clone(),
+            spreads[5].clone(),
+            spreads[6].clone(),
+        ];
+    }
+
+    let next_state_words = pre_state_words
+        .iter()
+        .zip(state.iter())
+        .map(|(pre, word)| {
+            let add = gate.add(ctx, QuantumCell::Existing(pre), QuantumCell::Existing(word));
+            mod_u64(ctx, range, &add)
+        })
+        .collect_vec();
+    Ok(next_state_words)
+}
+
+/// Returns the spreads of the four 16-bit limbs of `x`. The spread lookups bound the limbs to 16 bits, so this also
+/// constrains `x` to be less than `2^64`.
+fn word_to_spread_u64<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    x: &AssignedValue<'a, F>,
+) -> Result<SpreadU64<'a, F>, Error> {
+    let gate = range.gate();
+    let x_val = x.value().map(|v| v.get_lower_128() as u64);
+    let limbs = (0..NUM_LIMB)
+        .map(|idx| gate.load_witness(ctx, x_val.map(|v| F::from((v >> (16 * idx)) & 0xffff))))
+        .collect_vec();
+    let composed = gate.inner_product(
+        ctx,
+        limbs.iter().map(QuantumCell::Existing),
+        (0..NUM_LIMB).map(|idx| QuantumCell::Constant(F::from(1u64 << (16 * idx)))),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(x),
+        QuantumCell::Existing(&composed),
+    );
+    let spreads = limbs
+        .iter()
+        .map(|limb| spread_config.spread(ctx, range, limb))
+        .collect::<Result<Vec<_>, Error>>()?;
+    Ok(spreads.try_into().unwrap())
+}
+
+/// Composes four 16-bit dense limbs, from the least significant one, into a 64-bit word.
+fn compose_u64<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    limbs: &[AssignedValue<'a, F>],
+) -> AssignedValue<'a, F> {
+    debug_assert_eq!(limbs.len(), NUM_LIMB);
+    range.gate().inner_product(
+        ctx,
+        limbs.iter().map(QuantumCell::Existing),
+        (0..NUM_LIMB).map(|idx| QuantumCell::Constant(F::from(1u64 << (16 * idx)))),
+    )
+}
+
+/// Reduces `x` modulo `2^64`. Both the result and the carry are range-checked, so the decomposition is unique.
+fn mod_u64<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+) -> AssignedValue<'a, F> {
+    let gate = range.gate();
+    let lo = x.value().map(|v| F::from(v.get_lower_128() as u64));
+    let hi = x.value().map(|v| F::from((v.get_lower_128() >> 64) as u64));
+    let assigned_lo = gate.load_witness(ctx, lo);
+    let assigned_hi = gate.load_witness(ctx, hi);
+    range.range_check(ctx, &assigned_lo, 64);
+    range.range_check(ctx, &assigned_hi, MAX_CARRY_BITS);
+    let composed = gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&assigned_hi),
+        QuantumCell::Constant(F::from_u128(1u128 << 64)),
+        QuantumCell::Existing(&assigned_lo),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(x),
+        QuantumCell::Existing(&composed),
+    );
+    assigned_lo
+}
+
+/// Splits a 64-bit word `x` into `(lo, hi)` where `lo` has `lo_bits` bits.
+fn split_u64<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    lo_bits: usize,
+) -> (AssignedValue<'a, F>, AssignedValue<'a, F>) {
+    debug_assert!(lo_bits > 0 && lo_bits < 64);
+    let gate = range.gate();
+    let x_val: Value<u64> = x.value().map(|v| v.get_lower_128() as u64);
+    let assigned_lo = gate.load_witness(ctx, x_val.map(|v| F::from(v & ((1u64 << lo_bits) - 1))));
+    let assigned_hi = gate.load_witness(ctx, x_val.map(|v| F::from(v >> lo_bits)));
+    range.range_check(ctx, &assigned_lo, lo_bits);
+    range.range_check(ctx, &assigned_hi, 64 - lo_bits);
+    let composed = gate.mul_add(
+        ctx,
+        QuantumCell::Existing(&assigned_hi),
+        QuantumCell::Constant(F::from(1u64 << lo_bits)),
+        QuantumCell::Existing(&assigned_lo),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(x),
+        QuantumCell::Existing(&composed),
+    );
+    (assigned_lo, assigned_hi)
+}
+
+/// Rotates a 64-bit word `x` to the right by `rotation` bits.
+fn rotr_u64<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    rotation: usize,
+) -> AssignedValue<'a, F> {
+    let (lo, hi) = split_u64(ctx, range, x, rotation);
+    range.gate().mul_add(
+        ctx,
+        QuantumCell::Existing(&lo),
+        QuantumCell::Constant(F::from(1u64 << (64 - rotation))),
+        QuantumCell::Existing(&hi),
+    )
+}
+
+/// Shifts a 64-bit word `x` to the right by `shift` bits.
+fn shr_u64<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    x: &AssignedValue<'a, F>,
+    shift: usize,
+) -> AssignedValue<'a, F> {
+    split_u64(ctx, range, x, shift).1
+}
+
+/// Returns the XOR of three 64-bit words: the even bits of the sums of their spread limbs.
+fn xor3<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    words: &[AssignedValue<'a, F>; 3],
+) -> Result<AssignedValue<'a, F>, Error> {
+    let [x, y, z] = words;
+    let x = word_to_spread_u64(ctx, range, spread_config, x)?;
+    let y = word_to_spread_u64(ctx, range, spread_config, y)?;
+    let z = word_to_spread_u64(ctx, range, spread_config, z)?;
+    let mut out_limbs = vec![];
+    for idx in 0..NUM_LIMB {
+        let sum = three_add(
+            ctx,
+            range.gate(),
+            QuantumCell::Existing(&x[idx]),
+            QuantumCell::Existing(&y[idx]),
+            QuantumCell::Existing(&z[idx]),
+        );
+        let ((even, _), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+        out_limbs.push(even);
+    }
+    Ok(compose_u64(ctx, range, &out_limbs))
+}
+
+/// Returns `(e & f) ^ (!e & g)`. Both terms are the odd bits of a sum of two spreads and never overlap.
+fn ch<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    e: &SpreadU64<'a, F>,
+    f: &SpreadU64<'a, F>,
+    g: &SpreadU64<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let gate = range.gate();
+    let mut out_limbs = vec![];
+    for idx in 0..NUM_LIMB {
+        let p = gate.add(
+            ctx,
+            QuantumCell::Existing(&e[idx]),
+            QuantumCell::Existing(&f[idx]),
+        );
+        let e_neg = gate.neg(ctx, QuantumCell::Existing(&e[idx]));
+        let q = three_add(
+            ctx,
+            gate,
+            QuantumCell::Constant(F::from(MASK_EVEN_16)),
+            QuantumCell::Existing(&e_neg),
+            QuantumCell::Existing(&g[idx]),
+        );
+        let ((_, p_odd), _) = even_and_odd(ctx, range, spread_config, &p)?;
+        let ((_, q_odd), _) = even_and_odd(ctx, range, spread_config, &q)?;
+        out_limbs.push(gate.add(
+            ctx,
+            QuantumCell::Existing(&p_odd),
+            QuantumCell::Existing(&q_odd),
+        ));
+    }
+    Ok(compose_u64(ctx, range, &out_limbs))
+}
+
+/// Returns `(a & b) ^ (a & c) ^ (b & c)`: the odd bits of the sums of the spread limbs.
+fn maj<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    spread_config: &mut SpreadConfig<F>,
+    a: &SpreadU64<'a, F>,
+    b: &SpreadU64<'a, F>,
+    c: &SpreadU64<'a, F>,
+) -> Result<AssignedValue<'a, F>, Error> {
+    let mut out_limbs = vec![];
+    for idx in 0..NUM_LIMB {
+        let sum = three_add(
+            ctx,
+            range.gate(),
+            QuantumCell::Existing(&a[idx]),
+            QuantumCell::Existing(&b[idx]),
+            QuantumCell::Existing(&c[idx]),
+        );
+        let ((_, odd), _) = even_and_odd(ctx, range, spread_config, &sum)?;
+        out_limbs.push(odd);
+    }
+    Ok(compose_u64(ctx, range, &out_limbs))
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::SimpleFloorPlanner, dev::MockProver, halo2curves::bn256::Fr, plonk::Circuit,
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, ContextParams, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        test_input: Vec<u8>,
+        truncated: bool,
+        expected_output: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha512Config<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[8], &[1], 1, 16, 0, 17);
+            Sha512Config::configure(meta, range_config, 8, 2)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha512 = config.clone();
+            let range = sha512.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha512.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "sha512 test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut Context::new(
+                        region,
+                        ContextParams {
+                            max_rows: range.gate.max_rows,
+                            num_context_ids: 1,
+                            fixed_columns: range.gate.constants.clone(),
+                        },
+                    );
+                    let gate = range.gate();
+                    let input = self
+                        .test_input
+                        .iter()
+                        .map(|byte| {
+                            let byte = gate.load_witness(ctx, Value::known(F::from(*byte as u64)));
+                            range.range_check(ctx, &byte, 8);
+                            byte
+                        })
+                        .collect_vec();
+                    let output_bytes = if self.truncated {
+                        sha512.digest_512_256(ctx, &input)?
+                    } else {
+                        sha512.digest(ctx, &input)?
+                    };
+                    assert_eq!(output_bytes.len(), self.expected_output.len());
+                    for (assigned, expected) in output_bytes.iter().zip(self.expected_output.iter())
+                    {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_sha512_abc() {
+        let circuit = TestCircuit::<Fr> {
+            test_input: b"abc".to_vec(),
+            truncated: false,
+            expected_output: hex::decode("ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f").unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_sha512_256_abc() {
+        let circuit = TestCircuit::<Fr> {
+            test_input: b"abc".to_vec(),
+            truncated: true,
+            expected_output: hex::decode(
+                "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23",
+            )
+            .unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+}
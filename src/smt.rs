@@ -0,0 +1,205 @@
+use crate::endian::digest_to_be_bytes;
+use crate::mmr::{conditional_swap, hash_node};
+use crate::{Sha256DynamicConfig, INIT_STATE};
+use alloc::{vec, vec::Vec};
+use generic_array::GenericArray;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+use sha2::compress256;
+
+/// Returns the roots of empty subtrees of height `0..=depth`, where the empty leaf is 32 zero bytes.
+pub fn default_nodes(depth: usize) -> Vec<[u8; 32]> {
+    let mut nodes = vec![[0u8; 32]];
+    for _ in 0..depth {
+        let child = nodes.last().unwrap();
+        let block = child.iter().chain(child.iter()).cloned().collect_vec();
+        let mut state = INIT_STATE;
+        compress256(&mut state, &[GenericArray::clone_from_slice(&block)]);
+        nodes.push(digest_to_be_bytes(&state));
+    }
+    nodes
+}
+
+/// Computes the root of a sparse Merkle tree from `leaf` at the position given by `key_bits`.
+/// `key_bits` are little-endian from the leaf level and a set bit means that the node is the right child.
+/// A `None` sibling is the empty subtree of that height and is assigned as constants.
+pub fn compute_root<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    leaf: &[AssignedValue<'a, F>],
+    key_bits: &[AssignedValue<'a, F>],
+    siblings: &[Option<Vec<AssignedValue<'a, F>>>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    assert_eq!(leaf.len(), 32);
+    assert_eq!(key_bits.len(), siblings.len());
+    let gate = sha256.range().gate().clone();
+    let defaults = default_nodes(siblings.len());
+    let mut node = leaf.to_vec();
+    for (height, (key_bit, sibling)) in key_bits.iter().zip(siblings.iter()).enumerate() {
+        gate.assert_bit(ctx, key_bit);
+        let sibling = match sibling {
+            Some(sibling) => sibling.clone(),
+            None => defaults[height]
+                .iter()
+                .map(|byte| gate.load_constant(ctx, F::from(*byte as u64)))
+                .collect_vec(),
+        };
+        let (left, right) = conditional_swap(ctx, &gate, &node, &sibling, key_bit);
+        node = hash_node(ctx, sha256, &left, &right)?;
+    }
+    Ok(node)
+}
+
+/// Constrains that `value` is stored at `key_bits` in the tree with `root`.
+/// `value` must not be the empty leaf, i.e., 32 zero bytes.
+pub fn verify_membership<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    root: &[AssignedValue<'a, F>],
+    key_bits: &[AssignedValue<'a, F>],
+    value: &[AssignedValue<'a, F>],
+    siblings: &[Option<Vec<AssignedValue<'a, F>>>],
+) -> Result<(), Error> {
+    let computed = compute_root(ctx, sha256, value, key_bits, siblings)?;
+    for (computed, expected) in computed.iter().zip(root.iter()) {
+        ctx.region
+            .constrain_equal(computed.cell(), expected.cell())?;
+    }
+    Ok(())
+}
+
+/// Constrains that no value is stored at `key_bits` in the tree with `root`, i.e., the leaf there is empty.
+pub fn verify_non_membership<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    root: &[AssignedValue<'a, F>],
+    key_bits: &[AssignedValue<'a, F>],
+    siblings: &[Option<Vec<AssignedValue<'a, F>>>],
+) -> Result<(), Error> {
+    let gate = sha256.range().gate().clone();
+    let empty_leaf = (0..32).map(|_| gate.load_zero(ctx)).collect_vec();
+    verify_membership(ctx, sha256, root, key_bits, &empty_leaf, siblings)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_utils::{prove, PlainShape};
+    use halo2_base::halo2_proofs::{circuit::Value, dev::VerifyFailure, halo2curves::bn256::Fr};
+
+    const DEPTH: usize = 3;
+
+    struct TestCase {
+        root: [u8; 32],
+        key: usize,
+        // `None` checks that the leaf at `key` is empty.
+        value: Option<[u8; 32]>,
+        siblings: Vec<Option<[u8; 32]>>,
+    }
+
+    fn run(case: &TestCase) -> Result<(), Vec<VerifyFailure>> {
+        prove::<PlainShape<8>, _>(vec![], |ctx, chips| {
+            let gate = chips.range.gate();
+            let load_bytes = |ctx: &mut Context<_>, bytes: &[u8]| {
+                bytes
+                    .iter()
+                    .map(|byte| gate.load_witness(ctx, Value::known(Fr::from(*byte as u64))))
+                    .collect_vec()
+            };
+            let root = load_bytes(ctx, &case.root);
+            let key_bits = (0..case.siblings.len())
+                .map(|height| {
+                    let bit = (case.key >> height) & 1;
+                    gate.load_witness(ctx, Value::known(Fr::from(bit as u64)))
+                })
+                .collect_vec();
+            let siblings = case
+                .siblings
+                .iter()
+                .map(|sibling| sibling.map(|sibling| load_bytes(ctx, &sibling)))
+                .collect_vec();
+            match case.value {
+                Some(value) => {
+                    let value = load_bytes(ctx, &value);
+                    verify_membership(ctx, &mut chips.sha256, &root, &key_bits, &value, &siblings)?
+                }
+                None => verify_non_membership(ctx, &mut chips.sha256, &root, &key_bits, &siblings)?,
+            }
+            Ok(vec![])
+        })
+    }
+
+    /// Returns the levels of the full tree over `leaves`, from the leaves to the root.
+    fn native_tree(leaves: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+        let mut levels = vec![leaves.to_vec()];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| {
+                    let block = pair[0].iter().chain(pair[1].iter()).cloned().collect_vec();
+                    let mut state = INIT_STATE;
+                    compress256(&mut state, &[GenericArray::clone_from_slice(&block)]);
+                    digest_to_be_bytes(&state)
+                })
+                .collect_vec();
+            levels.push(level);
+        }
+        levels
+    }
+
+    /// Returns a case proving the leaf at `key` of the tree whose leaves 2 and 5 are set.
+    /// The siblings that are empty subtrees are given as `None`.
+    fn test_case(key: usize, value: Option<[u8; 32]>) -> TestCase {
+        let mut leaves = [[0u8; 32]; 1 << DEPTH];
+        leaves[2] = [9; 32];
+        leaves[5] = [7; 32];
+        let levels = native_tree(&leaves);
+        let defaults = default_nodes(DEPTH);
+        let siblings = (0..DEPTH)
+            .map(|height| {
+                let sibling = levels[height][(key >> height) ^ 1];
+                (sibling != defaults[height]).then_some(sibling)
+            })
+            .collect_vec();
+        TestCase {
+            root: levels[DEPTH][0],
+            key,
+            value,
+            siblings,
+        }
+    }
+
+    #[test]
+    fn test_smt_membership() {
+        let case = test_case(5, Some([7; 32]));
+        // the sibling subtree of leaves 0 to 3 holds leaf 2.
+        assert_eq!(case.siblings.iter().filter(|s| s.is_some()).count(), 1);
+        assert_eq!(run(&case), Ok(()));
+
+        assert!(run(&test_case(5, Some([8; 32]))).is_err());
+    }
+
+    #[test]
+    fn test_smt_non_membership() {
+        assert_eq!(run(&test_case(3, None)), Ok(()));
+
+        // leaf 5 is set, so it cannot be proven empty.
+        assert!(run(&test_case(5, None)).is_err());
+    }
+
+    #[test]
+    fn test_default_nodes() {
+        let levels = native_tree(&[[0u8; 32]; 1 << DEPTH]);
+        let defaults = default_nodes(DEPTH);
+        for (height, level) in levels.iter().enumerate() {
+            assert_eq!(level[0], defaults[height]);
+        }
+    }
+}
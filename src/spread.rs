@@ -1,6 +1,7 @@
-use std::marker::PhantomData;
+use alloc::{format, vec, vec::Vec};
+use core::marker::PhantomData;
 
-use crate::{utils::*, SpreadU32};
+use crate::{bits::*, SpreadU32};
 use halo2_base::halo2_proofs::halo2curves::FieldExt;
 use halo2_base::halo2_proofs::{
     circuit::{AssignedCell, Cell, Layouter, Region, SimpleFloorPlanner, Value},
@@ -22,6 +23,18 @@ use hex;
 use itertools::Itertools;
 use num_bigint::BigUint;
 
+/// Lookup of 16-bit words into their spread form, where bit `i` of the dense value is moved to bit `2i`.
+///
+/// Adding the spreads of several words and splitting the sum into even and odd bits evaluates bitwise
+/// XOR (even bits of two words) or AND (odd bits of two words) without decomposing into single bits.
+/// The table maps every `num_bits_lookup`-bit limb to its spread, and each dense/spread column pair
+/// looks into it. A config is stateful: it tracks the next free row of its columns, so chips sharing
+/// a table must share the same `SpreadConfig` through a mutable reference.
+///
+/// Both table columns are stored. The spread of a limb is not a low-degree expression of its dense value, and a
+/// single column of `dense + 2^num_bits_lookup * spread` would let a prover shift a multiple of `2^num_bits_lookup`
+/// from the dense limb into the spread unless every limb were also range-checked, which costs more rows than the
+/// `2^num_bits_lookup` rows of the second column it saves.
 #[derive(Debug, Clone)]
 pub struct SpreadConfig<F: PrimeField> {
     denses: Vec<Column<Advice>>,
@@ -32,41 +45,55 @@ pub struct SpreadConfig<F: PrimeField> {
     num_advice_columns: usize,
     num_limb_sum: usize,
     row_offset: usize,
+    annotation: (&'static str, usize),
     _f: PhantomData<F>,
 }
 
 impl<F: PrimeField> SpreadConfig<F> {
+    /// Allocates `num_advice_columns` dense columns, `num_advice_columns` spread columns and the two table columns.
+    /// `num_bits_lookup` must divide 16; the table has `2^num_bits_lookup` rows.
     pub fn configure(
         meta: &mut ConstraintSystem<F>,
         num_bits_lookup: usize,
         num_advice_columns: usize,
     ) -> Self {
-        debug_assert_eq!(16 % num_bits_lookup, 0);
-        // debug_assert_eq!(16 % (num_bits_lookup * num_advice_columns), 0);
         let denses = (0..num_advice_columns)
-            .map(|_| {
-                let column = meta.advice_column();
-                meta.enable_equality(column);
-                column
-            })
+            .map(|_| meta.advice_column())
             .collect_vec();
         let spreads = (0..num_advice_columns)
-            .map(|_| {
-                let column = meta.advice_column();
-                meta.enable_equality(column);
-                column
-            })
+            .map(|_| meta.advice_column())
             .collect_vec();
+        Self::configure_with_columns(meta, num_bits_lookup, denses, spreads, None)
+    }
 
-        let table_dense = meta.lookup_table_column();
-        let table_spread = meta.lookup_table_column();
-        for (idx, (dense, spread)) in denses.iter().zip(spreads.iter()).enumerate() {
+    /// Same as `configure`, but uses the caller's dense and spread columns, which may be shared with other chips.
+    /// `table_columns` are the (dense, spread) table columns; they are allocated when `None`.
+    /// The table is assigned by `load`, so the caller must not assign other values to provided table columns.
+    pub fn configure_with_columns(
+        meta: &mut ConstraintSystem<F>,
+        num_bits_lookup: usize,
+        denses: Vec<Column<Advice>>,
+        spreads: Vec<Column<Advice>>,
+        table_columns: Option<(TableColumn, TableColumn)>,
+    ) -> Self {
+        debug_assert_eq!(16 % num_bits_lookup, 0);
+        debug_assert_eq!(denses.len(), spreads.len());
+        debug_assert!(!denses.is_empty());
+        // equality cannot be skipped: every limb is copied in from the gate columns and every spread is copied
+        // back out, so each dense and spread column takes part in the permutation argument.
+        for column in denses.iter().chain(spreads.iter()) {
+            meta.enable_equality(*column);
+        }
+        let (table_dense, table_spread) = table_columns
+            .unwrap_or_else(|| (meta.lookup_table_column(), meta.lookup_table_column()));
+        for (dense, spread) in denses.iter().zip(spreads.iter()) {
             meta.lookup("spread lookup", |meta| {
                 let dense = meta.query_advice(*dense, Rotation::cur());
                 let spread = meta.query_advice(*spread, Rotation::cur());
                 vec![(dense, table_dense), (spread, table_spread)]
             });
         }
+        let num_advice_columns = denses.len();
         Self {
             denses,
             spreads,
@@ -76,10 +103,13 @@ impl<F: PrimeField> SpreadConfig<F> {
             num_advice_columns,
             num_limb_sum: 0,
             row_offset: 0,
+            annotation: ("spread", 0),
             _f: PhantomData,
         }
     }
 
+    /// Returns the spread of a 16-bit `dense` value. The value is decomposed into limbs that are looked up in the table,
+    /// so this also constrains `dense` to be less than `2^16`.
     pub fn spread<'v: 'a, 'a>(
         &mut self,
         ctx: &mut Context<'v, F>,
@@ -93,6 +123,11 @@ impl<F: PrimeField> SpreadConfig<F> {
         let assigned_limbs = (0..num_limbs)
             .map(|idx| gate.load_witness(ctx, limbs.as_ref().map(|vec| vec[idx])))
             .collect_vec();
+        // every limb is looked up in the table, which already bounds it to `limb_bits` bits.
+        #[cfg(feature = "paranoid")]
+        for limb in assigned_limbs.iter() {
+            range.range_check(ctx, limb, limb_bits);
+        }
         {
             let mut limbs_sum = gate.load_zero(ctx);
             for (idx, limb) in assigned_limbs.iter().enumerate() {
@@ -143,21 +178,18 @@ impl<F: PrimeField> SpreadConfig<F> {
     //     )?;
     // }
 
+    /// Splits a 32-bit `spread` value into the dense words formed by its even and odd bits.
+    /// Both outputs are range-checked to 16 bits, but it is not constrained that they recompose to `spread`;
+    /// callers must add that constraint (e.g. by spreading them again and comparing).
     pub fn decompose_even_and_odd_unchecked<'v: 'a, 'a>(
         &self,
         ctx: &mut Context<'v, F>,
         range: &RangeConfig<F>,
         spread: &AssignedValue<F>,
     ) -> Result<(AssignedValue<'a, F>, AssignedValue<'a, F>), Error> {
-        let bits_val = spread.value().map(|val| fe_to_bits_le(val, 32));
-        let even_bits_val = bits_val
-            .as_ref()
-            .map(|bits| (0..bits.len() / 2).map(|idx| bits[2 * idx]).collect_vec());
-        let odd_bits_val = bits_val.as_ref().map(|bits| {
-            (0..bits.len() / 2)
-                .map(|idx| bits[2 * idx + 1])
-                .collect_vec()
-        });
+        let bits_val = value_to_bits_le(spread.value(), 32)?;
+        let even_bits_val = bits_val.as_ref().map(|bits| even_bits(bits));
+        let odd_bits_val = bits_val.as_ref().map(|bits| odd_bits(bits));
         let (even_val, odd_val) = even_bits_val
             .zip(odd_bits_val)
             .map(|(even_bits, odd_bits)| (bits_le_to_fe(&even_bits), bits_le_to_fe(&odd_bits)))
@@ -169,26 +201,27 @@ impl<F: PrimeField> SpreadConfig<F> {
         Ok((even_assigned, odd_assigned))
     }
 
+    /// Assigns the spread table. Must be called exactly once per circuit for each table.
+    /// Rows are assigned one by one straight from their integer values, without materialising the table.
     pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        #[cfg(feature = "tracing")]
+        let _span =
+            tracing::debug_span!("spread table load", num_bits_lookup = self.num_bits_lookup)
+                .entered();
         layouter.assign_table(
             || "spread table",
             |mut table| {
                 for idx in 0..(1usize << self.num_bits_lookup) {
                     let val_dense = F::from(idx as u64);
-                    let val_bits = fe_to_bits_le(&val_dense, 32);
-                    let mut spread_bits = vec![false; val_bits.len() * 2];
-                    for i in 0..val_bits.len() {
-                        spread_bits[2 * i] = val_bits[i];
-                    }
-                    let val_spread: F = bits_le_to_fe(&spread_bits);
+                    let val_spread = F::from(spread_u16(idx as u16) as u64);
                     table.assign_cell(
-                        || format!("table_dense at {}", idx),
+                        || "table_dense",
                         self.table_dense,
                         idx,
                         || Value::known(val_dense),
                     )?;
                     table.assign_cell(
-                        || format!("table_spread at {}", idx),
+                        || "table_spread",
                         self.table_spread,
                         idx,
                         || Value::known(val_spread),
@@ -200,6 +233,40 @@ impl<F: PrimeField> SpreadConfig<F> {
         Ok(())
     }
 
+    pub fn num_bits_lookup(&self) -> usize {
+        self.num_bits_lookup
+    }
+
+    pub fn dense_column_indexes(&self) -> Vec<usize> {
+        self.denses
+            .iter()
+            .map(|column| column.index())
+            .collect_vec()
+    }
+
+    pub fn spread_column_indexes(&self) -> Vec<usize> {
+        self.spreads
+            .iter()
+            .map(|column| column.index())
+            .collect_vec()
+    }
+
+    /// Sets the step, e.g. `("Σ1 in compression round", 17)`, named in the annotations of the cells assigned next,
+    /// so that a failing lookup reported by `MockProver` can be traced back to the part of the hash that assigned it.
+    pub fn set_annotation(&mut self, step: &'static str, index: usize) {
+        self.annotation = (step, index);
+    }
+
+    /// Returns the number of limbs looked up so far.
+    pub fn num_lookups(&self) -> usize {
+        self.num_limb_sum
+    }
+
+    /// Returns the number of rows of the dense and spread columns used so far.
+    pub fn num_rows(&self) -> usize {
+        (self.num_limb_sum + self.num_advice_columns - 1) / self.num_advice_columns
+    }
+
     fn spread_limb<'v: 'a, 'a>(
         &mut self,
         ctx: &mut Context<'v, F>,
@@ -207,24 +274,20 @@ impl<F: PrimeField> SpreadConfig<F> {
         limb: &AssignedValue<F>,
     ) -> Result<AssignedValue<'a, F>, Error> {
         let column_idx = self.num_limb_sum % self.num_advice_columns;
+        let (step, index) = self.annotation;
         let assigned_dense_cell = ctx.region.assign_advice(
-            || format!("dense at offset {}", self.row_offset),
+            || format!("{} {}: dense limb in column {}", step, index, column_idx),
             self.denses[column_idx],
             self.row_offset,
             || limb.value,
         )?;
         ctx.region
             .constrain_equal(assigned_dense_cell.cell(), limb.cell())?;
-        let spread_value: Value<F> = limb.value().map(|val| {
-            let val_bits = fe_to_bits_le(val, 32);
-            let mut spread_bits = vec![false; val_bits.len() * 2];
-            for i in 0..val_bits.len() {
-                spread_bits[2 * i] = val_bits[i];
-            }
-            bits_le_to_fe(&spread_bits)
-        });
+        let spread_value: Value<F> = limb
+            .value()
+            .map(|val| F::from(spread_u16(val.get_lower_32() as u16) as u64));
         let assigned_spread_cell = ctx.region.assign_advice(
-            || format!("spread at offset {}", self.row_offset),
+            || format!("{} {}: spread limb in column {}", step, index, column_idx),
             self.spreads[column_idx],
             self.row_offset,
             || spread_value,
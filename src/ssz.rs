@@ -0,0 +1,405 @@
+use crate::Sha256DynamicConfig;
+use alloc::{vec, vec::Vec};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, range::RangeConfig, GateInstructions, RangeInstructions},
+    utils::PrimeField,
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+use sha2::{Digest, Sha256};
+
+pub const BYTES_PER_CHUNK: usize = 32;
+
+/// Returns the roots of all-zero subtrees of depth `0..=depth`.
+pub fn zero_hashes(depth: usize) -> Vec<[u8; BYTES_PER_CHUNK]> {
+    let mut hashes = vec![[0u8; BYTES_PER_CHUNK]];
+    for _ in 0..depth {
+        let child = hashes.last().unwrap();
+        let mut hasher = Sha256::new();
+        hasher.update(child);
+        hasher.update(child);
+        hashes.push(hasher.finalize().into());
+    }
+    hashes
+}
+
+/// Packs bytes into 32-byte chunks, right-padding the last chunk with zeros.
+pub fn pack_bytes<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    gate: &FlexGateConfig<F>,
+    bytes: &[AssignedValue<'a, F>],
+) -> Vec<Vec<AssignedValue<'a, F>>> {
+    bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|chunk| {
+            let mut chunk = chunk.to_vec();
+            while chunk.len() < BYTES_PER_CHUNK {
+                chunk.push(gate.load_zero(ctx));
+            }
+            chunk
+        })
+        .collect_vec()
+}
+
+/// Serializes a `uint64` into a chunk, i.e., 8 little-endian bytes followed by 24 zero bytes.
+/// The bytes are range-checked, so `value` is constrained to be less than `2^64`.
+pub fn uint64_to_chunk<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    range: &RangeConfig<F>,
+    value: &AssignedValue<'a, F>,
+) -> Vec<AssignedValue<'a, F>> {
+    let gate = range.gate();
+    let value_u64 = value.value().map(|v| v.get_lower_128() as u64);
+    let mut chunk = (0..8)
+        .map(|idx| {
+            let byte = gate.load_witness(ctx, value_u64.map(|v| F::from((v >> (8 * idx)) & 0xff)));
+            range.range_check(ctx, &byte, 8);
+            byte
+        })
+        .collect_vec();
+    let recomposed = gate.inner_product(
+        ctx,
+        chunk.iter().map(QuantumCell::Existing),
+        (0..8).map(|idx| QuantumCell::Constant(F::from_u128(1u128 << (8 * idx)))),
+    );
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(value),
+        QuantumCell::Existing(&recomposed),
+    );
+    for _ in 8..BYTES_PER_CHUNK {
+        chunk.push(gate.load_zero(ctx));
+    }
+    chunk
+}
+
+/// Merkleizes `chunks` into a binary tree padded with zero chunks up to `limit` (or `chunks.len()`) rounded up to a power of two.
+/// Missing subtrees are the precomputed zero hashes assigned as constants, so only the hashes over `chunks` are computed.
+pub fn merkleize<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    chunks: &[Vec<AssignedValue<'a, F>>],
+    limit: Option<usize>,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let limit = limit.unwrap_or(chunks.len());
+    assert!(chunks.len() <= limit);
+    let depth = limit.max(1).next_power_of_two().trailing_zeros() as usize;
+    let zeros = zero_hashes(depth);
+    let gate = sha256.range().gate().clone();
+    let load_zero_hash = |ctx: &mut Context<'b, F>, height: usize| {
+        zeros[height]
+            .iter()
+            .map(|byte| gate.load_constant(ctx, F::from(*byte as u64)))
+            .collect_vec()
+    };
+    if chunks.is_empty() {
+        return Ok(load_zero_hash(ctx, depth));
+    }
+    let mut layer = chunks.to_vec();
+    for height in 0..depth {
+        if layer.len() % 2 == 1 {
+            layer.push(load_zero_hash(ctx, height));
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                let input = pair[0].iter().chain(pair[1].iter()).cloned().collect_vec();
+                sha256.digest_fixed(ctx, &input)
+            })
+            .collect::<Result<Vec<Vec<AssignedValue<F>>>, Error>>()?;
+    }
+    Ok(layer.pop().unwrap())
+}
+
+/// Returns `hash(root || length)` where `length` is serialized as a `uint64` chunk.
+pub fn mix_in_length<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    root: &[AssignedValue<'a, F>],
+    length: &AssignedValue<'a, F>,
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    let range = sha256.range();
+    let length_chunk = uint64_to_chunk(ctx, range, length);
+    let input = root
+        .iter()
+        .chain(length_chunk.iter())
+        .cloned()
+        .collect_vec();
+    sha256.digest_fixed(ctx, &input)
+}
+
+/// Computes the hash-tree-root of a `DepositData` container.
+/// The fields are `pubkey: Bytes48`, `withdrawal_credentials: Bytes32`, `amount: uint64` and `signature: Bytes96`.
+pub fn deposit_data_root<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    pubkey: &[AssignedValue<'a, F>],
+    withdrawal_credentials: &[AssignedValue<'a, F>],
+    amount: &AssignedValue<'a, F>,
+    signature: &[AssignedValue<'a, F>],
+) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+    assert_eq!(pubkey.len(), 48);
+    assert_eq!(withdrawal_credentials.len(), BYTES_PER_CHUNK);
+    assert_eq!(signature.len(), 96);
+    let range = sha256.range().clone();
+    let gate = range.gate();
+    let pubkey_chunks = pack_bytes(ctx, gate, pubkey);
+    let pubkey_root = merkleize(ctx, sha256, &pubkey_chunks, None)?;
+    let amount_chunk = uint64_to_chunk(ctx, &range, amount);
+    let signature_chunks = pack_bytes(ctx, gate, signature);
+    let signature_root = merkleize(ctx, sha256, &signature_chunks, None)?;
+    let field_roots = vec![
+        pubkey_root,
+        withdrawal_credentials.to_vec(),
+        amount_chunk,
+        signature_root,
+    ];
+    merkleize(ctx, sha256, &field_roots, None)
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{gates::range::RangeStrategy::Vertical, SKIP_FIRST_PASS};
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        pubkey: Vec<u8>,
+        withdrawal_credentials: Vec<u8>,
+        amount: u64,
+        signature: Vec<u8>,
+        expected_root: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[24], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![], range_config, 8, 8, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "deposit data root test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let mut load_bytes = |bytes: &[u8]| {
+                        bytes
+                            .iter()
+                            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                            .collect_vec()
+                    };
+                    let pubkey = load_bytes(&self.pubkey);
+                    let withdrawal_credentials = load_bytes(&self.withdrawal_credentials);
+                    let signature = load_bytes(&self.signature);
+                    let amount = gate.load_witness(ctx, Value::known(F::from(self.amount)));
+                    let root = deposit_data_root(
+                        ctx,
+                        &mut sha256,
+                        &pubkey,
+                        &withdrawal_credentials,
+                        &amount,
+                        &signature,
+                    )?;
+                    for (assigned, expected) in root.iter().zip(self.expected_root.iter()) {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_deposit_data_root() {
+        let mut withdrawal_credentials = vec![0x01];
+        withdrawal_credentials.extend(vec![0; 11]);
+        withdrawal_credentials.extend(0..20u8);
+        let circuit = TestCircuit::<Fr> {
+            pubkey: (0..48u8).collect_vec(),
+            withdrawal_credentials,
+            amount: 32_000_000_000,
+            signature: (0..96u32).map(|idx| ((idx * 7) % 256) as u8).collect_vec(),
+            expected_root: hex::decode(
+                "4ca94fe01601ac7c1e8980df704076dfdc8c1f945f36a91a38e5d2e3caebcecb",
+            )
+            .unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[derive(Debug, Clone)]
+    struct ByteListCircuit<F: PrimeField> {
+        bytes: Vec<u8>,
+        limit_chunks: usize,
+        length: u128,
+        expected_root: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for ByteListCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            TestCircuit::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "byte list root test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let bytes = self
+                        .bytes
+                        .iter()
+                        .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                        .collect_vec();
+                    let length = gate.load_witness(ctx, Value::known(F::from_u128(self.length)));
+                    let chunks = pack_bytes(ctx, gate, &bytes);
+                    let root = merkleize(ctx, &mut sha256, &chunks, Some(self.limit_chunks))?;
+                    let root = mix_in_length(ctx, &mut sha256, &root, &length)?;
+                    for (assigned, expected) in root.iter().zip(self.expected_root.iter()) {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    /// Merkleizes `chunks` padded with zero chunks to `limit` rounded up to a power of two, hashing every node.
+    fn native_merkleize(chunks: &[[u8; BYTES_PER_CHUNK]], limit: usize) -> [u8; BYTES_PER_CHUNK] {
+        let mut layer = chunks.to_vec();
+        layer.resize(limit.next_power_of_two(), [0; BYTES_PER_CHUNK]);
+        while layer.len() > 1 {
+            layer = layer
+                .chunks(2)
+                .map(|pair| {
+                    let mut hasher = Sha256::new();
+                    hasher.update(pair[0]);
+                    hasher.update(pair[1]);
+                    hasher.finalize().into()
+                })
+                .collect_vec();
+        }
+        layer[0]
+    }
+
+    /// Returns the hash-tree-root of `bytes` as a `ByteList` of up to `limit_chunks` chunks.
+    fn native_byte_list_root(bytes: &[u8], limit_chunks: usize) -> Vec<u8> {
+        let chunks = bytes
+            .chunks(BYTES_PER_CHUNK)
+            .map(|chunk| {
+                let mut padded = [0; BYTES_PER_CHUNK];
+                padded[..chunk.len()].copy_from_slice(chunk);
+                padded
+            })
+            .collect_vec();
+        let mut length_chunk = [0; BYTES_PER_CHUNK];
+        length_chunk[..8].copy_from_slice(&(bytes.len() as u64).to_le_bytes());
+        let mut hasher = Sha256::new();
+        hasher.update(native_merkleize(&chunks, limit_chunks));
+        hasher.update(length_chunk);
+        hasher.finalize().to_vec()
+    }
+
+    #[test]
+    fn test_zero_hashes() {
+        let hashes = zero_hashes(3);
+        for (depth, hash) in hashes.iter().enumerate() {
+            assert_eq!(*hash, native_merkleize(&[], 1 << depth));
+        }
+    }
+
+    #[test]
+    fn test_byte_list_root() {
+        // two chunks under a limit of eight, so the tree is completed with zero hashes of height 0, 1 and 2.
+        let bytes = (0..40u8).collect_vec();
+        let circuit = ByteListCircuit::<Fr> {
+            expected_root: native_byte_list_root(&bytes, 8),
+            bytes,
+            limit_chunks: 8,
+            length: 40,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // the root of the same bytes with another length.
+        let mut circuit = circuit;
+        circuit.length = 41;
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_uint64_chunk_rejects_overflow() {
+        // the witnessed bytes of the length wrap around to 40, which is not equal to the assigned length.
+        let bytes = (0..40u8).collect_vec();
+        let circuit = ByteListCircuit::<Fr> {
+            expected_root: native_byte_list_root(&bytes, 8),
+            bytes,
+            limit_chunks: 8,
+            length: (1u128 << 64) + 40,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+/// Resources assigned by the compressions of a `Sha256DynamicConfig`, accumulated during synthesis.
+/// Cells assigned outside of the compressions, e.g. for padding and length checks, are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sha256Stats {
+    /// Number of 64-byte blocks compressed.
+    pub num_compressions: usize,
+    /// Cells assigned in the flex gate advice columns.
+    pub advice_cells: usize,
+    /// Cells range-checked through the lookup advice columns of the range chip.
+    pub range_lookup_cells: usize,
+    /// Limbs looked up in the spread table. Each of them is copied into and out of the spread columns.
+    pub spread_lookups: usize,
+    /// Rows of the spread columns used so far.
+    pub spread_rows: usize,
+}
+
+impl Sha256Stats {
+    /// Returns the number of flex gate advice cells per compressed block.
+    pub fn advice_cells_per_block(&self) -> usize {
+        self.advice_cells / self.num_compressions.max(1)
+    }
+
+    /// Returns the number of spread lookups per compressed block.
+    pub fn spread_lookups_per_block(&self) -> usize {
+        self.spread_lookups / self.num_compressions.max(1)
+    }
+}
+
+/// Columns of a circuit that only contains the SHA-256 chip, as passed to `RangeConfig::configure` and
+/// `Sha256DynamicConfig::configure`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutOptions {
+    pub num_advice: usize,
+    pub num_lookup_advice: usize,
+    pub lookup_bits: usize,
+    pub num_bits_lookup: usize,
+    pub num_spread_columns: usize,
+    /// Rows reserved by the proving system at the end of each column, see `ConstraintSystem::minimum_rows`.
+    pub num_unusable_rows: usize,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            num_advice: 3,
+            num_lookup_advice: 1,
+            lookup_bits: 16,
+            num_bits_lookup: 8,
+            num_spread_columns: 2,
+            num_unusable_rows: 10,
+        }
+    }
+}
+
+/// Returns the smallest `k` whose usable rows fit the compressions of a `max_bytes`-byte input, given the
+/// `stats` of a synthesis of the same chip (e.g. a single `MockProver` run with any input size).
+/// The rows of both lookup tables are taken into account; cells assigned outside of the compressions are not.
+pub fn min_k_for(max_bytes: usize, stats: &Sha256Stats, options: &LayoutOptions) -> u32 {
+    assert!(
+        stats.num_compressions > 0,
+        "stats must cover at least one compression"
+    );
+    let num_blocks = (max_bytes + 9 + 63) / 64;
+    let per_block = |total: usize| (total + stats.num_compressions - 1) / stats.num_compressions;
+    let div_ceil = |cells: usize, columns: usize| (cells + columns - 1) / columns;
+    let num_rows = [
+        div_ceil(
+            num_blocks * per_block(stats.advice_cells),
+            options.num_advice,
+        ),
+        div_ceil(
+            num_blocks * per_block(stats.range_lookup_cells),
+            options.num_lookup_advice,
+        ),
+        div_ceil(
+            num_blocks * per_block(stats.spread_lookups),
+            options.num_spread_columns,
+        ),
+        1 << options.lookup_bits,
+        1 << options.num_bits_lookup,
+    ]
+    .iter()
+    .max()
+    .unwrap()
+        + options.num_unusable_rows;
+    let mut k = 0;
+    while (1usize << k) < num_rows {
+        k += 1;
+    }
+    k
+}
+
+/// Returns the largest input size in bytes whose compressions fit in the usable rows of a circuit of `2^k` rows,
+/// given the `stats` of a synthesis of the same chip. This is the inverse of `min_k_for`, e.g. for a service whose
+/// trusted setup fixes `k`. Returns 0 if the lookup tables or a single block do not fit.
+pub fn max_bytes_for_k(k: u32, stats: &Sha256Stats, options: &LayoutOptions) -> usize {
+    assert!(
+        stats.num_compressions > 0,
+        "stats must cover at least one compression"
+    );
+    let num_rows = (1usize << k).saturating_sub(options.num_unusable_rows);
+    if (1 << options.lookup_bits) > num_rows || (1 << options.num_bits_lookup) > num_rows {
+        return 0;
+    }
+    let per_block = |total: usize| (total + stats.num_compressions - 1) / stats.num_compressions;
+    let max_blocks = |total: usize, columns: usize| match per_block(total) {
+        0 => usize::MAX,
+        cells => num_rows * columns / cells,
+    };
+    let num_blocks = [
+        max_blocks(stats.advice_cells, options.num_advice),
+        max_blocks(stats.range_lookup_cells, options.num_lookup_advice),
+        max_blocks(stats.spread_lookups, options.num_spread_columns),
+    ]
+    .iter()
+    .copied()
+    .min()
+    .unwrap();
+    // the padding takes at least 9 bytes of the last block.
+    num_blocks.saturating_mul(64).saturating_sub(9)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_min_k_for() {
+        let stats = Sha256Stats {
+            num_compressions: 2,
+            advice_cells: 2 * 30000,
+            range_lookup_cells: 2 * 5000,
+            spread_lookups: 2 * 4000,
+            spread_rows: 2 * 2000,
+        };
+        let options = LayoutOptions::default();
+        // the range table alone needs 2^16 + 10 rows.
+        assert_eq!(min_k_for(0, &stats, &options), 17);
+        // 13 blocks need 130000 rows in each of the 3 advice columns, 14 blocks need 140000.
+        assert_eq!(min_k_for(13 * 64 - 9, &stats, &options), 17);
+        assert_eq!(min_k_for(13 * 64 - 8, &stats, &options), 18);
+    }
+
+    #[test]
+    fn test_max_bytes_for_k() {
+        let stats = Sha256Stats {
+            num_compressions: 2,
+            advice_cells: 2 * 30000,
+            range_lookup_cells: 2 * 5000,
+            spread_lookups: 2 * 4000,
+            spread_rows: 2 * 2000,
+        };
+        let options = LayoutOptions::default();
+        // the range table does not fit in 2^16 rows.
+        assert_eq!(max_bytes_for_k(16, &stats, &options), 0);
+        assert_eq!(max_bytes_for_k(17, &stats, &options), 13 * 64 - 9);
+        for k in 17..22 {
+            let max_bytes = max_bytes_for_k(k, &stats, &options);
+            assert_eq!(min_k_for(max_bytes, &stats, &options), k);
+            assert_eq!(min_k_for(max_bytes + 1, &stats, &options), k + 1);
+        }
+    }
+}
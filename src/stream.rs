@@ -0,0 +1,343 @@
+use alloc::{vec, vec::Vec};
+use core::marker::PhantomData;
+
+use crate::endian::digest_to_be_bytes;
+use crate::preimage::Sha256PreimageConfig;
+use crate::{reference, Sha256DynamicConfig, INIT_STATE, NUM_STATE_WORD};
+use halo2_base::halo2_proofs::{
+    circuit::{Cell, Layouter, SimpleFloorPlanner, Value},
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2_base::{
+    gates::{
+        range::{RangeConfig, RangeStrategy::Vertical},
+        GateInstructions, RangeInstructions,
+    },
+    utils::PrimeField,
+    SKIP_FIRST_PASS,
+};
+use itertools::Itertools;
+
+/// Public inputs of a `Sha256ChunkCircuit`: the state before and after the chunk and the number of bytes compressed
+/// into `midstate_in`. They are laid out in this order in the instance column, one word per row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkInstances {
+    pub midstate_in: [u32; NUM_STATE_WORD],
+    pub midstate_out: [u32; NUM_STATE_WORD],
+    pub length_so_far: u64,
+}
+
+impl ChunkInstances {
+    pub fn to_instances<F: PrimeField>(&self) -> Vec<F> {
+        self.midstate_in
+            .iter()
+            .chain(self.midstate_out.iter())
+            .map(|word| F::from(*word as u64))
+            .chain(core::iter::once(F::from(self.length_so_far)))
+            .collect()
+    }
+
+    /// Decodes the public inputs of a proof, or returns `None` if they are not 8 + 8 words of 32 bits and a 64-bit length.
+    pub fn from_instances<F: PrimeField>(instances: &[F]) -> Option<Self> {
+        if instances.len() != 2 * NUM_STATE_WORD + 1 {
+            return None;
+        }
+        let mut words = [0u32; 2 * NUM_STATE_WORD];
+        for (word, fe) in words.iter_mut().zip(instances.iter()) {
+            *word = fe.get_lower_32();
+            if F::from(*word as u64) != *fe {
+                return None;
+            }
+        }
+        let length_so_far = instances[2 * NUM_STATE_WORD].get_lower_128() as u64;
+        if F::from(length_so_far) != instances[2 * NUM_STATE_WORD] {
+            return None;
+        }
+        let mut midstate_in = [0u32; NUM_STATE_WORD];
+        let mut midstate_out = [0u32; NUM_STATE_WORD];
+        midstate_in.copy_from_slice(&words[0..NUM_STATE_WORD]);
+        midstate_out.copy_from_slice(&words[NUM_STATE_WORD..]);
+        Some(Self {
+            midstate_in,
+            midstate_out,
+            length_so_far,
+        })
+    }
+}
+
+/// Checks that `chunks`, the public inputs of verified `Sha256ChunkCircuit` proofs in order, chain into the hash of
+/// one message: the first one starts from the IV and every other one starts from the state and length reached by the
+/// previous one. All the proofs but the last must have been verified with the key of intermediate chunks, and the
+/// last one with the key of final chunks. Returns the digest of the message, or `None` if the chain is broken.
+pub fn check_chain<const CHUNK_BLOCKS: usize>(chunks: &[ChunkInstances]) -> Option<[u8; 32]> {
+    let mut state = INIT_STATE;
+    let mut length_so_far = 0;
+    for chunk in chunks.iter() {
+        if chunk.midstate_in != state || chunk.length_so_far != length_so_far {
+            return None;
+        }
+        state = chunk.midstate_out;
+        length_so_far += 64 * CHUNK_BLOCKS as u64;
+    }
+    chunks.last()?;
+    Some(digest_to_be_bytes(&state))
+}
+
+/// Proves one link of a chain of proofs hashing a message of any length.
+///
+/// An intermediate chunk compresses `CHUNK_BLOCKS` blocks of the message without padding. The final chunk hashes the
+/// remaining bytes, at most `max_final_byte_size()`, with the padding of the whole message, and its `midstate_out` is
+/// the digest as eight big-endian words. The public inputs are given by `ChunkInstances`. Both kinds have the same
+/// columns but different keys, which do not depend on the position of the chunk in the message.
+#[derive(Debug, Clone)]
+pub struct Sha256ChunkCircuit<F: PrimeField, const CHUNK_BLOCKS: usize> {
+    midstate_in: [u32; NUM_STATE_WORD],
+    length_so_far: u64,
+    chunk: Vec<u8>,
+    is_final: bool,
+    _f: PhantomData<F>,
+}
+
+impl<F: PrimeField, const CHUNK_BLOCKS: usize> Sha256ChunkCircuit<F, CHUNK_BLOCKS> {
+    pub const K: u32 = 17;
+    const NUM_FIXED: usize = 1;
+    const NUM_LOOKUP_ADVICE: usize = 1;
+    const LOOKUP_BITS: usize = 16;
+    /// Blocks whose spread lookups fit in one pair of spread columns at `K`.
+    const BLOCKS_PER_SPREAD_COLUMN: usize = 22;
+
+    /// Returns an intermediate chunk of `CHUNK_BLOCKS` blocks, starting from the state after `length_so_far` bytes.
+    pub fn new(midstate_in: [u32; NUM_STATE_WORD], length_so_far: u64, chunk: Vec<u8>) -> Self {
+        assert_eq!(chunk.len(), Self::chunk_byte_size());
+        assert_eq!(length_so_far % 64, 0);
+        Self {
+            midstate_in,
+            length_so_far,
+            chunk,
+            is_final: false,
+            _f: PhantomData,
+        }
+    }
+
+    /// Returns the final chunk, holding the last bytes of the message.
+    pub fn new_final(
+        midstate_in: [u32; NUM_STATE_WORD],
+        length_so_far: u64,
+        chunk: Vec<u8>,
+    ) -> Self {
+        assert!(chunk.len() <= Self::max_final_byte_size());
+        assert_eq!(length_so_far % 64, 0);
+        Self {
+            midstate_in,
+            length_so_far,
+            chunk,
+            is_final: true,
+            _f: PhantomData,
+        }
+    }
+
+    /// Splits `message` into the circuits of a chain, ending with the final chunk.
+    pub fn chain(message: &[u8]) -> Vec<Self> {
+        let mut circuits = vec![];
+        let mut state = INIT_STATE;
+        let mut offset = 0;
+        while message.len() - offset > Self::max_final_byte_size() {
+            let chunk = message[offset..offset + Self::chunk_byte_size()].to_vec();
+            let circuit = Self::new(state, offset as u64, chunk);
+            state = circuit.midstate_out();
+            circuits.push(circuit);
+            offset += Self::chunk_byte_size();
+        }
+        circuits.push(Self::new_final(
+            state,
+            offset as u64,
+            message[offset..].to_vec(),
+        ));
+        circuits
+    }
+
+    pub fn chunk_byte_size() -> usize {
+        64 * CHUNK_BLOCKS
+    }
+
+    /// Returns the maximum number of bytes of the final chunk, leaving room for the padding.
+    pub fn max_final_byte_size() -> usize {
+        Self::chunk_byte_size() - 9
+    }
+
+    /// Returns the state after the chunk, computed outside of the circuit.
+    pub fn midstate_out(&self) -> [u32; NUM_STATE_WORD] {
+        if !self.is_final {
+            let mut state = self.midstate_in;
+            for block in self.chunk.chunks(64) {
+                state = reference::compression_trace(&state, block).state_out;
+            }
+            return state;
+        }
+        let mut padded = self.chunk.clone();
+        padded.push(0x80);
+        while padded.len() % 64 != 56 {
+            padded.push(0);
+        }
+        let bit_len = 8 * (self.length_so_far + self.chunk.len() as u64);
+        padded.extend_from_slice(&bit_len.to_be_bytes());
+        let mut state = self.midstate_in;
+        for block in padded.chunks(64) {
+            state = reference::compression_trace(&state, block).state_out;
+        }
+        state
+    }
+
+    pub fn chunk_instances(&self) -> ChunkInstances {
+        ChunkInstances {
+            midstate_in: self.midstate_in,
+            midstate_out: self.midstate_out(),
+            length_so_far: self.length_so_far,
+        }
+    }
+
+    pub fn instances(&self) -> Vec<Vec<F>> {
+        vec![self.chunk_instances().to_instances()]
+    }
+
+    fn num_advice() -> usize {
+        (CHUNK_BLOCKS + 1) / 2 + 1
+    }
+
+    fn num_spread_columns() -> usize {
+        CHUNK_BLOCKS / Self::BLOCKS_PER_SPREAD_COLUMN + 1
+    }
+}
+
+impl<F: PrimeField, const CHUNK_BLOCKS: usize> Circuit<F> for Sha256ChunkCircuit<F, CHUNK_BLOCKS> {
+    type Config = Sha256PreimageConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        if self.is_final {
+            Self::new_final(INIT_STATE, 0, vec![])
+        } else {
+            Self::new(INIT_STATE, 0, vec![0; Self::chunk_byte_size()])
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let range_config = RangeConfig::configure(
+            meta,
+            Vertical,
+            &[Self::num_advice()],
+            &[Self::NUM_LOOKUP_ADVICE],
+            Self::NUM_FIXED,
+            Self::LOOKUP_BITS,
+            0,
+            Self::K as usize,
+        );
+        let digest_column = meta.instance_column();
+        meta.enable_equality(digest_column);
+        let sha256 = Sha256DynamicConfig::configure(
+            meta,
+            vec![Self::chunk_byte_size()],
+            range_config,
+            8,
+            Self::num_spread_columns(),
+            true,
+        );
+        Self::Config {
+            sha256,
+            digest_column,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let mut sha256 = config.sha256.clone();
+        let range = sha256.range().clone();
+        range.load_lookup_table(&mut layouter)?;
+        sha256.load(&mut layouter)?;
+        let mut first_pass = SKIP_FIRST_PASS;
+        let mut public_cells: Vec<Cell> = vec![];
+        layouter.assign_region(
+            || "sha256 chunk",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let ctx = &mut sha256.new_context(region);
+                let gate = range.gate();
+                let midstate_in = self
+                    .midstate_in
+                    .iter()
+                    .map(|word| gate.load_witness(ctx, Value::known(F::from(*word as u64))))
+                    .collect_vec();
+                let length_so_far =
+                    gate.load_witness(ctx, Value::known(F::from(self.length_so_far)));
+                let midstate_out = if self.is_final {
+                    sha256
+                        .digest_from_midstate_with_assigned_len(
+                            ctx,
+                            &midstate_in,
+                            &length_so_far,
+                            &self.chunk,
+                        )?
+                        .output_words
+                } else {
+                    let bytes = self
+                        .chunk
+                        .iter()
+                        .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                        .collect_vec();
+                    let mut state = midstate_in.clone();
+                    for block in bytes.chunks(64) {
+                        state = sha256.compress_with_state(ctx, &state, block)?;
+                    }
+                    state
+                };
+                public_cells = midstate_in
+                    .iter()
+                    .chain(midstate_out.iter())
+                    .chain(core::iter::once(&length_so_far))
+                    .map(|cell| cell.cell())
+                    .collect();
+                range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        for (idx, cell) in public_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.digest_column, idx)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use halo2_base::halo2_proofs::{dev::MockProver, halo2curves::bn256::Fr};
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn test_sha256_chunk_chain() {
+        let message = (0..150).map(|idx| idx as u8).collect_vec();
+        let circuits = Sha256ChunkCircuit::<Fr, 1>::chain(&message);
+        assert_eq!(circuits.len(), 3);
+        let mut chunks = vec![];
+        for circuit in circuits.iter() {
+            let instances = circuit.instances();
+            let prover =
+                MockProver::run(Sha256ChunkCircuit::<Fr, 1>::K, circuit, instances.clone())
+                    .unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+            chunks.push(ChunkInstances::from_instances(&instances[0]).unwrap());
+        }
+        assert_eq!(
+            check_chain::<1>(&chunks).unwrap()[..],
+            Sha256::digest(&message)[..]
+        );
+        chunks.swap(0, 1);
+        assert_eq!(check_chain::<1>(&chunks), None);
+    }
+}
@@ -0,0 +1,195 @@
+//! Circuit harness of the unit tests of the chips built on `Sha256DynamicConfig`.
+//!
+//! `Sha256TestCircuit` configures the range and SHA-256 chips, an instance column and an optional extra chip, loads
+//! their tables and calls a synthesis closure in a single region, so that a test only writes the part it checks.
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use crate::rlc::RlcConfig;
+use crate::Sha256DynamicConfig;
+use halo2_base::halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner, Value},
+    dev::{MockProver, VerifyFailure},
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
+};
+use halo2_base::{
+    gates::range::{RangeConfig, RangeStrategy::Vertical},
+    utils::PrimeField,
+    AssignedValue, Context, SKIP_FIRST_PASS,
+};
+
+/// Degree of the test circuits.
+pub(crate) const K: u32 = 17;
+
+/// Columns of a `Sha256TestCircuit`. `Circuit::configure` takes no `self`, so they are given by a type.
+pub(crate) trait TestShape<F: PrimeField> {
+    /// Chip configured next to the SHA-256 chip, or `()`.
+    type Extra: TestChip<F>;
+    /// Number of flex gate advice columns.
+    const NUM_ADVICE: usize;
+    const MAX_VARIABLE_BYTE_SIZES: &'static [usize] = &[];
+    const NUM_SPREAD_COLUMNS: usize = 2;
+}
+
+/// Shape with `NUM_ADVICE` advice columns, no input of dynamic length and no extra chip.
+pub(crate) struct PlainShape<const NUM_ADVICE: usize>;
+
+impl<F: PrimeField, const NUM_ADVICE: usize> TestShape<F> for PlainShape<NUM_ADVICE> {
+    type Extra = ();
+    const NUM_ADVICE: usize = NUM_ADVICE;
+}
+
+/// A chip configured next to the SHA-256 chip of a `Sha256TestCircuit`.
+pub(crate) trait TestChip<F: PrimeField>: Clone {
+    fn configure(meta: &mut ConstraintSystem<F>, range: &RangeConfig<F>) -> Self;
+
+    /// Assigns the tables of the chip, or reads its challenges, before the test region is assigned.
+    fn load(&mut self, _layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<F: PrimeField> TestChip<F> for () {
+    fn configure(_: &mut ConstraintSystem<F>, _: &RangeConfig<F>) -> Self {}
+}
+
+/// An `RlcConfig` with the challenge read from the layouter, for the closure of a `Sha256TestCircuit`.
+#[derive(Debug, Clone)]
+pub(crate) struct RlcTestChip<F: PrimeField> {
+    pub rlc: RlcConfig<F>,
+    pub challenge: Value<F>,
+}
+
+impl<F: PrimeField> TestChip<F> for RlcTestChip<F> {
+    fn configure(meta: &mut ConstraintSystem<F>, _: &RangeConfig<F>) -> Self {
+        Self {
+            rlc: RlcConfig::configure(meta),
+            challenge: Value::unknown(),
+        }
+    }
+
+    fn load(&mut self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.challenge = layouter.get_challenge(self.rlc.challenge);
+        Ok(())
+    }
+}
+
+/// The chips of a `Sha256TestCircuit`, handed to its closure.
+#[derive(Debug, Clone)]
+pub(crate) struct TestChips<F: PrimeField, E> {
+    pub range: RangeConfig<F>,
+    pub sha256: Sha256DynamicConfig<F>,
+    pub extra: E,
+    pub instance: Column<Instance>,
+}
+
+/// Calls `synthesize` in one region after loading the tables, then finalizes the range chip and constrains the
+/// values returned by `synthesize` to the rows of the instance column, in order.
+pub(crate) struct Sha256TestCircuit<F, S, G> {
+    synthesize: G,
+    _marker: PhantomData<(F, S)>,
+}
+
+impl<F, S, G> Sha256TestCircuit<F, S, G>
+where
+    F: PrimeField,
+    S: TestShape<F>,
+    G: for<'b> Fn(
+        &mut Context<'b, F>,
+        &mut TestChips<F, S::Extra>,
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error>,
+{
+    pub(crate) fn new(synthesize: G) -> Self {
+        Self {
+            synthesize,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, S, G> Circuit<F> for Sha256TestCircuit<F, S, G>
+where
+    F: PrimeField,
+    S: TestShape<F>,
+    G: for<'b> Fn(
+        &mut Context<'b, F>,
+        &mut TestChips<F, S::Extra>,
+    ) -> Result<Vec<AssignedValue<'b, F>>, Error>,
+{
+    type Config = TestChips<F, S::Extra>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        unimplemented!()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let range =
+            RangeConfig::configure(meta, Vertical, &[S::NUM_ADVICE], &[1], 1, 16, 0, K as usize);
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let extra = S::Extra::configure(meta, &range);
+        let sha256 = Sha256DynamicConfig::configure(
+            meta,
+            S::MAX_VARIABLE_BYTE_SIZES.to_vec(),
+            range.clone(),
+            8,
+            S::NUM_SPREAD_COLUMNS,
+            true,
+        );
+        TestChips {
+            range,
+            sha256,
+            extra,
+            instance,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        let mut chips = config.clone();
+        chips.range.load_lookup_table(&mut layouter)?;
+        chips.sha256.load(&mut layouter)?;
+        chips.extra.load(&mut layouter)?;
+        let mut public_cells = Vec::new();
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "sha256 test",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+                let ctx = &mut chips.sha256.new_context(region);
+                let public_values = (self.synthesize)(ctx, &mut chips)?;
+                public_cells = public_values.iter().map(|value| value.cell()).collect();
+                chips.range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        for (row, cell) in public_cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.instance, row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `MockProver` on the `Sha256TestCircuit` of shape `S` that calls `synthesize`, with `instances` in its
+/// instance column, and returns the result of `verify`.
+pub(crate) fn prove<S, G>(instances: Vec<Fr>, synthesize: G) -> Result<(), Vec<VerifyFailure>>
+where
+    S: TestShape<Fr>,
+    G: for<'b> Fn(
+        &mut Context<'b, Fr>,
+        &mut TestChips<Fr, S::Extra>,
+    ) -> Result<Vec<AssignedValue<'b, Fr>>, Error>,
+{
+    let circuit = Sha256TestCircuit::<Fr, S, G>::new(synthesize);
+    MockProver::run(K, &circuit, vec![instances])
+        .unwrap()
+        .verify()
+}
@@ -0,0 +1,274 @@
+use crate::endian::digest_to_be_bytes;
+use crate::{Sha256DynamicConfig, INIT_STATE, NUM_STATE_WORD};
+use alloc::{vec, vec::Vec};
+use generic_array::GenericArray;
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::QuantumCell;
+use halo2_base::{
+    gates::{GateInstructions, RangeInstructions},
+    utils::{biguint_to_fe, PrimeField},
+    AssignedValue, Context,
+};
+use itertools::Itertools;
+use num_bigint::BigUint;
+use sha2::compress256;
+
+const BLOCK_BYTES: usize = 64;
+/// The number of leading digest bytes packed into a challenge, which always fits in the field.
+const CHALLENGE_BYTES: usize = 31;
+
+/// Fiat-Shamir transcript chaining the midstates of the SHA-256 compression function.
+/// Absorbed bytes are compressed block by block, and every squeeze pads the buffered bytes with `0x80 || 0x00..` and compresses them.
+/// `NativeSha256Transcript` computes the same challenges out of the circuit.
+#[derive(Debug, Clone)]
+pub struct Sha256TranscriptChip<'a, F: PrimeField> {
+    state: Vec<AssignedValue<'a, F>>,
+    buffer: Vec<AssignedValue<'a, F>>,
+}
+
+impl<'a, F: PrimeField> Sha256TranscriptChip<'a, F> {
+    pub fn new<'b: 'a>(ctx: &mut Context<'b, F>, sha256: &Sha256DynamicConfig<F>) -> Self {
+        let gate = sha256.range().gate();
+        let state = INIT_STATE
+            .iter()
+            .map(|h| gate.load_constant(ctx, F::from(*h as u64)))
+            .collect_vec();
+        Self {
+            state,
+            buffer: vec![],
+        }
+    }
+
+    /// Absorbs range-checked bytes.
+    pub fn absorb<'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        sha256: &mut Sha256DynamicConfig<F>,
+        bytes: &[AssignedValue<'a, F>],
+    ) -> Result<(), Error> {
+        self.buffer.extend_from_slice(bytes);
+        while self.buffer.len() >= BLOCK_BYTES {
+            let block = self.buffer.drain(0..BLOCK_BYTES).collect_vec();
+            self.state = sha256.compress_with_state(ctx, &self.state, &block)?;
+        }
+        Ok(())
+    }
+
+    /// Returns 32 bytes derived from everything absorbed so far.
+    pub fn squeeze_bytes<'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        sha256: &mut Sha256DynamicConfig<F>,
+    ) -> Result<Vec<AssignedValue<'a, F>>, Error> {
+        let gate = sha256.range().gate().clone();
+        let mut block = self.buffer.drain(..).collect_vec();
+        block.push(gate.load_constant(ctx, F::from(0x80)));
+        while block.len() < BLOCK_BYTES {
+            block.push(gate.load_zero(ctx));
+        }
+        self.state = sha256.compress_with_state(ctx, &self.state, &block)?;
+        Ok(sha256.digest_bytes(ctx, &self.state))
+    }
+
+    /// Returns a challenge composed of the first 31 squeezed bytes in big-endian.
+    pub fn squeeze_challenge<'b: 'a>(
+        &mut self,
+        ctx: &mut Context<'b, F>,
+        sha256: &mut Sha256DynamicConfig<F>,
+    ) -> Result<AssignedValue<'a, F>, Error> {
+        let bytes = self.squeeze_bytes(ctx, sha256)?;
+        let gate = sha256.range().gate();
+        let mut challenge = gate.load_zero(ctx);
+        for byte in bytes[0..CHALLENGE_BYTES].iter() {
+            challenge = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&challenge),
+                QuantumCell::Constant(F::from(256)),
+                QuantumCell::Existing(byte),
+            );
+        }
+        Ok(challenge)
+    }
+}
+
+/// Out-of-circuit counterpart of `Sha256TranscriptChip`.
+#[derive(Debug, Clone)]
+pub struct NativeSha256Transcript {
+    state: [u32; NUM_STATE_WORD],
+    buffer: Vec<u8>,
+}
+
+impl Default for NativeSha256Transcript {
+    fn default() -> Self {
+        Self {
+            state: INIT_STATE,
+            buffer: vec![],
+        }
+    }
+}
+
+impl NativeSha256Transcript {
+    pub fn absorb(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+        while self.buffer.len() >= BLOCK_BYTES {
+            let block = self.buffer.drain(0..BLOCK_BYTES).collect_vec();
+            compress256(&mut self.state, &[GenericArray::clone_from_slice(&block)]);
+        }
+    }
+
+    pub fn squeeze_bytes(&mut self) -> [u8; 32] {
+        let mut block = self.buffer.drain(..).collect_vec();
+        block.push(0x80);
+        block.resize(BLOCK_BYTES, 0);
+        compress256(&mut self.state, &[GenericArray::clone_from_slice(&block)]);
+        digest_to_be_bytes(&self.state)
+    }
+
+    pub fn squeeze_challenge<F: PrimeField>(&mut self) -> F {
+        let bytes = self.squeeze_bytes();
+        biguint_to_fe(&BigUint::from_bytes_be(&bytes[0..CHALLENGE_BYTES]))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use crate::reference;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{
+        gates::range::{RangeConfig, RangeStrategy::Vertical},
+        SKIP_FIRST_PASS,
+    };
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        first: Vec<u8>,
+        second: Vec<u8>,
+        // the challenge squeezed after `first` and the bytes squeezed after `second`.
+        challenge: F,
+        squeezed: [u8; 32],
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[3], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(meta, vec![], range_config, 8, 2, true)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "transcript test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let load_bytes = |ctx: &mut Context<F>, bytes: &[u8]| {
+                        bytes
+                            .iter()
+                            .map(|byte| {
+                                let byte =
+                                    gate.load_witness(ctx, Value::known(F::from(*byte as u64)));
+                                range.range_check(ctx, &byte, 8);
+                                byte
+                            })
+                            .collect_vec()
+                    };
+                    let first = load_bytes(ctx, &self.first);
+                    let second = load_bytes(ctx, &self.second);
+                    let mut transcript = Sha256TranscriptChip::new(ctx, &sha256);
+                    transcript.absorb(ctx, &mut sha256, &first)?;
+                    let challenge = transcript.squeeze_challenge(ctx, &mut sha256)?;
+                    gate.assert_is_const(ctx, &challenge, self.challenge);
+                    transcript.absorb(ctx, &mut sha256, &second)?;
+                    let squeezed = transcript.squeeze_bytes(ctx, &mut sha256)?;
+                    for (assigned, expected) in squeezed.iter().zip(self.squeezed.iter()) {
+                        gate.assert_is_const(ctx, assigned, F::from(*expected as u64));
+                    }
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    fn test_circuit(first: &[u8], second: &[u8], native_first: &[u8]) -> TestCircuit<Fr> {
+        let mut native = NativeSha256Transcript::default();
+        native.absorb(native_first);
+        let challenge = native.squeeze_challenge();
+        native.absorb(second);
+        TestCircuit {
+            first: first.to_vec(),
+            second: second.to_vec(),
+            challenge,
+            squeezed: native.squeeze_bytes(),
+            _f: PhantomData,
+        }
+    }
+
+    #[test]
+    fn test_native_transcript() {
+        // a squeeze is a compression of the buffered bytes followed by `0x80 || 0x00..`.
+        let data = (0..70u8).collect_vec();
+        let mut native = NativeSha256Transcript::default();
+        native.absorb(&data);
+        let mut blocks = data.clone();
+        blocks.push(0x80);
+        blocks.resize(2 * BLOCK_BYTES, 0);
+        let squeezed = digest_to_be_bytes(&reference::midstate(&blocks));
+        assert_eq!(native.clone().squeeze_bytes(), squeezed);
+        let challenge: Fr = native.squeeze_challenge();
+        let expected = squeezed[..CHALLENGE_BYTES]
+            .iter()
+            .fold(Fr::zero(), |acc, byte| {
+                acc * Fr::from(256) + Fr::from(*byte as u64)
+            });
+        assert_eq!(challenge, expected);
+    }
+
+    #[test]
+    fn test_transcript_matches_native() {
+        // the first absorb spans a whole block, and the second leaves bytes buffered until the squeeze.
+        let first = (0..70u8).collect_vec();
+        let circuit = test_circuit(&first, b"abc", &first);
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_transcript_rejects_other_absorbed_bytes() {
+        let first = (0..70u8).collect_vec();
+        let mut native_first = first.clone();
+        native_first[65] ^= 1;
+        let circuit = test_circuit(&first, b"abc", &native_first);
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{AssignedHashResult, Sha256DynamicConfig};
+use halo2_base::halo2_proofs::plonk::Error;
+use halo2_base::{utils::PrimeField, AssignedValue, Context};
+
+const BLOCK_BYTES: usize = 64;
+
+/// Returns the number of compressions needed to hash a DER certificate of up to `max_der_len` bytes.
+pub fn num_certificate_blocks(max_der_len: usize) -> usize {
+    (max_der_len + 9 + BLOCK_BYTES - 1) / BLOCK_BYTES
+}
+
+/// Returns the entry of `max_variable_byte_sizes` to reserve for DER certificates of up to `max_der_len` bytes.
+/// Every hash consumes all of its reserved blocks regardless of the actual length, so the bound should be tight.
+pub fn certificate_capacity(max_der_len: usize) -> usize {
+    num_certificate_blocks(max_der_len) * BLOCK_BYTES
+}
+
+/// Hashes the first `der_len` bytes of `der_bytes`, the DER encoding of a certificate, and constrains the digest to be
+/// equal to `fingerprint`. `der_len` is an assigned value, so one circuit handles certificates of any length up to
+/// `der_bytes.len()`, for which `certificate_capacity` bytes must be reserved.
+/// To make the fingerprint public, constrain the cells of `fingerprint` to an instance column with `layouter.constrain_instance` after the region is assigned.
+pub fn digest_certificate<'a, 'b: 'a, F: PrimeField>(
+    ctx: &mut Context<'b, F>,
+    sha256: &mut Sha256DynamicConfig<F>,
+    der_bytes: &[AssignedValue<'b, F>],
+    der_len: &AssignedValue<'b, F>,
+    fingerprint: &[AssignedValue<'b, F>],
+) -> Result<AssignedHashResult<'b, F>, Error> {
+    assert_eq!(fingerprint.len(), 32);
+    let max_byte_size = sha256.max_variable_byte_sizes[sha256.cur_hash_idx];
+    assert!(
+        certificate_capacity(der_bytes.len()) <= max_byte_size,
+        "certificates of up to {} bytes need {} bytes of capacity, but only {} bytes are reserved",
+        der_bytes.len(),
+        certificate_capacity(der_bytes.len()),
+        max_byte_size
+    );
+    let result = sha256.digest_assigned_dynamic(ctx, der_bytes, der_len, None)?;
+    for (output, expected) in result.output_bytes.iter().zip(fingerprint.iter()) {
+        ctx.region.constrain_equal(output.cell(), expected.cell())?;
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use std::marker::PhantomData;
+
+    use super::*;
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::{
+        gates::{
+            range::{RangeConfig, RangeStrategy::Vertical},
+            GateInstructions, RangeInstructions,
+        },
+        SKIP_FIRST_PASS,
+    };
+    use itertools::Itertools;
+
+    /// DER encoding of the Amazon Root CA 3 certificate, 442 bytes hashed in 8 blocks.
+    const CERTIFICATE: &[u8] = include_bytes!(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/testdata/amazon_root_ca_3.der"
+    ));
+    /// SHA-256 fingerprint of `CERTIFICATE`, as published with the certificate.
+    const FINGERPRINT: &str = "18ce6cfe7bf14e60b2e347b8dfe868cb31d02ebb3ada271569f50343b46db3a4";
+    const MAX_DER_LEN: usize = 448;
+
+    #[derive(Debug, Clone)]
+    struct TestCircuit<F: PrimeField> {
+        der: Vec<u8>,
+        fingerprint: Vec<u8>,
+        _f: PhantomData<F>,
+    }
+
+    impl<F: PrimeField> Circuit<F> for TestCircuit<F> {
+        type Config = Sha256DynamicConfig<F>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            unimplemented!()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+            let range_config = RangeConfig::configure(meta, Vertical, &[8], &[1], 1, 16, 0, 17);
+            Sha256DynamicConfig::configure(
+                meta,
+                vec![certificate_capacity(MAX_DER_LEN)],
+                range_config,
+                8,
+                2,
+                true,
+            )
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<F>,
+        ) -> Result<(), Error> {
+            let mut sha256 = config.clone();
+            let range = sha256.range().clone();
+            range.load_lookup_table(&mut layouter)?;
+            sha256.load(&mut layouter)?;
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "x509 test",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let ctx = &mut sha256.new_context(region);
+                    let gate = range.gate();
+                    let mut buffer = self.der.clone();
+                    buffer.resize(MAX_DER_LEN, 0);
+                    let der_bytes = buffer
+                        .iter()
+                        .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                        .collect_vec();
+                    let der_len =
+                        gate.load_witness(ctx, Value::known(F::from(self.der.len() as u64)));
+                    let fingerprint = self
+                        .fingerprint
+                        .iter()
+                        .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+                        .collect_vec();
+                    digest_certificate(ctx, &mut sha256, &der_bytes, &der_len, &fingerprint)?;
+                    range.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_certificate_fingerprint() {
+        let circuit = TestCircuit::<Fr> {
+            der: CERTIFICATE.to_vec(),
+            fingerprint: hex::decode(FINGERPRINT).unwrap(),
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn test_certificate_wrong_fingerprint() {
+        let mut fingerprint = hex::decode(FINGERPRINT).unwrap();
+        fingerprint[31] ^= 1;
+        let circuit = TestCircuit::<Fr> {
+            der: CERTIFICATE.to_vec(),
+            fingerprint,
+            _f: PhantomData,
+        };
+        let prover = MockProver::run(17, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_certificate_capacity() {
+        assert_eq!(certificate_capacity(0), 64);
+        // 55 bytes, the 0x80 byte and the 8 length bytes fill one block exactly; one more byte needs another block.
+        assert_eq!(certificate_capacity(55), 64);
+        assert_eq!(certificate_capacity(56), 128);
+        assert_eq!(num_certificate_blocks(119), 2);
+        assert_eq!(num_certificate_blocks(120), 3);
+        assert_eq!(certificate_capacity(CERTIFICATE.len()), 512);
+        assert_eq!(num_certificate_blocks(4096), 65);
+    }
+}